@@ -0,0 +1,18 @@
+extern crate moccasin_asn1;
+
+use std::env;
+use std::process;
+
+fn main() {
+	let args = env::args().skip(1).collect::<Vec<_>>();
+
+	if args.len() != 2 {
+		eprintln!("usage: moccasin-asn1 <module.asn> <output.rs>");
+		process::exit(2);
+	}
+
+	if let Err(why) = moccasin_asn1::compile_file(&args[0], &args[1]) {
+		eprintln!("{}: {}", args[0], why);
+		process::exit(1);
+	}
+}
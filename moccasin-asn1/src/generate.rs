@@ -0,0 +1,572 @@
+use std::collections::HashMap;
+
+use Error;
+use parser::{Module, Type, Component, Value, Class, Mode, TagDefault};
+
+/// The Rust type of a field or alias
+#[derive(Debug, Clone)]
+enum Ty {
+	/// A type of moccasin, and whether it borrows from the input
+	Builtin(&'static str, bool),
+	Named(String),
+	SequenceOf(Box<Ty>),
+	SetOf(Box<Ty>),
+}
+
+struct Field {
+	/// The identifier in the ASN.1 notation
+	name: String,
+	ty: Ty,
+	/// The class, number and whether the tag is explicit
+	tagging: Option<(Class, usize, bool)>,
+	/// The number of the universal tag replaced by an implicit tag
+	replaces: Option<usize>,
+	optional: bool,
+	default: Option<String>,
+}
+
+enum Kind {
+	Struct{fields: Vec<Field>, set: bool, extensible: bool},
+	Enum(Vec<Field>),
+	Alias(Ty),
+}
+
+struct Def {
+	name: String,
+	kind: Kind,
+}
+
+struct Generator<'a> {
+	assignments: HashMap<&'a str, &'a Type>,
+	defs: Vec<Def>,
+	consts: Vec<(String, i64)>,
+}
+
+/// Generates a type deriving `Decode` for every type assignment of the
+/// modules. Structured types nested within others are named after the
+/// components they appear in.
+pub fn generate(modules: &[Module]) -> Result<String, Error> {
+	let mut generator = Generator{
+		assignments: HashMap::new(),
+		defs: Vec::new(),
+		consts: Vec::new(),
+	};
+
+	for module in modules {
+		for assignment in &module.assignments {
+			if generator.assignments.insert(&assignment.name, &assignment.ty).is_some() {
+				return Err(Error::Unsupported(format!("duplicate type {} on line {}", assignment.name, assignment.line)));
+			}
+		}
+	}
+
+	for module in modules {
+		for assignment in &module.assignments {
+			try!(generator.define(camel(&assignment.name), &assignment.ty, module.tagging));
+		}
+	}
+
+	let mut names = HashMap::new();
+	for def in &generator.defs {
+		if names.insert(&def.name[..], ()).is_some() {
+			return Err(Error::Unsupported(format!("types named {} twice", def.name)));
+		}
+	}
+
+	let modules = modules.iter().map(|m| &m.name[..]).collect::<Vec<_>>();
+	Ok(generator.render(&modules.join(", ")))
+}
+
+impl<'a> Generator<'a> {
+	// Defines the type `name` for `ty`
+	fn define(&mut self, name: String, ty: &'a Type, tagging: TagDefault) -> Result<(), Error> {
+		let index = self.defs.len();
+
+		let kind = match *ty {
+			Type::Sequence(ref components, extensible) => Kind::Struct{
+				fields: try!(self.fields(&name, components, tagging)),
+				set: false,
+				extensible: extensible,
+			},
+			Type::Set(ref components, extensible) => Kind::Struct{
+				fields: try!(self.fields(&name, components, tagging)),
+				set: true,
+				extensible: extensible,
+			},
+			Type::Choice(ref components, _) => {
+				try!(self.check_choice(&name, ty, components, tagging));
+				Kind::Enum(try!(self.fields(&name, components, tagging)))
+			},
+			_ => Kind::Alias(try!(self.ty(&name, ty, tagging)))
+		};
+
+		// Nested types follow the type they appear in
+		self.defs.insert(index, Def{
+			name: name,
+			kind: kind,
+		});
+
+		Ok(())
+	}
+
+	fn fields(&mut self, parent: &str, components: &'a [Component], tagging: TagDefault) -> Result<Vec<Field>, Error> {
+		let automatic = is_automatic(components, tagging);
+
+		let mut fields = Vec::new();
+
+		for (i, component) in components.iter().enumerate() {
+			let context = format!("{}{}", parent, camel(&component.name));
+
+			let (tag, ty) = match component.ty {
+				Type::Tagged(class, number, mode, ref ty) => (Some((class, number, mode)), &**ty),
+				ref ty if automatic => (Some((Class::Context, i, None)), ty),
+				ref ty => (None, ty)
+			};
+
+			let attr = match tag {
+				Some((Class::Universal, _, _)) => {
+					return Err(Error::Unsupported(format!("UNIVERSAL tag of {}", context)));
+				},
+				// Tags of choices and open types are always explicit (31.2.7)
+				Some((class, number, mode)) => {
+					let explicit = match mode {
+						Some(Mode::Explicit) => true,
+						Some(Mode::Implicit) => false,
+						None => tagging == TagDefault::Explicit
+					};
+
+					Some((class, number, explicit || self.is_open(ty)))
+				},
+				// UTCTime and GeneralizedTime are both decoded into Time, so
+				// their own tags tell them apart
+				None => match *self.resolve(ty) {
+					Type::Time(ref name) => Some((Class::Universal, universal_number(name), false)),
+					_ => None
+				}
+			};
+
+			// Strings and times are decoded depending on the universal tag
+			// an implicit tag replaces
+			let replaces = match (attr, self.resolve(ty)) {
+				(Some((Class::Universal, _, _)), _) => None,
+				(Some((_, _, false)), &Type::String(ref name)) |
+				(Some((_, _, false)), &Type::Time(ref name)) => Some(universal_number(name)),
+				_ => None
+			};
+
+			let default = match component.default {
+				Some(ref value) => Some(try!(self.default(&context, ty, value))),
+				None => None
+			};
+
+			// Additions are absent from encodings of earlier versions
+			let optional = component.optional || (component.addition && default.is_none());
+
+			fields.push(Field{
+				name: component.name.clone(),
+				ty: try!(self.ty(&context, ty, tagging)),
+				tagging: attr,
+				replaces: replaces,
+				optional: optional,
+				default: default,
+			});
+		}
+
+		Ok(fields)
+	}
+
+	fn ty(&mut self, context: &str, ty: &'a Type, tagging: TagDefault) -> Result<Ty, Error> {
+		let ty = match *ty {
+			Type::Boolean => Ty::Builtin("::moccasin::types::Bool", false),
+			Type::Integer(ref numbers) => {
+				for &(ref name, number) in numbers {
+					self.consts.push((format!("{}_{}", upper(context), upper(name)), number));
+				}
+
+				// Integers without named numbers may be as large as serial numbers
+				match numbers.is_empty() {
+					true  => Ty::Builtin("::moccasin::types::BigInt", false),
+					false => Ty::Builtin("::moccasin::types::Int<i64>", false)
+				}
+			},
+			Type::BitString => Ty::Builtin("::moccasin::types::Bitstring<'a>", true),
+			Type::OctetString => Ty::Builtin("::moccasin::types::Octetstring<'a>", true),
+			Type::Null => Ty::Builtin("::moccasin::types::Null", false),
+			Type::ObjectIdentifier => Ty::Builtin("::moccasin::types::Oid", false),
+			Type::String(ref name) => match &name[..] {
+				"UTF8String" | "PrintableString" | "IA5String" | "VisibleString" |
+				"T61String" | "TeletexString" | "NumericString" | "GraphicString" |
+				"UniversalString" | "BMPString" => Ty::Builtin("::moccasin::types::String<'a>", true),
+				_ => return Err(Error::Unsupported(format!("{} in {}", name, context)))
+			},
+			Type::Time(_) => Ty::Builtin("::moccasin::types::Time", false),
+			Type::Any => Ty::Builtin("::moccasin::dsl::Any<'a>", true),
+			Type::Reference(ref name) => {
+				if !self.assignments.contains_key(&name[..]) {
+					return Err(Error::Unsupported(format!("unknown type {} in {}", name, context)));
+				}
+
+				Ty::Named(camel(name))
+			},
+			Type::Sequence(..) | Type::Set(..) | Type::Choice(..) => {
+				try!(self.define(context.to_string(), ty, tagging));
+				Ty::Named(context.to_string())
+			},
+			Type::SequenceOf(ref element) => Ty::SequenceOf(Box::new(try!(self.element(context, element, tagging)))),
+			Type::SetOf(ref element) => Ty::SetOf(Box::new(try!(self.element(context, element, tagging)))),
+			Type::Enumerated => return Err(Error::Unsupported(format!("ENUMERATED in {}", context))),
+			Type::Tagged(..) => return Err(Error::Unsupported(format!("tagged type {}", context)))
+		};
+
+		Ok(ty)
+	}
+
+	fn element(&mut self, context: &str, element: &'a Type, tagging: TagDefault) -> Result<Ty, Error> {
+		self.ty(&format!("{}Item", context), element, tagging)
+	}
+
+	// The type `ty` refers to, following references
+	fn resolve(&self, mut ty: &'a Type) -> &'a Type {
+		// References may be circular
+		for _ in 0..self.assignments.len() {
+			match *ty {
+				Type::Reference(ref name) => match self.assignments.get(&name[..]) {
+					Some(target) => ty = target,
+					None => break
+				},
+				_ => break
+			}
+		}
+
+		ty
+	}
+
+	// The alternatives of a CHOICE have to have distinct tags (29.2)
+	fn check_choice(&self, name: &str, ty: &'a Type, components: &'a [Component], tagging: TagDefault) -> Result<(), Error> {
+		if components.len() < 2 {
+			return Ok(());
+		}
+
+		let tags = match self.tags(ty, tagging, 0) {
+			Some(tags) => tags,
+			None => return Err(Error::Unsupported(format!("untagged open type in CHOICE {}", name)))
+		};
+
+		for (i, tag) in tags.iter().enumerate() {
+			if tags[..i].contains(tag) {
+				return Err(Error::Unsupported(format!("alternatives of CHOICE {} with the same tag", name)));
+			}
+		}
+
+		Ok(())
+	}
+
+	// The tags a value of `ty` may have, or None if it may have any tag
+	fn tags(&self, ty: &'a Type, tagging: TagDefault, nesting: usize) -> Option<Vec<(Class, usize)>> {
+		let universal = |number| Some(vec![(Class::Universal, number)]);
+
+		match *self.resolve(ty) {
+			Type::Boolean => universal(1),
+			Type::Integer(_) => universal(2),
+			Type::BitString => universal(3),
+			Type::OctetString => universal(4),
+			Type::Null => universal(5),
+			Type::ObjectIdentifier => universal(6),
+			Type::Enumerated => universal(10),
+			Type::String(ref name) | Type::Time(ref name) => universal(universal_number(name)),
+			Type::Sequence(..) | Type::SequenceOf(..) => universal(16),
+			Type::Set(..) | Type::SetOf(..) => universal(17),
+			Type::Tagged(class, number, _, _) => Some(vec![(class, number)]),
+			// Choices may refer to themselves
+			Type::Choice(ref components, _) if nesting < self.assignments.len() => {
+				let mut tags = Vec::new();
+
+				for (i, component) in components.iter().enumerate() {
+					if is_automatic(components, tagging) {
+						tags.push((Class::Context, i));
+						continue;
+					}
+
+					match self.tags(&component.ty, tagging, nesting + 1) {
+						Some(alternative) => tags.extend(alternative),
+						None => return None
+					}
+				}
+
+				Some(tags)
+			},
+			_ => None
+		}
+	}
+
+	fn is_open(&self, ty: &'a Type) -> bool {
+		match *self.resolve(ty) {
+			Type::Choice(..) | Type::Any => true,
+			_ => false
+		}
+	}
+
+	fn default(&self, context: &str, ty: &'a Type, value: &Value) -> Result<String, Error> {
+		match (self.resolve(ty), value) {
+			(&Type::Boolean, &Value::Bool(b)) => Ok(format!("::moccasin::types::Bool({})", b)),
+			(&Type::Integer(ref numbers), &Value::Int(n)) if numbers.is_empty() => Ok(format!("::moccasin::types::BigInt::from({}i64)", n)),
+			(&Type::Integer(_), &Value::Int(n)) => Ok(format!("::moccasin::types::Int({})", n)),
+			(&Type::Integer(ref numbers), &Value::Name(ref name)) => {
+				match numbers.iter().find(|&&(ref n, _)| n == name) {
+					Some(&(_, number)) => Ok(format!("::moccasin::types::Int({})", number)),
+					None => Err(Error::Unsupported(format!("unknown DEFAULT {} of {}", name, context)))
+				}
+			},
+			_ => Err(Error::Unsupported(format!("DEFAULT of {}", context)))
+		}
+	}
+
+	fn render(&self, modules: &str) -> String {
+		let borrows = self.borrows();
+		let mut out = format!("// Generated by moccasin-asn1 from {}\n", modules);
+
+		if !self.consts.is_empty() {
+			out.push('\n');
+		}
+
+		for &(ref name, number) in &self.consts {
+			out.push_str(&format!("pub const {}: i64 = {};\n", name, number));
+		}
+
+		for def in &self.defs {
+			let lifetime = match borrows[&def.name] {
+				true  => "<'a>",
+				false => ""
+			};
+
+			out.push('\n');
+
+			match def.kind {
+				Kind::Struct{ref fields, set, extensible} => {
+					out.push_str("#[derive(Debug, PartialEq, Decode)]\n");
+
+					let attrs = [(set, "set"), (extensible, "extensible")].iter()
+						.filter(|&&(enabled, _)| enabled)
+						.map(|&(_, attr)| attr)
+						.collect::<Vec<_>>();
+
+					if !attrs.is_empty() {
+						out.push_str(&format!("#[asn1({})]\n", attrs.join(", ")));
+					}
+
+					out.push_str(&format!("pub struct {}{} {{\n", def.name, lifetime));
+
+					for field in fields {
+						let mut ty = render_ty(&field.ty, &borrows);
+
+						if field.optional {
+							ty = format!("::std::option::Option<{}>", ty);
+						}
+
+						out.push_str(&attributes(field));
+						out.push_str(&format!("\tpub {}: {},\n", snake(&field.name), ty));
+					}
+
+					out.push_str("}\n");
+				},
+				Kind::Enum(ref variants) => {
+					out.push_str("#[derive(Debug, PartialEq, Decode)]\n");
+					out.push_str(&format!("pub enum {}{} {{\n", def.name, lifetime));
+
+					for variant in variants {
+						out.push_str(&attributes(variant));
+						out.push_str(&format!("\t{}({}),\n", camel(&variant.name), render_ty(&variant.ty, &borrows)));
+					}
+
+					out.push_str("}\n");
+				},
+				Kind::Alias(ref ty) => {
+					out.push_str(&format!("pub type {}{} = {};\n", def.name, lifetime, render_ty(ty, &borrows)));
+				}
+			}
+		}
+
+		out
+	}
+
+	// Whether each type borrows from the input, and needs a lifetime
+	fn borrows(&self) -> HashMap<String, bool> {
+		let mut borrows = self.defs.iter().map(|def| (def.name.clone(), false)).collect::<HashMap<_, _>>();
+
+		loop {
+			let mut changed = false;
+
+			for def in &self.defs {
+				let borrowed = match def.kind {
+					Kind::Struct{ref fields, ..} | Kind::Enum(ref fields) => {
+						fields.iter().any(|field| ty_borrows(&field.ty, &borrows))
+					},
+					Kind::Alias(ref ty) => ty_borrows(ty, &borrows)
+				};
+
+				if borrowed && !borrows[&def.name] {
+					borrows.insert(def.name.clone(), true);
+					changed = true;
+				}
+			}
+
+			if !changed {
+				return borrows;
+			}
+		}
+	}
+}
+
+fn ty_borrows(ty: &Ty, borrows: &HashMap<String, bool>) -> bool {
+	match *ty {
+		Ty::Builtin(_, borrowed) => borrowed,
+		Ty::Named(ref name) => borrows.get(name).cloned().unwrap_or(false),
+		Ty::SequenceOf(ref element) | Ty::SetOf(ref element) => ty_borrows(element, borrows)
+	}
+}
+
+fn render_ty(ty: &Ty, borrows: &HashMap<String, bool>) -> String {
+	match *ty {
+		Ty::Builtin(path, _) => path.to_string(),
+		Ty::Named(ref name) if ty_borrows(ty, borrows) => format!("{}<'a>", name),
+		Ty::Named(ref name) => name.clone(),
+		Ty::SequenceOf(ref element) => format!("::moccasin::dsl::SequenceOf<{}>", render_ty(element, borrows)),
+		Ty::SetOf(ref element) => format!("::moccasin::dsl::SetOf<{}>", render_ty(element, borrows))
+	}
+}
+
+// Whether the components are numbered, as none of them is tagged (25.3)
+fn is_automatic(components: &[Component], tagging: TagDefault) -> bool {
+	tagging == TagDefault::Automatic &&
+		components.iter().all(|c| match c.ty { Type::Tagged(..) => false, _ => true })
+}
+
+// The number of the universal tag of a string or time type
+fn universal_number(name: &str) -> usize {
+	match name {
+		"UTF8String"                     => 12,
+		"NumericString"                  => 18,
+		"PrintableString"                => 19,
+		"T61String" | "TeletexString"    => 20,
+		"VideotexString"                 => 21,
+		"IA5String"                      => 22,
+		"UTCTime"                        => 23,
+		"GeneralizedTime"                => 24,
+		"GraphicString"                  => 25,
+		"VisibleString"                  => 26,
+		"GeneralString"                  => 27,
+		"UniversalString"                => 28,
+		// BMPString
+		_                                => 30
+	}
+}
+
+// The `#[asn1(...)]` attribute of a field or variant
+fn attributes(field: &Field) -> String {
+	let mut attrs = Vec::new();
+
+	if let Some((class, number, explicit)) = field.tagging {
+		attrs.push(format!("{} = {}", if explicit { "explicit" } else { "implicit" }, number));
+
+		match class {
+			Class::Application => attrs.push("application".to_string()),
+			Class::Private => attrs.push("private".to_string()),
+			Class::Universal => attrs.push("universal".to_string()),
+			Class::Context => ()
+		}
+	}
+
+	if let Some(number) = field.replaces {
+		attrs.push(format!("replaces = {}", number));
+	}
+
+	if let Some(ref default) = field.default {
+		attrs.push(format!("default = \"{}\"", default));
+	}
+
+	match attrs.is_empty() {
+		true  => String::new(),
+		false => format!("\t#[asn1({})]\n", attrs.join(", "))
+	}
+}
+
+const KEYWORDS: [&'static str; 38] = [
+	"abstract", "as", "box", "break", "const", "continue", "crate", "do", "else", "enum", "extern",
+	"false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+	"priv", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+	"unsafe", "use", "where",
+];
+
+// A type or variant name for an ASN.1 reference or identifier
+fn camel(name: &str) -> String {
+	name.split('-').map(|part| {
+		let mut chars = part.chars();
+
+		match chars.next() {
+			Some(first) => first.to_uppercase().chain(chars).collect(),
+			None => String::new()
+		}
+	}).collect()
+}
+
+// A field name for an ASN.1 identifier
+fn snake(name: &str) -> String {
+	let mut out = words(name);
+
+	if KEYWORDS.contains(&&out[..]) {
+		out.push('_');
+	}
+
+	out
+}
+
+fn upper(name: &str) -> String {
+	words(name).to_uppercase()
+}
+
+// Separates the words of an identifier with underscores
+fn words(name: &str) -> String {
+	let chars = name.chars().collect::<Vec<_>>();
+	let mut out = String::new();
+
+	for (i, &c) in chars.iter().enumerate() {
+		if c == '-' {
+			out.push('_');
+			continue;
+		}
+
+		if c.is_uppercase() && i > 0 {
+			let prev = chars[i - 1];
+			let next_lower = chars.get(i + 1).map_or(false, |n| n.is_lowercase());
+
+			if prev.is_lowercase() || prev.is_digit(10) || (prev.is_uppercase() && next_lower) {
+				out.push('_');
+			}
+		}
+
+		out.extend(c.to_lowercase());
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{camel, snake, upper};
+
+	#[test]
+	fn names() {
+		assert_eq!(camel("TBSCertificate"), "TBSCertificate");
+		assert_eq!(camel("rfc822Name"), "Rfc822Name");
+		assert_eq!(camel("Extension-Request"), "ExtensionRequest");
+
+		assert_eq!(snake("subjectPublicKeyInfo"), "subject_public_key_info");
+		assert_eq!(snake("extnID"), "extn_id");
+		assert_eq!(snake("TBSCertificate"), "tbs_certificate");
+		assert_eq!(snake("rfc822Name"), "rfc822_name");
+		assert_eq!(snake("type"), "type_");
+		assert_eq!(snake("key-usage"), "key_usage");
+
+		assert_eq!(upper("TBSCertificateVersion"), "TBS_CERTIFICATE_VERSION");
+	}
+}
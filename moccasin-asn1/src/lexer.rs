@@ -0,0 +1,185 @@
+use Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+	/// Type references, identifiers and keywords, which may contain hyphens
+	Word(String),
+	Number(i64),
+	Symbol(&'static str),
+	/// Character, binary and hexadecimal strings, which only appear in
+	/// values and are not interpreted
+	Text,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lexeme {
+	pub token: Token,
+	pub line: usize,
+}
+
+// Longer symbols come first, so that they are preferred over their prefixes
+const SYMBOLS: [&'static str; 20] = [
+	"::=", "...", "..", "[[", "]]",
+	"{", "}", "(", ")", "[", "]", ",", ";", "|", "^", "@", ".", "!", "<", ":",
+];
+
+/// Splits ASN.1 notation into tokens, dropping comments (X.680 12).
+pub fn tokenize(source: &str) -> Result<Vec<Lexeme>, Error> {
+	let chars = source.chars().collect::<Vec<_>>();
+	let mut tokens = Vec::new();
+	let mut line = 1;
+	let mut i = 0;
+
+	let syntax = |line: usize, message: &str| Error::Syntax{line: line, message: message.to_string()};
+
+	while i < chars.len() {
+		let c = chars[i];
+		let next = chars.get(i + 1).cloned();
+
+		if c == '\n' {
+			line += 1;
+			i += 1;
+		} else if c.is_whitespace() {
+			i += 1;
+		} else if c == '-' && next == Some('-') {
+			// Comments end with another pair of hyphens or the line
+			i += 2;
+			while i < chars.len() && chars[i] != '\n' {
+				if chars[i] == '-' && chars.get(i + 1) == Some(&'-') {
+					i += 2;
+					break;
+				}
+				i += 1;
+			}
+		} else if c == '/' && next == Some('*') {
+			let mut nesting = 0;
+			loop {
+				match (chars.get(i), chars.get(i + 1)) {
+					(Some(&'/'), Some(&'*')) => { nesting += 1; i += 2; },
+					(Some(&'*'), Some(&'/')) => { nesting -= 1; i += 2; },
+					(Some(&'\n'), _) => { line += 1; i += 1; },
+					(Some(_), _) => i += 1,
+					(None, _) => return Err(syntax(line, "unterminated comment"))
+				}
+
+				if nesting == 0 {
+					break;
+				}
+			}
+		} else if c == '"' || c == '\'' {
+			// Quotes are escaped by doubling them
+			i += 1;
+			loop {
+				match chars.get(i) {
+					Some(&q) if q == c && chars.get(i + 1) == Some(&c) => i += 2,
+					Some(&q) if q == c => break,
+					Some(&'\n') => { line += 1; i += 1; },
+					Some(_) => i += 1,
+					None => return Err(syntax(line, "unterminated string"))
+				}
+			}
+
+			// Skip the B or H suffix of binary and hexadecimal strings
+			i += 1;
+			if c == '\'' && i < chars.len() && chars[i].is_alphabetic() {
+				i += 1;
+			}
+
+			tokens.push(Lexeme{token: Token::Text, line: line});
+		} else if c.is_digit(10) || (c == '-' && next.map_or(false, |n| n.is_digit(10))) {
+			let start = i;
+			i += 1;
+			while i < chars.len() && chars[i].is_digit(10) {
+				i += 1;
+			}
+
+			let text = chars[start..i].iter().cloned().collect::<String>();
+			let number = try!(text.parse().map_err(|_| syntax(line, "number out of range")));
+			tokens.push(Lexeme{token: Token::Number(number), line: line});
+		} else if c.is_alphabetic() || c == '&' {
+			// Hyphens may not be doubled or end a word (12.2)
+			let start = i;
+			i += 1;
+			while i < chars.len() {
+				let hyphen = chars[i] == '-' && chars.get(i + 1).map_or(false, |n| n.is_alphanumeric());
+				if chars[i].is_alphanumeric() || hyphen {
+					i += 1;
+				} else {
+					break;
+				}
+			}
+
+			let word = chars[start..i].iter().cloned().collect();
+			tokens.push(Lexeme{token: Token::Word(word), line: line});
+		} else {
+			let rest = chars[i..].iter().take(3).cloned().collect::<String>();
+
+			match SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+				Some(symbol) => {
+					tokens.push(Lexeme{token: Token::Symbol(symbol), line: line});
+					i += symbol.len();
+				},
+				None => return Err(syntax(line, &format!("unexpected character '{}'", c)))
+			}
+		}
+	}
+
+	Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{tokenize, Token};
+	use super::Token::*;
+
+	fn tokens(source: &str) -> Vec<Token> {
+		tokenize(source).unwrap().into_iter().map(|l| l.token).collect()
+	}
+
+	#[test]
+	fn words() {
+		assert_eq!(tokens("id-ce-keyUsage OBJECT IDENTIFIER ::= { id-ce 15 }"), vec![
+			Word("id-ce-keyUsage".to_string()),
+			Word("OBJECT".to_string()),
+			Word("IDENTIFIER".to_string()),
+			Symbol("::="),
+			Symbol("{"),
+			Word("id-ce".to_string()),
+			Number(15),
+			Symbol("}"),
+		]);
+
+		assert_eq!(tokens("SIZE (1..MAX) -1 [[ ... ]]"), vec![
+			Word("SIZE".to_string()),
+			Symbol("("),
+			Number(1),
+			Symbol(".."),
+			Word("MAX".to_string()),
+			Symbol(")"),
+			Number(-1),
+			Symbol("[["),
+			Symbol("..."),
+			Symbol("]]"),
+		]);
+	}
+
+	#[test]
+	fn comments() {
+		let lexemes = tokenize("a -- one -- b -- two\nc /* three /* four */\n*/ d").unwrap();
+
+		assert_eq!(lexemes.iter().map(|l| l.token.clone()).collect::<Vec<_>>(), vec![
+			Word("a".to_string()),
+			Word("b".to_string()),
+			Word("c".to_string()),
+			Word("d".to_string()),
+		]);
+		assert_eq!(lexemes[3].line, 3);
+	}
+
+	#[test]
+	fn strings() {
+		assert_eq!(tokens("\"say \"\"hi\"\"\" '0101'B 'FF'H"), vec![Text, Text, Text]);
+		assert!(tokenize("\"open").is_err());
+		assert!(tokenize("a # b").is_err());
+	}
+}
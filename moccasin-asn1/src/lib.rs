@@ -0,0 +1,83 @@
+//! Compiles ASN.1 modules (X.680) into Rust types, which are decoded by
+//! `#[derive(Decode)]` from moccasin-derive.
+//!
+//! SEQUENCE and SET types become structs, CHOICE types become enums and
+//! other types become aliases of the types of moccasin. SEQUENCE OF and SET
+//! OF become `dsl::SequenceOf` and `dsl::SetOf`, ANY becomes `dsl::Any`.
+//! INTEGER becomes `Int<i64>` if it has named numbers, which become
+//! constants, and `BigInt` otherwise. Constraints, value assignments and
+//! imports are ignored, so imported types have to be compiled along with the
+//! module.
+//!
+//! The output is meant to be included from a build script:
+//!
+//! ```ignore
+//! let out = Path::new(&env::var("OUT_DIR").unwrap()).join("pkix.rs");
+//! moccasin_asn1::compile_file("src/pkix.asn", out).unwrap();
+//! ```
+//!
+//! ```ignore
+//! #[macro_use]
+//! extern crate moccasin_derive;
+//! extern crate moccasin;
+//!
+//! mod pkix {
+//!     include!(concat!(env!("OUT_DIR"), "/pkix.rs"));
+//! }
+//! ```
+
+mod lexer;
+mod parser;
+mod generate;
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+	Syntax{line: usize, message: String},
+	/// Notation which is valid ASN.1, but cannot be compiled
+	Unsupported(String),
+	Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+	fn from(why: io::Error) -> Error {
+		Error::Io(why)
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::Syntax{line, ref message} => write!(f, "line {}: {}", line, message),
+			Error::Unsupported(ref what) => write!(f, "unsupported: {}", what),
+			Error::Io(ref why) => write!(f, "{}", why)
+		}
+	}
+}
+
+impl error::Error for Error {}
+
+/// Returns the Rust source of the types of the modules in `source`.
+pub fn compile(source: &str) -> Result<String, Error> {
+	let tokens = try!(lexer::tokenize(source));
+	let modules = try!(parser::parse(&tokens));
+	generate::generate(&modules)
+}
+
+/// Compiles the modules in the file `input` into the file `output`.
+pub fn compile_file<P, Q>(input: P, output: Q) -> Result<(), Error>
+	where P: AsRef<Path>, Q: AsRef<Path>
+{
+	let mut source = String::new();
+	try!(try!(File::open(input)).read_to_string(&mut source));
+
+	let code = try!(compile(&source));
+	try!(try!(File::create(output)).write_all(code.as_bytes()));
+
+	Ok(())
+}
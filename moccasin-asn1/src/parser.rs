@@ -0,0 +1,655 @@
+use Error;
+use lexer::{Lexeme, Token};
+
+/// The tagging of components without IMPLICIT or EXPLICIT (X.680 13.3)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TagDefault {
+	Explicit,
+	Implicit,
+	Automatic,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Class {
+	Universal,
+	Application,
+	Context,
+	Private,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+	Explicit,
+	Implicit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+	pub name: String,
+	pub tagging: TagDefault,
+	pub assignments: Vec<Assignment>,
+}
+
+/// A type assignment. Value assignments are parsed but not kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+	pub name: String,
+	pub ty: Type,
+	pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+	Boolean,
+	/// INTEGER with its named numbers
+	Integer(Vec<(String, i64)>),
+	Enumerated,
+	BitString,
+	OctetString,
+	Null,
+	ObjectIdentifier,
+	/// The character string types, by name
+	String(String),
+	Time(String),
+	Any,
+	Reference(String),
+	Sequence(Vec<Component>, bool),
+	Set(Vec<Component>, bool),
+	Choice(Vec<Component>, bool),
+	SequenceOf(Box<Type>),
+	SetOf(Box<Type>),
+	Tagged(Class, usize, Option<Mode>, Box<Type>),
+}
+
+/// A component of a SEQUENCE or SET, or an alternative of a CHOICE
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+	pub name: String,
+	pub ty: Type,
+	pub optional: bool,
+	pub default: Option<Value>,
+	/// Whether the component follows the extension marker
+	pub addition: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Bool(bool),
+	Int(i64),
+	/// A named number or another value reference
+	Name(String),
+	/// Any other value, which is not interpreted
+	Other,
+}
+
+const STRINGS: [&'static str; 12] = [
+	"BMPString", "GeneralString", "GraphicString", "IA5String", "NumericString", "PrintableString",
+	"T61String", "TeletexString", "UniversalString", "UTF8String", "VideotexString", "VisibleString",
+];
+
+struct Parser<'a> {
+	tokens: &'a [Lexeme],
+	pos: usize,
+}
+
+/// Parses the modules of a specification (X.680 13).
+pub fn parse(tokens: &[Lexeme]) -> Result<Vec<Module>, Error> {
+	let mut parser = Parser{tokens: tokens, pos: 0};
+	let mut modules = Vec::new();
+
+	while parser.peek().is_some() {
+		modules.push(try!(parser.module()));
+	}
+
+	Ok(modules)
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<&'a Token> {
+		self.tokens.get(self.pos).map(|l| &l.token)
+	}
+
+	fn peek_at(&self, n: usize) -> Option<&'a Token> {
+		self.tokens.get(self.pos + n).map(|l| &l.token)
+	}
+
+	fn line(&self) -> usize {
+		match self.tokens.get(self.pos).or(self.tokens.last()) {
+			Some(lexeme) => lexeme.line,
+			None => 1
+		}
+	}
+
+	fn error<T>(&self, message: &str) -> Result<T, Error> {
+		let found = match self.peek() {
+			Some(&Token::Word(ref word)) => format!("'{}'", word),
+			Some(&Token::Number(n)) => format!("'{}'", n),
+			Some(&Token::Symbol(symbol)) => format!("'{}'", symbol),
+			Some(&Token::Text) => "a string".to_string(),
+			None => "the end of input".to_string()
+		};
+
+		Err(Error::Syntax{line: self.line(), message: format!("expected {}, found {}", message, found)})
+	}
+
+	fn is_symbol(&self, symbol: &str) -> bool {
+		match self.peek() {
+			Some(&Token::Symbol(s)) => s == symbol,
+			_ => false
+		}
+	}
+
+	fn is_word(&self, word: &str) -> bool {
+		match self.peek() {
+			Some(&Token::Word(ref w)) => w == word,
+			_ => false
+		}
+	}
+
+	fn eat_symbol(&mut self, symbol: &str) -> bool {
+		let found = self.is_symbol(symbol);
+		if found {
+			self.pos += 1;
+		}
+		found
+	}
+
+	fn eat_word(&mut self, word: &str) -> bool {
+		let found = self.is_word(word);
+		if found {
+			self.pos += 1;
+		}
+		found
+	}
+
+	fn symbol(&mut self, symbol: &str) -> Result<(), Error> {
+		match self.eat_symbol(symbol) {
+			true  => Ok(()),
+			false => self.error(&format!("'{}'", symbol))
+		}
+	}
+
+	fn keyword(&mut self, word: &str) -> Result<(), Error> {
+		match self.eat_word(word) {
+			true  => Ok(()),
+			false => self.error(word)
+		}
+	}
+
+	fn word(&mut self, what: &str) -> Result<String, Error> {
+		match self.peek() {
+			Some(&Token::Word(ref word)) => {
+				self.pos += 1;
+				Ok(word.clone())
+			},
+			_ => self.error(what)
+		}
+	}
+
+	fn number(&mut self) -> Result<i64, Error> {
+		match self.peek() {
+			Some(&Token::Number(n)) => {
+				self.pos += 1;
+				Ok(n)
+			},
+			_ => self.error("a number")
+		}
+	}
+
+	// Skips a group opened by the current symbol, including nested groups
+	fn skip_group(&mut self) -> Result<(), Error> {
+		let mut nesting = 0;
+
+		loop {
+			match self.peek() {
+				Some(&Token::Symbol("{")) | Some(&Token::Symbol("(")) => nesting += 1,
+				Some(&Token::Symbol("}")) | Some(&Token::Symbol(")")) => nesting -= 1,
+				Some(_) => (),
+				None => return self.error("the end of a group")
+			}
+
+			self.pos += 1;
+
+			if nesting == 0 {
+				return Ok(());
+			}
+		}
+	}
+
+	// 13.1
+	fn module(&mut self) -> Result<Module, Error> {
+		let name = try!(self.word("a module name"));
+
+		if self.is_symbol("{") {
+			try!(self.skip_group());
+		}
+
+		try!(self.keyword("DEFINITIONS"));
+
+		let tagging = if self.eat_word("EXPLICIT") {
+			TagDefault::Explicit
+		} else if self.eat_word("IMPLICIT") {
+			TagDefault::Implicit
+		} else if self.eat_word("AUTOMATIC") {
+			TagDefault::Automatic
+		} else {
+			TagDefault::Explicit
+		};
+
+		if tagging != TagDefault::Explicit || self.is_word("TAGS") {
+			try!(self.keyword("TAGS"));
+		}
+
+		if self.eat_word("EXTENSIBILITY") {
+			try!(self.keyword("IMPLIED"));
+		}
+
+		try!(self.symbol("::="));
+		try!(self.keyword("BEGIN"));
+
+		// Imported and exported symbols are not checked
+		for section in &["EXPORTS", "IMPORTS"] {
+			if self.eat_word(section) {
+				while !self.eat_symbol(";") {
+					if self.peek().is_none() {
+						return self.error("';'");
+					}
+					self.pos += 1;
+				}
+			}
+		}
+
+		let mut assignments = Vec::new();
+
+		while !self.eat_word("END") {
+			if let Some(assignment) = try!(self.assignment()) {
+				assignments.push(assignment);
+			}
+		}
+
+		Ok(Module{
+			name: name,
+			tagging: tagging,
+			assignments: assignments,
+		})
+	}
+
+	// 15.1 and 16.2
+	fn assignment(&mut self) -> Result<Option<Assignment>, Error> {
+		let line = self.line();
+		let name = try!(self.word("an assignment"));
+
+		if self.is_symbol("{") {
+			return Err(Error::Unsupported(format!("parameterized assignment {} on line {}", name, line)));
+		}
+
+		if is_reference(&name) {
+			try!(self.symbol("::="));
+			let ty = try!(self.ty());
+
+			return Ok(Some(Assignment{
+				name: name,
+				ty: ty,
+				line: line,
+			}));
+		}
+
+		try!(self.ty());
+		try!(self.symbol("::="));
+		try!(self.value());
+
+		Ok(None)
+	}
+
+	// 17.1
+	fn ty(&mut self) -> Result<Type, Error> {
+		let ty = try!(self.unconstrained());
+
+		// Constraints only restrict values (49.6)
+		while self.is_symbol("(") {
+			try!(self.skip_group());
+		}
+
+		Ok(ty)
+	}
+
+	fn unconstrained(&mut self) -> Result<Type, Error> {
+		if self.is_symbol("[") {
+			return self.tagged();
+		}
+
+		let word = try!(self.word("a type"));
+
+		let ty = match &word[..] {
+			"BOOLEAN" => Type::Boolean,
+			"INTEGER" => Type::Integer(try!(self.named_numbers())),
+			"ENUMERATED" => {
+				try!(self.skip_group());
+				Type::Enumerated
+			},
+			"BIT" => {
+				try!(self.keyword("STRING"));
+				try!(self.named_numbers());
+				Type::BitString
+			},
+			"OCTET" => {
+				try!(self.keyword("STRING"));
+				Type::OctetString
+			},
+			"NULL" => Type::Null,
+			"OBJECT" => {
+				try!(self.keyword("IDENTIFIER"));
+				Type::ObjectIdentifier
+			},
+			"ANY" => {
+				if self.eat_word("DEFINED") {
+					try!(self.keyword("BY"));
+					try!(self.word("an identifier"));
+				}
+				Type::Any
+			},
+			"UTCTime" | "GeneralizedTime" => Type::Time(word),
+			"SEQUENCE" | "SET" => {
+				// Size constraints, as in SIZE (1..MAX) or (SIZE (1..MAX))
+				if self.eat_word("SIZE") || self.is_symbol("(") {
+					try!(self.skip_group());
+				}
+
+				if self.eat_word("OF") {
+					// The element may be named (25.1)
+					if let (Some(&Token::Word(ref w)), Some(&Token::Word(_))) = (self.peek(), self.peek_at(1)) {
+						if !is_reference(w) {
+							self.pos += 1;
+						}
+					}
+
+					let element = Box::new(try!(self.ty()));
+
+					match &word[..] {
+						"SEQUENCE" => Type::SequenceOf(element),
+						_          => Type::SetOf(element),
+					}
+				} else {
+					let (components, extensible) = try!(self.components());
+
+					match &word[..] {
+						"SEQUENCE" => Type::Sequence(components, extensible),
+						_          => Type::Set(components, extensible),
+					}
+				}
+			},
+			"CHOICE" => {
+				let (components, extensible) = try!(self.components());
+				Type::Choice(components, extensible)
+			},
+			_ if STRINGS.contains(&&word[..]) => Type::String(word),
+			_ if is_reference(&word) => {
+				// External references name their module
+				if self.eat_symbol(".") {
+					Type::Reference(try!(self.word("a type reference")))
+				} else {
+					Type::Reference(word)
+				}
+			},
+			_ => {
+				self.pos -= 1;
+				return self.error("a type");
+			}
+		};
+
+		Ok(ty)
+	}
+
+	// 31.2
+	fn tagged(&mut self) -> Result<Type, Error> {
+		try!(self.symbol("["));
+
+		let class = if self.eat_word("UNIVERSAL") {
+			Class::Universal
+		} else if self.eat_word("APPLICATION") {
+			Class::Application
+		} else if self.eat_word("PRIVATE") {
+			Class::Private
+		} else {
+			Class::Context
+		};
+
+		let number = try!(self.number());
+		try!(self.symbol("]"));
+
+		if number < 0 {
+			return Err(Error::Syntax{line: self.line(), message: "negative tag number".to_string()});
+		}
+
+		let mode = if self.eat_word("IMPLICIT") {
+			Some(Mode::Implicit)
+		} else if self.eat_word("EXPLICIT") {
+			Some(Mode::Explicit)
+		} else {
+			None
+		};
+
+		let ty = try!(self.ty());
+		Ok(Type::Tagged(class, number as usize, mode, Box::new(ty)))
+	}
+
+	// 19.1, named bits of 22.1
+	fn named_numbers(&mut self) -> Result<Vec<(String, i64)>, Error> {
+		let mut numbers = Vec::new();
+
+		if !self.eat_symbol("{") {
+			return Ok(numbers);
+		}
+
+		loop {
+			let name = try!(self.word("a named number"));
+			try!(self.symbol("("));
+			let number = try!(self.number());
+			try!(self.symbol(")"));
+
+			numbers.push((name, number));
+
+			if !self.eat_symbol(",") {
+				break;
+			}
+		}
+
+		try!(self.symbol("}"));
+		Ok(numbers)
+	}
+
+	// 25.1, 27.1 and 29.1. Components following the extension marker are
+	// additions, up to a second marker.
+	fn components(&mut self) -> Result<(Vec<Component>, bool), Error> {
+		try!(self.symbol("{"));
+
+		let mut components = Vec::new();
+		let mut extensible = false;
+		let mut addition = false;
+
+		if self.eat_symbol("}") {
+			return Ok((components, extensible));
+		}
+
+		loop {
+			if self.eat_symbol("...") {
+				extensible = true;
+				addition = !addition;
+
+				// Exception specifications (49.4)
+				if self.eat_symbol("!") {
+					try!(self.value());
+				}
+			} else if self.eat_symbol("[[") {
+				// Version numbers of addition groups
+				if let Some(&Token::Number(_)) = self.peek() {
+					self.pos += 1;
+					try!(self.symbol(":"));
+				}
+
+				loop {
+					components.push(try!(self.component(true)));
+
+					if !self.eat_symbol(",") {
+						break;
+					}
+				}
+
+				try!(self.symbol("]]"));
+			} else if self.is_word("COMPONENTS") {
+				return Err(Error::Unsupported(format!("COMPONENTS OF on line {}", self.line())));
+			} else {
+				components.push(try!(self.component(addition)));
+			}
+
+			if !self.eat_symbol(",") {
+				break;
+			}
+		}
+
+		try!(self.symbol("}"));
+		Ok((components, extensible))
+	}
+
+	fn component(&mut self, addition: bool) -> Result<Component, Error> {
+		let name = try!(self.word("a component name"));
+		let ty = try!(self.ty());
+
+		let mut optional = false;
+		let mut default = None;
+
+		if self.eat_word("OPTIONAL") {
+			optional = true;
+		} else if self.eat_word("DEFAULT") {
+			default = Some(try!(self.value()));
+		}
+
+		Ok(Component{
+			name: name,
+			ty: ty,
+			optional: optional,
+			default: default,
+			addition: addition,
+		})
+	}
+
+	// 17.7
+	fn value(&mut self) -> Result<Value, Error> {
+		let value = match self.peek() {
+			Some(&Token::Word(ref word)) if word == "TRUE" => Value::Bool(true),
+			Some(&Token::Word(ref word)) if word == "FALSE" => Value::Bool(false),
+			Some(&Token::Word(ref word)) => Value::Name(word.clone()),
+			Some(&Token::Number(n)) => Value::Int(n),
+			Some(&Token::Text) => Value::Other,
+			Some(&Token::Symbol("{")) => {
+				try!(self.skip_group());
+				return Ok(Value::Other);
+			},
+			_ => return self.error("a value")
+		};
+
+		self.pos += 1;
+		Ok(value)
+	}
+}
+
+// Type references start with an upper case letter, identifiers and value
+// references with a lower case one (12.2 and 12.3)
+fn is_reference(word: &str) -> bool {
+	word.chars().next().map_or(false, |c| c.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+	use lexer::tokenize;
+	use super::*;
+
+	fn module(body: &str) -> Module {
+		let source = format!("Test DEFINITIONS IMPLICIT TAGS ::= BEGIN {} END", body);
+		parse(&tokenize(&source).unwrap()).unwrap().remove(0)
+	}
+
+	#[test]
+	fn header() {
+		let source = "PKIX1Explicit88 { iso(1) identified-organization(3) 0 } DEFINITIONS ::= BEGIN \
+			IMPORTS Name FROM Other { 1 2 }; END \
+			Other DEFINITIONS AUTOMATIC TAGS ::= BEGIN END";
+		let modules = parse(&tokenize(source).unwrap()).unwrap();
+
+		assert_eq!(modules.len(), 2);
+		assert_eq!(modules[0].name, "PKIX1Explicit88");
+		assert_eq!(modules[0].tagging, TagDefault::Explicit);
+		assert_eq!(modules[1].tagging, TagDefault::Automatic);
+	}
+
+	#[test]
+	fn types() {
+		let m = module("
+			id-ce OBJECT IDENTIFIER ::= { joint-iso-ccitt(2) ds(5) 29 }
+			Version ::= INTEGER { v1(0), v2(1), v3(2) }
+			Names ::= SEQUENCE SIZE (1..MAX) OF name GeneralName
+			Extension ::= SEQUENCE {
+				extnID OBJECT IDENTIFIER,
+				critical BOOLEAN DEFAULT FALSE,
+				extnValue OCTET STRING (CONTAINING Foo)
+			}
+			GeneralName ::= CHOICE {
+				rfc822Name [1] IA5String,
+				directoryName [4] EXPLICIT Name,
+				...,
+				other [APPLICATION 9] ANY DEFINED BY id
+			}
+		");
+
+		assert_eq!(m.assignments.len(), 4);
+		assert_eq!(m.assignments[0].ty, Type::Integer(vec![("v1".to_string(), 0), ("v2".to_string(), 1), ("v3".to_string(), 2)]));
+		assert_eq!(m.assignments[1].ty, Type::SequenceOf(Box::new(Type::Reference("GeneralName".to_string()))));
+
+		match m.assignments[2].ty {
+			Type::Sequence(ref components, false) => {
+				assert_eq!(components[1].default, Some(Value::Bool(false)));
+				assert_eq!(components[2].ty, Type::OctetString);
+			},
+			ref ty => panic!("{:?}", ty)
+		}
+
+		match m.assignments[3].ty {
+			Type::Choice(ref components, true) => {
+				assert_eq!(components[0].ty, Type::Tagged(Class::Context, 1, None, Box::new(Type::String("IA5String".to_string()))));
+				assert_eq!(components[1].ty, Type::Tagged(Class::Context, 4, Some(Mode::Explicit), Box::new(Type::Reference("Name".to_string()))));
+				assert_eq!(components[2].ty, Type::Tagged(Class::Application, 9, None, Box::new(Type::Any)));
+				assert!(components[2].addition);
+			},
+			ref ty => panic!("{:?}", ty)
+		}
+	}
+
+	#[test]
+	fn extensions() {
+		let m = module("A ::= SEQUENCE { a INTEGER, ..., [[ 2: b BOOLEAN, c NULL ]], d NULL, ..., e NULL }");
+
+		match m.assignments[0].ty {
+			Type::Sequence(ref components, true) => {
+				let additions = components.iter().map(|c| c.addition).collect::<Vec<_>>();
+				assert_eq!(additions, vec![false, true, true, true, false]);
+			},
+			ref ty => panic!("{:?}", ty)
+		}
+	}
+
+	#[test]
+	fn errors() {
+		let source = "Test DEFINITIONS ::= BEGIN\nA ::= SEQUENCE { a INTEGER,\n}\nEND";
+
+		match parse(&tokenize(source).unwrap()) {
+			Err(Error::Syntax{line, ref message}) => {
+				assert_eq!(line, 3);
+				assert_eq!(message, "expected a component name, found '}'");
+			},
+			result => panic!("{:?}", result)
+		}
+
+		let source = "Test DEFINITIONS ::= BEGIN A ::= SEQUENCE { COMPONENTS OF B } END";
+		assert!(parse(&tokenize(source).unwrap()).is_err());
+	}
+}
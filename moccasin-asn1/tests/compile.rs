@@ -0,0 +1,148 @@
+#[macro_use]
+extern crate moccasin_derive;
+extern crate moccasin;
+extern crate moccasin_asn1;
+
+#[allow(dead_code)]
+mod pkix {
+	include!("generated/pkix.rs");
+}
+
+use moccasin::{Parser, Token, Tag, Class, Writer};
+use moccasin::dsl::decode;
+use moccasin::query::Query;
+use moccasin::types::{Int, Oid};
+use moccasin_asn1::{compile, Error};
+
+use pkix::*;
+
+const GOOGLE: &'static [u8] = include_bytes!("../../tests/certs/google.der");
+
+// The encoding of the value found by `query`
+fn find(query: &str) -> &'static [u8] {
+	let tokens = Query::new(query).unwrap().find(GOOGLE, moccasin::Rules::Der).unwrap();
+	let token: &Token = &tokens[0];
+	&GOOGLE[token.offset..token.offset + token.header.len() + token.body.len()]
+}
+
+#[test]
+fn generated() {
+	// Regenerate with `moccasin-asn1 tests/pkix.asn tests/generated/pkix.rs`
+	assert_eq!(compile(include_str!("pkix.asn")).unwrap(), include_str!("generated/pkix.rs"));
+}
+
+#[test]
+fn x509_google() {
+	let cert = decode::<Certificate>(GOOGLE).unwrap();
+	let tbs = &cert.tbs_certificate;
+
+	assert_eq!(tbs.version, Int(VERSION_V3));
+	assert_eq!(tbs.serial_number.to_bytes_be().1.len(), 16);
+	assert_eq!(tbs.signature, cert.signature_algorithm);
+	assert_eq!(tbs.extensions.as_ref().map(|extensions| extensions.0.len()), Some(5));
+
+	let name = decode::<Name>(find("0/0/3")).unwrap();
+	let Name::RdnSequence(ref rdns) = name;
+
+	assert_eq!(rdns.0.len(), 7);
+	assert_eq!(rdns.0[3].0[0].type_, Oid::from_arcs(&[2, 5, 4, 10]).unwrap());
+
+	let validity = decode::<Validity>(find("0/0/4")).unwrap();
+	match (validity.not_before, validity.not_after) {
+		(Time::UtcTime(before), Time::UtcTime(after)) => assert!(before < after),
+		times => panic!("{:?}", times)
+	}
+
+	// Times after 2049 are GeneralizedTime (RFC 5280 4.1.2.5)
+	let mut w = Writer::new();
+	w.constructed(Tag::Sequence, |w| {
+		w.primitive(Tag::UtcTime, b"170101000000Z");
+		w.primitive(Tag::GeneralizedTime, b"20500101000000Z");
+		Ok(())
+	}).unwrap();
+
+	let validity = decode::<Validity>(w.as_bytes()).unwrap();
+	match (validity.not_before, validity.not_after) {
+		(Time::UtcTime(before), Time::GeneralTime(after)) => assert!(before < after),
+		times => panic!("{:?}", times)
+	}
+
+	let spki = decode::<SubjectPublicKeyInfo>(find("0/0/6")).unwrap();
+	assert_eq!(spki.algorithm.algorithm, Oid::from_arcs(&[1, 2, 840, 113549, 1, 1, 1]).unwrap());
+	assert_eq!(spki.algorithm.parameters.unwrap().tag, moccasin::Tag::Null);
+
+	let extensions = decode::<Extensions>(find("0/0/[3]/0")).unwrap();
+	assert_eq!(extensions.0.len(), 5);
+	assert!(extensions.0.iter().all(|ext| ext.critical == moccasin::types::Bool(false) || ext.extn_id == Oid::from_arcs(&[2, 5, 29, 19]).unwrap()));
+
+	// The version is explicitly tagged
+	let mut parser = Parser::new(find("0/0/[0]"));
+	assert_eq!(parser.nth(1).unwrap().unwrap().body, &[VERSION_V3 as u8]);
+	let _: Version = Int(VERSION_V3);
+}
+
+#[test]
+fn implicit_tags() {
+	// Strings and times are decoded according to the universal tags their
+	// implicit tags replace
+	let name = |tag, body: &[u8]| {
+		let mut w = Writer::new();
+		w.primitive(Tag::Composed(Class::Context, tag), body);
+		decode::<GeneralName>(w.as_bytes()).map(|name| format!("{:?}", name))
+	};
+
+	assert_eq!(name(1, b"root@example.com").unwrap(), "Rfc822Name(String(\"root@example.com\"))");
+	assert_eq!(name(2, b"example.com").unwrap(), "DNSName(String(\"example.com\"))");
+	assert_eq!(name(8, &[0x2A, 0x03]).unwrap(), format!("RegisteredID({:?})", Oid::from_arcs(&[1, 2, 3]).unwrap()));
+	assert!(name(2, b"\xC3\xA9xample.com").is_err());
+
+	let mut w = Writer::new();
+	w.constructed(Tag::Sequence, |w| {
+		w.primitive(Tag::Composed(Class::Context, 1), b"20500101000000Z");
+		Ok(())
+	}).unwrap();
+
+	let period = decode::<PrivateKeyUsagePeriod>(w.as_bytes()).unwrap();
+	assert!(period.not_before.is_none());
+	assert_eq!(period.not_after.map(|time| time.to_rfc3339()), Some("2050-01-01T00:00:00+00:00".to_string()));
+}
+
+#[test]
+fn unsupported() {
+	let compile = |body: &str| compile(&format!("Test DEFINITIONS IMPLICIT TAGS ::= BEGIN {} END", body));
+
+	match compile("A ::= SEQUENCE { b B }") {
+		Err(Error::Unsupported(ref what)) => assert_eq!(what, "unknown type B in AB"),
+		result => panic!("{:?}", result)
+	}
+
+	assert!(compile("A ::= ENUMERATED { a(0) }").is_err());
+	assert!(compile("A ::= SEQUENCE { a [UNIVERSAL 2] INTEGER }").is_err());
+	assert!(compile("A ::= SEQUENCE { a INTEGER DEFAULT b }").is_err());
+	assert!(compile("A ::= SEQUENCE { b SEQUENCE {} } AB ::= NULL").is_err());
+
+	match compile("A ::= CHOICE { b INTEGER, c B } B ::= CHOICE { d NULL, e INTEGER }") {
+		Err(Error::Unsupported(ref what)) => assert_eq!(what, "alternatives of CHOICE A with the same tag"),
+		result => panic!("{:?}", result)
+	}
+
+	assert!(compile("A ::= CHOICE { b ANY, c NULL }").is_err());
+	assert!(compile("A ::= CHOICE { b UTCTime, c GeneralizedTime, d [0] NULL, e B } B ::= CHOICE { f NULL }").is_ok());
+}
+
+#[test]
+fn tagging() {
+	let code = compile("Test DEFINITIONS AUTOMATIC TAGS ::= BEGIN
+		Request ::= SEQUENCE { level INTEGER { low(0), high(1) } DEFAULT high, body Body OPTIONAL, ..., trailer NULL }
+		Body ::= CHOICE { empty NULL, items SEQUENCE OF SEQUENCE { flag BOOLEAN, count INTEGER DEFAULT 5 } }
+	END").unwrap();
+
+	assert!(code.contains("pub const REQUEST_LEVEL_HIGH: i64 = 1;"));
+	assert!(code.contains("#[asn1(implicit = 0, default = \"::moccasin::types::Int(1)\")]\n\tpub level: ::moccasin::types::Int<i64>,"));
+	assert!(code.contains("#[asn1(explicit = 1)]\n\tpub body: ::std::option::Option<Body>,"));
+	assert!(code.contains("#[asn1(implicit = 2)]\n\tpub trailer: ::std::option::Option<::moccasin::types::Null>,"));
+	assert!(code.contains("#[asn1(extensible)]\npub struct Request {"));
+	assert!(code.contains("\tItems(::moccasin::dsl::SequenceOf<BodyItemsItem>),"));
+	assert!(code.contains("pub struct BodyItemsItem {\n\t#[asn1(implicit = 0)]\n\tpub flag: ::moccasin::types::Bool,"));
+	assert!(code.contains("#[asn1(implicit = 1, default = \"::moccasin::types::BigInt::from(5i64)\")]\n\tpub count: ::moccasin::types::BigInt,"));
+}
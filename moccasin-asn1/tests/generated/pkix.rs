@@ -0,0 +1,124 @@
+// Generated by moccasin-asn1 from PKIX1Explicit88, PKIX1Implicit88
+
+pub const VERSION_V1: i64 = 0;
+pub const VERSION_V2: i64 = 1;
+pub const VERSION_V3: i64 = 2;
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct Certificate<'a> {
+	pub tbs_certificate: TBSCertificate<'a>,
+	pub signature_algorithm: AlgorithmIdentifier<'a>,
+	pub signature: ::moccasin::types::Bitstring<'a>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct TBSCertificate<'a> {
+	#[asn1(explicit = 0, default = "::moccasin::types::Int(0)")]
+	pub version: Version,
+	pub serial_number: CertificateSerialNumber,
+	pub signature: AlgorithmIdentifier<'a>,
+	pub issuer: Name<'a>,
+	pub validity: Validity,
+	pub subject: Name<'a>,
+	pub subject_public_key_info: SubjectPublicKeyInfo<'a>,
+	#[asn1(implicit = 1)]
+	pub issuer_unique_id: ::std::option::Option<UniqueIdentifier<'a>>,
+	#[asn1(implicit = 2)]
+	pub subject_unique_id: ::std::option::Option<UniqueIdentifier<'a>>,
+	#[asn1(explicit = 3)]
+	pub extensions: ::std::option::Option<Extensions<'a>>,
+}
+
+pub type Version = ::moccasin::types::Int<i64>;
+
+pub type CertificateSerialNumber = ::moccasin::types::BigInt;
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct Validity {
+	pub not_before: Time,
+	pub not_after: Time,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+pub enum Time {
+	#[asn1(implicit = 23, universal)]
+	UtcTime(::moccasin::types::Time),
+	#[asn1(implicit = 24, universal)]
+	GeneralTime(::moccasin::types::Time),
+}
+
+pub type UniqueIdentifier<'a> = ::moccasin::types::Bitstring<'a>;
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct SubjectPublicKeyInfo<'a> {
+	pub algorithm: AlgorithmIdentifier<'a>,
+	pub subject_public_key: ::moccasin::types::Bitstring<'a>,
+}
+
+pub type Extensions<'a> = ::moccasin::dsl::SequenceOf<Extension<'a>>;
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct Extension<'a> {
+	pub extn_id: ::moccasin::types::Oid,
+	#[asn1(default = "::moccasin::types::Bool(false)")]
+	pub critical: ::moccasin::types::Bool,
+	pub extn_value: ::moccasin::types::Octetstring<'a>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct AlgorithmIdentifier<'a> {
+	pub algorithm: ::moccasin::types::Oid,
+	pub parameters: ::std::option::Option<::moccasin::dsl::Any<'a>>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+pub enum Name<'a> {
+	RdnSequence(RDNSequence<'a>),
+}
+
+pub type RDNSequence<'a> = ::moccasin::dsl::SequenceOf<RelativeDistinguishedName<'a>>;
+
+pub type RelativeDistinguishedName<'a> = ::moccasin::dsl::SetOf<AttributeTypeAndValue<'a>>;
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct AttributeTypeAndValue<'a> {
+	pub type_: AttributeType,
+	pub value: AttributeValue<'a>,
+}
+
+pub type AttributeType = ::moccasin::types::Oid;
+
+pub type AttributeValue<'a> = ::moccasin::dsl::Any<'a>;
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct PrivateKeyUsagePeriod {
+	#[asn1(implicit = 0, replaces = 24)]
+	pub not_before: ::std::option::Option<::moccasin::types::Time>,
+	#[asn1(implicit = 1, replaces = 24)]
+	pub not_after: ::std::option::Option<::moccasin::types::Time>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+pub enum GeneralName<'a> {
+	#[asn1(implicit = 0)]
+	OtherName(AnotherName<'a>),
+	#[asn1(implicit = 1, replaces = 22)]
+	Rfc822Name(::moccasin::types::String<'a>),
+	#[asn1(implicit = 2, replaces = 22)]
+	DNSName(::moccasin::types::String<'a>),
+	#[asn1(explicit = 4)]
+	DirectoryName(Name<'a>),
+	#[asn1(implicit = 6, replaces = 22)]
+	UniformResourceIdentifier(::moccasin::types::String<'a>),
+	#[asn1(implicit = 7)]
+	IPAddress(::moccasin::types::Octetstring<'a>),
+	#[asn1(implicit = 8)]
+	RegisteredID(::moccasin::types::Oid),
+}
+
+#[derive(Debug, PartialEq, Decode)]
+pub struct AnotherName<'a> {
+	pub type_id: ::moccasin::types::Oid,
+	#[asn1(explicit = 0)]
+	pub value: ::moccasin::dsl::Any<'a>,
+}
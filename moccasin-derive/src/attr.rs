@@ -0,0 +1,135 @@
+use proc_macro2::{TokenStream, Literal};
+use syn::{Attribute, Expr, Lit, Meta, NestedMeta, Result, Error};
+
+/// A context specific, application, private or universal tag replacing the
+/// tag of a value, or wrapping it when explicit.
+pub struct Tagging {
+	pub class: &'static str,
+	pub number: usize,
+	pub explicit: bool,
+	/// The number of the universal tag an implicit tag replaces
+	pub replaces: Option<usize>,
+}
+
+impl Tagging {
+	pub fn tag(&self) -> TokenStream {
+		let class = match self.class {
+			"application" => quote!(::moccasin::Class::Application),
+			"private"     => quote!(::moccasin::Class::Private),
+			"universal"   => quote!(::moccasin::Class::Universal),
+			_             => quote!(::moccasin::Class::Context),
+		};
+		let number = Literal::usize_unsuffixed(self.number);
+
+		quote!(::moccasin::Tag::Composed(#class, #number))
+	}
+
+	/// The universal tag replaced by the tag, if given.
+	pub fn universal(&self) -> Option<TokenStream> {
+		self.replaces.map(|number| {
+			let number = Literal::usize_unsuffixed(number);
+			quote!(::moccasin::Tag::universal(#number))
+		})
+	}
+}
+
+/// The `#[asn1(...)]` attributes of a field or enum variant.
+#[derive(Default)]
+pub struct FieldAttrs {
+	pub tagging: Option<Tagging>,
+	pub default: Option<Expr>,
+}
+
+/// The `#[asn1(...)]` attributes of a struct or enum.
+#[derive(Default)]
+pub struct ContainerAttrs {
+	pub extensible: bool,
+	pub set: bool,
+}
+
+fn nested(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+	let mut nested = Vec::new();
+
+	for attr in attrs.iter().filter(|attr| attr.path.is_ident("asn1")) {
+		match attr.parse_meta()? {
+			Meta::List(list) => nested.extend(list.nested),
+			meta => return Err(Error::new_spanned(meta, "expected #[asn1(...)]"))
+		}
+	}
+
+	Ok(nested)
+}
+
+impl FieldAttrs {
+	pub fn parse(attrs: &[Attribute]) -> Result<FieldAttrs> {
+		let mut result = FieldAttrs::default();
+		let mut number = None;
+		let mut explicit = false;
+		let mut class = "context";
+		let mut replaces = None;
+
+		for meta in nested(attrs)? {
+			match meta {
+				NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("implicit") || nv.path.is_ident("explicit") => {
+					if number.is_some() {
+						return Err(Error::new_spanned(nv, "duplicate tag"));
+					}
+
+					explicit = nv.path.is_ident("explicit");
+					number = match nv.lit {
+						Lit::Int(ref n) => Some(n.base10_parse::<usize>()?),
+						ref lit => return Err(Error::new_spanned(lit, "expected a tag number"))
+					};
+				},
+				NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("replaces") => {
+					replaces = match nv.lit {
+						Lit::Int(ref n) => Some((n.base10_parse::<usize>()?, nv.clone())),
+						ref lit => return Err(Error::new_spanned(lit, "expected a tag number"))
+					};
+				},
+				NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("default") => {
+					result.default = match nv.lit {
+						Lit::Str(ref s) => Some(s.parse()?),
+						ref lit => return Err(Error::new_spanned(lit, "expected an expression in a string"))
+					};
+				},
+				NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("application") => class = "application",
+				NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("private") => class = "private",
+				NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("universal") => class = "universal",
+				meta => return Err(Error::new_spanned(meta, "unknown asn1 attribute"))
+			}
+		}
+
+		// Only implicit tags replace the tag of the value
+		if let Some((_, ref nv)) = replaces {
+			if number.is_none() || explicit {
+				return Err(Error::new_spanned(nv, "replaces needs an implicit tag"));
+			}
+		}
+
+		result.tagging = number.map(|number| Tagging{
+			class: class,
+			number: number,
+			explicit: explicit,
+			replaces: replaces.map(|(number, _)| number),
+		});
+
+		Ok(result)
+	}
+}
+
+impl ContainerAttrs {
+	pub fn parse(attrs: &[Attribute]) -> Result<ContainerAttrs> {
+		let mut result = ContainerAttrs::default();
+
+		for meta in nested(attrs)? {
+			match meta {
+				NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("extensible") => result.extensible = true,
+				NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("set") => result.set = true,
+				meta => return Err(Error::new_spanned(meta, "unknown asn1 attribute"))
+			}
+		}
+
+		Ok(result)
+	}
+}
@@ -0,0 +1,368 @@
+use proc_macro2::{TokenStream, Span};
+use syn::{Data, DeriveInput, Fields, Ident, Index, Member, Result, Error};
+
+use attr::{FieldAttrs, ContainerAttrs};
+use {generics, option_type};
+
+// How a field or alternative is matched and decoded
+struct Component {
+	name: String,
+	attrs: FieldAttrs,
+}
+
+impl Component {
+	fn new(name: String, attrs: &[syn::Attribute]) -> Result<Component> {
+		Ok(Component{
+			name: name,
+			attrs: FieldAttrs::parse(attrs)?,
+		})
+	}
+
+	fn matcher(&self, depth: &TokenStream) -> TokenStream {
+		match self.attrs.tagging {
+			Some(ref tagging) => {
+				let tag = tagging.tag();
+
+				match tagging.universal() {
+					Some(universal) => quote!(::moccasin::dsl::Matcher::implicit(#depth, #tag, #universal)),
+					None => quote!(::moccasin::dsl::Matcher::with_tag(#depth, #tag))
+				}
+			},
+			None => quote!(::moccasin::dsl::Matcher::new(#depth))
+		}
+	}
+
+	// The type decoded from the tokens, which wraps explicitly tagged values
+	fn decoded_type(&self, ty: &syn::Type) -> TokenStream {
+		match self.attrs.tagging {
+			Some(ref tagging) if tagging.explicit => quote!(::moccasin::dsl::Explicit<#ty>),
+			_ => quote!(#ty)
+		}
+	}
+
+	// The default value as the decoded type
+	fn wrap(&self, value: &TokenStream) -> TokenStream {
+		match self.attrs.tagging {
+			Some(ref tagging) if tagging.explicit => quote!(::moccasin::dsl::Explicit(#value)),
+			_ => quote!(#value)
+		}
+	}
+
+	fn unwrap(&self) -> TokenStream {
+		match self.attrs.tagging {
+			Some(ref tagging) if tagging.explicit => quote!(.0),
+			_ => quote!()
+		}
+	}
+}
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+	let attrs = ContainerAttrs::parse(&input.attrs)?;
+
+	let (decode, accepts) = match input.data {
+		Data::Struct(ref data) => structure(input, &data.fields, &attrs)?,
+		Data::Enum(_) if attrs.set => return Err(Error::new_spanned(input, "choices cannot be a set")),
+		Data::Enum(ref data) => choice(input, data)?,
+		Data::Union(_) => return Err(Error::new_spanned(input, "unions cannot be decoded"))
+	};
+
+	let name = &input.ident;
+	let (lifetime, generics) = generics(&input.generics, |param| quote!(::moccasin::dsl::Decode<#param>));
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+	let (_, ty_generics, _) = input.generics.split_for_impl();
+
+	Ok(quote! {
+		impl #impl_generics ::moccasin::dsl::Decode<#lifetime> for #name #ty_generics #where_clause {
+			fn decode<I>(matcher: ::moccasin::dsl::Matcher, parser: &mut ::std::iter::Peekable<I>) -> ::std::result::Result<Self, ::moccasin::Error>
+				where I: ::std::iter::Iterator<Item=::std::result::Result<::moccasin::Token<#lifetime>, ::moccasin::Error>>
+			{
+				#decode
+			}
+
+			fn accepts(matcher: &::moccasin::dsl::Matcher, token: &::moccasin::Token<#lifetime>) -> bool {
+				#accepts
+			}
+		}
+	})
+}
+
+// A SEQUENCE with a component for each field
+fn structure(input: &DeriveInput, fields: &Fields, attrs: &ContainerAttrs) -> Result<(TokenStream, TokenStream)> {
+	let name = &input.ident;
+	let child_depth = quote!(depth + 1);
+
+	let mut components = Vec::new();
+	let mut members = Vec::new();
+	let mut locals = Vec::new();
+
+	for (i, field) in fields.iter().enumerate() {
+		let (member, field_name) = match field.ident {
+			Some(ref ident) => (Member::Named(ident.clone()), ident.to_string()),
+			None => (Member::Unnamed(Index::from(i)), i.to_string())
+		};
+
+		let component = Component::new(field_name, &field.attrs)?;
+
+		if let (Some(_), &Some(ref default)) = (option_type(&field.ty), &component.attrs.default) {
+			return Err(Error::new_spanned(default, "optional fields cannot have a default"));
+		}
+
+		components.push((component, &field.ty));
+		members.push(member);
+		locals.push(Ident::new(&format!("field_{}", i), Span::call_site()));
+	}
+
+	let (ty, body) = match attrs.set {
+		true  => (quote!(::moccasin::types::Set), set(&components, &locals, attrs, &child_depth)),
+		false => (quote!(::moccasin::types::Sequence), sequence(&components, &locals, attrs, &child_depth))
+	};
+
+	// CER and DER order the components of a SET by their tags
+	let check = match attrs.set {
+		true => quote! {
+			let set = match parser.peek() {
+				Some(&Ok(ref token)) => token.clone(),
+				Some(&Err(ref why)) => return Err(why.clone()),
+				None => return Err(::moccasin::Error::PrematureEof)
+			};
+
+			matcher.required::<_, #ty>(parser)?;
+
+			if set.rules != ::moccasin::Rules::Ber {
+				::moccasin::types::Set::check_components(&set)?;
+			}
+		},
+		false => quote!(matcher.required::<_, #ty>(parser)?;)
+	};
+
+	let decode = quote! {
+		let depth = matcher.depth();
+		#check
+
+		#body
+
+		Ok(#name{ #(#members: #locals),* })
+	};
+
+	let accepts = quote! {
+		matcher.matches::<#ty>(token)
+	};
+
+	Ok((decode, accepts))
+}
+
+// Decodes the components of a SEQUENCE in order
+fn sequence(components: &[(Component, &syn::Type)], locals: &[Ident], attrs: &ContainerAttrs, depth: &TokenStream) -> TokenStream {
+	let mut decode = Vec::new();
+
+	for (&(ref component, ty), local) in components.iter().zip(locals) {
+		let matcher = component.matcher(depth);
+		let field_name = &component.name;
+		let unwrap = component.unwrap();
+
+		let value = match (option_type(ty), &component.attrs.default) {
+			(Some(inner), _) => {
+				let ty = component.decoded_type(inner);
+				quote! {
+					::moccasin::dsl::optional::<_, #ty>(#matcher, parser)
+						.map(|value| value.map(|value| value #unwrap))
+				}
+			},
+			(None, &Some(ref default)) => {
+				let ty = component.decoded_type(ty);
+				let default = component.wrap(&quote!(#default));
+				quote! {
+					::moccasin::dsl::default::<_, #ty>(#matcher, parser, #default)
+						.map(|value| value #unwrap)
+				}
+			},
+			(None, &None) => {
+				let ty = component.decoded_type(ty);
+				quote! {
+					::moccasin::dsl::required::<_, #ty>(#matcher, parser)
+						.map(|value| value #unwrap)
+				}
+			}
+		};
+
+		decode.push(quote! {
+			let #local = #value.map_err(|why| why.field(#field_name))?;
+		});
+	}
+
+	let end = match attrs.extensible {
+		true  => quote!(::moccasin::dsl::skip(depth, parser)?;),
+		false => quote!(::moccasin::dsl::end(depth, parser)?;)
+	};
+
+	quote! {
+		#(#decode)*
+		#end
+	}
+}
+
+// Decodes the components of a SET, which may appear in any order, by the
+// first absent field accepting each child.
+fn set(components: &[(Component, &syn::Type)], locals: &[Ident], attrs: &ContainerAttrs, depth: &TokenStream) -> TokenStream {
+	let mut accepts = Vec::new();
+	let mut decode = Vec::new();
+	let mut finish = Vec::new();
+
+	for (i, (&(ref component, ty), local)) in components.iter().zip(locals).enumerate() {
+		let matcher = component.matcher(depth);
+		let field_name = &component.name;
+		let unwrap = component.unwrap();
+		let decoded = component.decoded_type(option_type(ty).unwrap_or(ty));
+
+		accepts.push(quote! {
+			#local.is_none() && <#decoded as ::moccasin::dsl::Decode>::accepts(&#matcher, token)
+		});
+
+		let value = match component.attrs.default {
+			Some(ref default) => {
+				let default = component.wrap(&quote!(#default));
+				quote!(::moccasin::dsl::default::<_, #decoded>(#matcher, parser, #default))
+			},
+			None => quote!(::moccasin::dsl::required::<_, #decoded>(#matcher, parser))
+		};
+
+		decode.push(quote! {
+			#i => {
+				let value = #value.map_err(|why| why.field(#field_name))?;
+				#local = Some(value #unwrap);
+			},
+		});
+
+		finish.push(match (option_type(ty), &component.attrs.default) {
+			(Some(_), _) => quote!(),
+			(None, &Some(ref default)) => quote! {
+				let #local = #local.unwrap_or_else(|| #default);
+			},
+			(None, &None) => quote! {
+				let #local = match #local {
+					Some(value) => value,
+					None => {
+						let why = ::moccasin::Error::MalformedToken.at(::moccasin::Location::of(&set));
+						return Err(why.field(#field_name));
+					}
+				};
+			}
+		});
+	}
+
+	let indices = 0..accepts.len();
+
+	let unknown = match attrs.extensible {
+		true  => quote!(::std::usize::MAX),
+		false => quote!(return Err(::moccasin::Error::MalformedToken.at(::moccasin::Location::of(token))))
+	};
+
+	quote! {
+		#(let mut #locals = None;)*
+
+		loop {
+			let component = match parser.peek() {
+				Some(&Ok(ref token)) if token.depth > depth => {
+					#(if #accepts { #indices } else)* {
+						#unknown
+					}
+				},
+				Some(&Err(ref why)) => return Err(why.clone()),
+				_ => break
+			};
+
+			match component {
+				#(#decode)*
+				_ => {
+					parser.next();
+					::moccasin::dsl::skip(depth + 1, parser)?;
+				}
+			}
+		}
+
+		#(#finish)*
+	}
+}
+
+// A CHOICE with an alternative for each variant
+fn choice(input: &DeriveInput, data: &syn::DataEnum) -> Result<(TokenStream, TokenStream)> {
+	let name = &input.ident;
+	let depth = quote!(depth);
+
+	let mut accepts = Vec::new();
+	let mut decode = Vec::new();
+
+	for (i, variant) in data.variants.iter().enumerate() {
+		let ty = match variant.fields {
+			Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+			_ => return Err(Error::new_spanned(variant, "alternatives must have exactly one unnamed field"))
+		};
+
+		let component = Component::new(variant.ident.to_string(), &variant.attrs)?;
+
+		if component.attrs.default.is_some() {
+			return Err(Error::new_spanned(variant, "alternatives cannot have a default"));
+		}
+
+		let matcher = component.matcher(&depth);
+		let decoded = component.decoded_type(ty);
+		let unwrap = component.unwrap();
+		let ident = &variant.ident;
+		let variant_name = &component.name;
+
+		accepts.push(quote! {
+			<#decoded as ::moccasin::dsl::Decode>::accepts(&#matcher, token)
+		});
+
+		decode.push(quote! {
+			#i => ::moccasin::dsl::required::<_, #decoded>(#matcher, parser)
+				.map(|value| #name::#ident(value #unwrap))
+				.map_err(|why| why.field(#variant_name)),
+		});
+	}
+
+	if accepts.is_empty() {
+		return Err(Error::new_spanned(input, "a choice needs at least one alternative"));
+	}
+
+	let indices = 0..accepts.len();
+	let checks = accepts.clone();
+
+	// Tags of a CHOICE are always explicit (X.680 31.2.9)
+	let decode = quote! {
+		if let Some(tag) = matcher.tag() {
+			let explicit = ::moccasin::dsl::Matcher::explicit(matcher.depth(), tag);
+			return ::moccasin::dsl::required::<_, Self>(explicit, parser);
+		}
+
+		let depth = matcher.depth();
+
+		let alternative = match parser.peek() {
+			Some(&Ok(ref token)) => {
+				#(if #checks { #indices } else)* {
+					let why = ::moccasin::Error::TokenMismatch{file: file!(), line: line!(), col: column!()};
+					return Err(why.at(::moccasin::Location::of(token)));
+				}
+			},
+			Some(&Err(ref why)) => return Err(why.clone()),
+			None => return Err(::moccasin::Error::PrematureEof)
+		};
+
+		match alternative {
+			#(#decode)*
+			_ => unreachable!()
+		}
+	};
+
+	let accepts = quote! {
+		if let Some(tag) = matcher.tag() {
+			let explicit = ::moccasin::dsl::Matcher::explicit(matcher.depth(), tag);
+			return ::moccasin::dsl::accepts::<Self>(&explicit, token);
+		}
+
+		let depth = matcher.depth();
+		#(#accepts)||*
+	};
+
+	Ok((decode, accepts))
+}
@@ -0,0 +1,136 @@
+use proc_macro2::TokenStream;
+use syn::{Data, DeriveInput, Fields, Index, Member, Result, Error};
+
+use attr::{FieldAttrs, ContainerAttrs};
+use {bounded, option_type};
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+	let attrs = ContainerAttrs::parse(&input.attrs)?;
+
+	let encode = match input.data {
+		Data::Struct(ref data) => structure(&data.fields, &attrs)?,
+		Data::Enum(_) if attrs.set => return Err(Error::new_spanned(input, "choices cannot be a set")),
+		Data::Enum(ref data) => choice(input, data)?,
+		Data::Union(_) => return Err(Error::new_spanned(input, "unions cannot be encoded"))
+	};
+
+	let name = &input.ident;
+	let generics = bounded(&input.generics, &quote!(::moccasin::Encode));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	Ok(quote! {
+		impl #impl_generics ::moccasin::Encode for #name #ty_generics #where_clause {
+			fn encode(&self, tag: ::std::option::Option<::moccasin::Tag>, w: &mut ::moccasin::Writer) -> ::std::result::Result<(), ::moccasin::Error> {
+				#encode
+			}
+		}
+	})
+}
+
+// Writes the value referenced by `value` with the tagging of `attrs`
+fn component(attrs: &FieldAttrs, name: &str, value: TokenStream) -> TokenStream {
+	let write = match attrs.tagging {
+		Some(ref tagging) if tagging.explicit => {
+			let tag = tagging.tag();
+			quote!(w.constructed(#tag, |w| ::moccasin::Encode::encode(#value, None, w)))
+		},
+		Some(ref tagging) => {
+			let tag = tagging.tag();
+			quote!(::moccasin::Encode::encode(#value, Some(#tag), w))
+		},
+		None => quote!(::moccasin::Encode::encode(#value, None, w))
+	};
+
+	quote!(#write.map_err(|why| why.field(#name)))
+}
+
+// A SEQUENCE or SET with a component for each field. Absent fields and
+// fields equal to their DEFAULT are omitted (11.5).
+fn structure(fields: &Fields, attrs: &ContainerAttrs) -> Result<TokenStream> {
+	let mut encode = Vec::new();
+
+	for (i, field) in fields.iter().enumerate() {
+		let (member, name) = match field.ident {
+			Some(ref ident) => (Member::Named(ident.clone()), ident.to_string()),
+			None => (Member::Unnamed(Index::from(i)), i.to_string())
+		};
+
+		let field_attrs = FieldAttrs::parse(&field.attrs)?;
+
+		encode.push(match (option_type(&field.ty), &field_attrs.default) {
+			(Some(_), &Some(ref default)) => {
+				return Err(Error::new_spanned(default, "optional fields cannot have a default"));
+			},
+			(Some(_), &None) => {
+				let write = component(&field_attrs, &name, quote!(value));
+				quote! {
+					if let Some(ref value) = self.#member {
+						#write?;
+					}
+				}
+			},
+			(None, &Some(ref default)) => {
+				let write = component(&field_attrs, &name, quote!(&self.#member));
+				quote! {
+					if self.#member != (#default) {
+						#write?;
+					}
+				}
+			},
+			(None, &None) => {
+				let write = component(&field_attrs, &name, quote!(&self.#member));
+				quote!(#write?;)
+			}
+		});
+	}
+
+	let (constructed, default_tag) = match attrs.set {
+		true  => (quote!(set), quote!(::moccasin::Tag::Set)),
+		false => (quote!(constructed), quote!(::moccasin::Tag::Sequence))
+	};
+
+	Ok(quote! {
+		w.#constructed(tag.unwrap_or(#default_tag), |w| {
+			#(#encode)*
+			Ok(())
+		})
+	})
+}
+
+// A CHOICE, written as the alternative of the variant. The tags of a choice
+// are those of its alternatives, so a tag given to the choice itself wraps
+// it explicitly (X.680 31.2.9).
+fn choice(input: &DeriveInput, data: &syn::DataEnum) -> Result<TokenStream> {
+	let name = &input.ident;
+	let mut arms = Vec::new();
+
+	for variant in data.variants.iter() {
+		match variant.fields {
+			Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => (),
+			_ => return Err(Error::new_spanned(variant, "alternatives must have exactly one unnamed field"))
+		}
+
+		let attrs = FieldAttrs::parse(&variant.attrs)?;
+
+		if attrs.default.is_some() {
+			return Err(Error::new_spanned(variant, "alternatives cannot have a default"));
+		}
+
+		let ident = &variant.ident;
+		let write = component(&attrs, &ident.to_string(), quote!(value));
+
+		arms.push(quote! {
+			#name::#ident(ref value) => #write,
+		});
+	}
+
+	Ok(quote! {
+		if let Some(tag) = tag {
+			return w.constructed(tag, |w| ::moccasin::Encode::encode(self, None, w));
+		}
+
+		match *self {
+			#(#arms)*
+		}
+	})
+}
@@ -0,0 +1,117 @@
+//! Derives decoders and encoders for structures and choices described by
+//! `#[asn1(...)]` attributes.
+//!
+//! Structs are decoded from a SEQUENCE with a component for each field, in
+//! order. Fields of type `Option<T>` are OPTIONAL. Enums are decoded as a
+//! CHOICE, with an alternative for each variant, each of which holds a
+//! single value. Tags of fields holding a CHOICE are always explicit (X.680
+//! 31.2.9). Fields and variants accept the following attributes:
+//!
+//! * `implicit = 0` replaces the tag of the value with `[0]`
+//! * `explicit = 0` wraps the value in a constructed `[0]`
+//! * `application`, `private` or `universal` change the class of these tags,
+//!   the latter telling apart types sharing a Rust type, such as UTCTime
+//!   (`implicit = 23, universal`) and GeneralizedTime
+//! * `replaces = 22` gives the number of the universal tag replaced by an
+//!   implicit tag, for types decoded depending on it, such as the IA5String
+//!   of `#[asn1(implicit = 1, replaces = 22)] String`
+//! * `default = "Bool(false)"` makes the field DEFAULT to the expression
+//!
+//! Structs marked `#[asn1(extensible)]` skip components following the known
+//! fields. Structs marked `#[asn1(set)]` are a SET instead, whose components
+//! are written in the canonical order of their tags when encoding. Decoding
+//! requires this order under CER and DER, and accepts any order under BER.
+//!
+//! Encoding omits absent OPTIONAL fields as well as fields equal to their
+//! DEFAULT, as DER and CER require (11.5). Decoding under these rules fails
+//! with `Error::EncodedDefault` if such a field is present.
+//!
+//! Depths are computed from the position of a value within the document,
+//! and errors name the fields they were raised in.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+mod attr;
+mod decode;
+mod encode;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use syn::{DeriveInput, Generics, GenericParam, Lifetime, LifetimeDef, Type, PathArguments, GenericArgument};
+
+#[proc_macro_derive(Decode, attributes(asn1))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+
+	match decode::derive(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(why)   => why.to_compile_error().into()
+	}
+}
+
+#[proc_macro_derive(Encode, attributes(asn1))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+
+	match encode::derive(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(why)   => why.to_compile_error().into()
+	}
+}
+
+// Returns the lifetime of the input tokens along with the generics of the
+// implementation, which require `bound` of all type parameters.
+fn generics<F>(generics: &Generics, bound: F) -> (Lifetime, Generics)
+	where F: Fn(&Lifetime) -> proc_macro2::TokenStream
+{
+	let mut generics = generics.clone();
+
+	let lifetime = match generics.lifetimes().next() {
+		Some(def) => def.lifetime.clone(),
+		None => {
+			let lifetime = Lifetime::new("'moccasin", Span::call_site());
+			generics.params.insert(0, GenericParam::Lifetime(LifetimeDef::new(lifetime.clone())));
+			lifetime
+		}
+	};
+
+	let bound = bound(&lifetime);
+	(lifetime, bounded(&generics, &bound))
+}
+
+// The generics of an implementation requiring `bound` of all type parameters
+fn bounded(generics: &Generics, bound: &proc_macro2::TokenStream) -> Generics {
+	let mut generics = generics.clone();
+
+	for param in generics.params.iter_mut() {
+		if let GenericParam::Type(ref mut param) = *param {
+			param.bounds.push(syn::parse2(bound.clone()).unwrap());
+		}
+	}
+
+	generics
+}
+
+// The `T` of a field of type `Option<T>`
+fn option_type(ty: &Type) -> Option<&Type> {
+	let segment = match *ty {
+		Type::Path(ref path) if path.qself.is_none() => path.path.segments.last(),
+		_ => None
+	};
+
+	match segment {
+		Some(segment) if segment.ident == "Option" => match segment.arguments {
+			PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => match args.args[0] {
+				GenericArgument::Type(ref ty) => Some(ty),
+				_ => None
+			},
+			_ => None
+		},
+		_ => None
+	}
+}
@@ -0,0 +1,220 @@
+#[macro_use]
+extern crate moccasin_derive;
+extern crate moccasin;
+
+use moccasin::{Tag, Class, Rules, Writer, Location};
+use moccasin::dsl::{decode, decode_with_rules};
+use moccasin::Error::*;
+use moccasin::types::{Int, Bool, Null, Oid, Octetstring};
+
+#[derive(Debug, PartialEq, Decode)]
+struct AlgorithmIdentifier {
+	algorithm: Oid,
+	parameters: Option<Null>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+struct Extension<'a> {
+	id: Oid,
+	#[asn1(default = "Bool(false)")]
+	critical: Bool,
+	value: Octetstring<'a>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+enum Name<'a> {
+	#[asn1(implicit = 1)]
+	Email(Octetstring<'a>),
+	#[asn1(implicit = 2)]
+	Dns(Octetstring<'a>),
+	#[asn1(explicit = 4)]
+	Directory(AlgorithmIdentifier),
+}
+
+#[derive(Debug, PartialEq, Decode)]
+struct Message<'a> {
+	#[asn1(explicit = 0, default = "Int(1)")]
+	version: Int<i64>,
+	algorithm: AlgorithmIdentifier,
+	#[asn1(implicit = 1)]
+	serial: Option<Int<i64>>,
+	names: Name<'a>,
+	#[asn1(implicit = 3, application)]
+	extension: Option<Extension<'a>>,
+}
+
+#[derive(Debug, PartialEq, Decode)]
+#[asn1(extensible)]
+struct Version(Int<i32>);
+
+fn oid(arcs: &[u32]) -> Oid {
+	Oid::from_arcs(arcs).unwrap()
+}
+
+// Writes a SEQUENCE holding the given fields
+fn sequence<F>(f: F) -> Vec<u8>
+	where F: FnOnce(&mut Writer) -> Result<(), moccasin::Error>
+{
+	let mut w = Writer::new();
+	w.constructed(Tag::Sequence, f).unwrap();
+	w.into_bytes()
+}
+
+#[test]
+fn structure() {
+	let data = sequence(|w| {
+		try!(w.write(&oid(&[1, 2, 840, 113549, 1, 1, 11])));
+		w.write(&Null)
+	});
+
+	assert_eq!(decode::<AlgorithmIdentifier>(&data).unwrap(), AlgorithmIdentifier{
+		algorithm: oid(&[1, 2, 840, 113549, 1, 1, 11]),
+		parameters: Some(Null),
+	});
+
+	let data = sequence(|w| w.write(&oid(&[1, 3, 101, 112])));
+	assert_eq!(decode::<AlgorithmIdentifier>(&data).unwrap().parameters, None);
+}
+
+#[test]
+fn default() {
+	let data = sequence(|w| {
+		try!(w.write(&oid(&[2, 5, 29, 19])));
+		w.write(&Octetstring(b"\x30\x00"[..].into()))
+	});
+
+	let ext = decode::<Extension>(&data).unwrap();
+	assert_eq!(ext.critical, Bool(false));
+	assert_eq!(&ext.value.0[..], b"\x30\x00");
+
+	let data = sequence(|w| {
+		try!(w.write(&oid(&[2, 5, 29, 19])));
+		try!(w.write(&Bool(true)));
+		w.write(&Octetstring(b"\x30\x00"[..].into()))
+	});
+
+	assert_eq!(decode::<Extension>(&data).unwrap().critical, Bool(true));
+
+	// Encoded defaults are only permitted by BER
+	let data = sequence(|w| {
+		try!(w.write(&oid(&[2, 5, 29, 19])));
+		try!(w.write(&Bool(false)));
+		w.write(&Octetstring(b"\x30\x00"[..].into()))
+	});
+
+	let why = decode::<Extension>(&data).unwrap_err();
+	assert_eq!(*why.kind(), EncodedDefault);
+	assert_eq!(why.fields(), vec!["critical"]);
+	assert_eq!(why.location().unwrap().offset, 7);
+	assert_eq!(decode_with_rules::<Extension>(&data, Rules::Ber).unwrap().critical, Bool(false));
+
+	let data = sequence(|w| {
+		try!(w.constructed(Tag::Composed(Class::Context, 0), |w| w.write(&Int(1i64))));
+		try!(w.constructed(Tag::Sequence, |w| w.write(&oid(&[1, 3, 101, 112]))));
+		w.primitive(Tag::Composed(Class::Context, 2), b"example.com");
+		Ok(())
+	});
+
+	assert_eq!(*decode::<Message>(&data).unwrap_err().kind(), EncodedDefault);
+}
+
+#[test]
+fn tagging() {
+	let algorithm = |w: &mut Writer| w.constructed(Tag::Sequence, |w| w.write(&oid(&[1, 3, 101, 112])));
+
+	let data = sequence(|w| {
+		try!(w.constructed(Tag::Composed(Class::Context, 0), |w| w.write(&Int(3i64))));
+		try!(algorithm(w));
+		w.primitive(Tag::Composed(Class::Context, 1), &[0x2A]);
+		try!(w.constructed(Tag::Composed(Class::Context, 4), algorithm));
+		w.constructed(Tag::Composed(Class::Application, 3), |w| {
+			try!(w.write(&oid(&[2, 5, 29, 17])));
+			w.write(&Octetstring(b"\x04\x00"[..].into()))
+		})
+	});
+
+	let message = decode::<Message>(&data).unwrap();
+	assert_eq!(message.version, Int(3));
+	assert_eq!(message.serial, Some(Int(42)));
+	assert_eq!(message.names, Name::Directory(AlgorithmIdentifier{
+		algorithm: oid(&[1, 3, 101, 112]),
+		parameters: None,
+	}));
+	assert_eq!(message.extension.unwrap().id, oid(&[2, 5, 29, 17]));
+
+	let data = sequence(|w| {
+		try!(algorithm(w));
+		w.primitive(Tag::Composed(Class::Context, 2), b"example.com");
+		Ok(())
+	});
+
+	let message = decode::<Message>(&data).unwrap();
+	assert_eq!(message.version, Int(1));
+	assert_eq!(message.serial, None);
+	assert_eq!(message.names, Name::Dns(Octetstring(b"example.com"[..].into())));
+	assert_eq!(message.extension, None);
+}
+
+#[test]
+fn extensible() {
+	let data = sequence(|w| {
+		try!(w.write(&Int(2i32)));
+		try!(w.constructed(Tag::Sequence, |w| w.write(&Null)));
+		w.write(&Null)
+	});
+
+	assert_eq!(decode::<Version>(&data).unwrap(), Version(Int(2)));
+	let why = decode::<AlgorithmIdentifier>(&data).unwrap_err();
+	assert_eq!(*why.kind(), TokenMismatch{file: "<unknown>", line: 0, col: 0});
+	assert_eq!(why.fields(), vec!["algorithm"]);
+	assert_eq!(why.location(), Some(&Location{offset: 2, depth: 1, tag: Some(Tag::Int), path: vec![Tag::Sequence]}));
+}
+
+#[test]
+fn errors() {
+	// Unknown trailing component
+	let data = sequence(|w| {
+		try!(w.write(&oid(&[1, 3, 101, 112])));
+		try!(w.write(&Null));
+		w.write(&Null)
+	});
+
+	let why = decode::<AlgorithmIdentifier>(&data).unwrap_err();
+	assert_eq!(*why.kind(), MalformedToken);
+	assert_eq!(why.location().unwrap().offset, 9);
+
+	// Padded integer in a nested field
+	let data = sequence(|w| {
+		try!(w.constructed(Tag::Sequence, |w| w.write(&oid(&[1, 3, 101, 112]))));
+		w.primitive(Tag::Composed(Class::Context, 1), &[0x00, 0x01]);
+		Ok(())
+	});
+
+	let why = decode::<Message>(&data).unwrap_err();
+	assert_eq!(*why.kind(), MalformedToken);
+	assert_eq!(why.fields(), vec!["serial"]);
+	assert_eq!(why.location().unwrap().path, vec![Tag::Sequence]);
+	assert_eq!(why.to_string(), "serial: malformed token at offset 9 (SEQUENCE/[1], depth 1)");
+
+	// None of the alternatives match
+	let data = sequence(|w| {
+		try!(w.constructed(Tag::Sequence, |w| w.write(&oid(&[1, 3, 101, 112]))));
+		w.write(&Null)
+	});
+
+	let why = decode::<Message>(&data).unwrap_err();
+	assert_eq!(why.fields(), vec!["names"]);
+	assert_eq!(why.location().unwrap().tag, Some(Tag::Null));
+
+	let data = sequence(|w| {
+		try!(w.constructed(Tag::Sequence, |w| w.write(&oid(&[1, 3, 101, 112]))));
+		w.constructed(Tag::Composed(Class::Context, 4), |w| w.constructed(Tag::Sequence, |w| w.write(&Null)))
+	});
+
+	let why = decode::<Message>(&data).unwrap_err();
+	assert_eq!(why.fields(), vec!["names", "Directory", "algorithm"]);
+	assert!(why.to_string().starts_with("names.Directory.algorithm: unexpected token"));
+
+	let why = moccasin::dsl::decode_with_rules::<AlgorithmIdentifier>(&[0x30, 0x80, 0x00, 0x00], Rules::Ber).unwrap_err();
+	assert_eq!(why.fields(), vec!["algorithm"]);
+}
@@ -0,0 +1,204 @@
+#[macro_use]
+extern crate moccasin_derive;
+extern crate moccasin;
+
+use moccasin::{Tag, Class, Rules, Writer, Encode};
+use moccasin::dsl::{decode, decode_with_rules};
+use moccasin::Error::*;
+use moccasin::types::{Int, Bool, Null, Oid, Octetstring};
+
+#[derive(Debug, PartialEq, Decode, Encode)]
+struct AlgorithmIdentifier {
+	algorithm: Oid,
+	parameters: Option<Null>,
+}
+
+#[derive(Debug, PartialEq, Decode, Encode)]
+struct Extension<'a> {
+	id: Oid,
+	#[asn1(default = "Bool(false)")]
+	critical: Bool,
+	value: Octetstring<'a>,
+}
+
+#[derive(Debug, PartialEq, Decode, Encode)]
+enum Name<'a> {
+	#[asn1(implicit = 2)]
+	Email(Octetstring<'a>),
+	#[asn1(explicit = 4)]
+	Directory(AlgorithmIdentifier),
+}
+
+#[derive(Debug, PartialEq, Decode, Encode)]
+struct Message<'a> {
+	#[asn1(explicit = 0, default = "Int(1)")]
+	version: Int<i64>,
+	algorithm: AlgorithmIdentifier,
+	#[asn1(implicit = 1)]
+	serial: Option<Int<i64>>,
+	names: Name<'a>,
+	#[asn1(implicit = 3, application)]
+	extension: Option<Extension<'a>>,
+}
+
+#[derive(Debug, PartialEq, Decode, Encode)]
+struct Contact<'a> {
+	#[asn1(implicit = 5)]
+	name: Name<'a>,
+}
+
+#[derive(Debug, PartialEq, Decode, Encode)]
+#[asn1(set)]
+struct Attributes {
+	#[asn1(implicit = 2)]
+	count: Int<i32>,
+	#[asn1(implicit = 0)]
+	flag: Option<Bool>,
+	id: Oid,
+	#[asn1(implicit = 1, default = "Int(0)")]
+	version: Int<i32>,
+}
+
+fn oid(arcs: &[u32]) -> Oid {
+	Oid::from_arcs(arcs).unwrap()
+}
+
+fn encode<T: Encode>(value: &T) -> Vec<u8> {
+	let mut w = Writer::new();
+	w.encode(value).unwrap();
+	w.into_bytes()
+}
+
+fn algorithm() -> AlgorithmIdentifier {
+	AlgorithmIdentifier{
+		algorithm: oid(&[1, 3, 101, 112]),
+		parameters: None,
+	}
+}
+
+#[test]
+fn structure() {
+	let bytes = encode(&algorithm());
+	assert_eq!(bytes, vec![0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x70]);
+	assert_eq!(decode::<AlgorithmIdentifier>(&bytes).unwrap(), algorithm());
+
+	let value = AlgorithmIdentifier{parameters: Some(Null), ..algorithm()};
+	assert_eq!(decode::<AlgorithmIdentifier>(&encode(&value)).unwrap(), value);
+}
+
+#[test]
+fn default() {
+	let ext = Extension{
+		id: oid(&[2, 5, 29, 19]),
+		critical: Bool(false),
+		value: Octetstring(b"\x30\x00"[..].into()),
+	};
+
+	let bytes = encode(&ext);
+	assert_eq!(bytes, vec![0x30, 0x09, 0x06, 0x03, 0x55, 0x1D, 0x13, 0x04, 0x02, 0x30, 0x00]);
+	assert_eq!(decode::<Extension>(&bytes).unwrap(), ext);
+
+	let ext = Extension{critical: Bool(true), ..ext};
+	assert_eq!(decode::<Extension>(&encode(&ext)).unwrap(), ext);
+}
+
+#[test]
+fn tagging() {
+	let message = Message{
+		version: Int(3),
+		algorithm: algorithm(),
+		serial: Some(Int(42)),
+		names: Name::Directory(algorithm()),
+		extension: Some(Extension{
+			id: oid(&[2, 5, 29, 17]),
+			critical: Bool(true),
+			value: Octetstring(b"\x04\x00"[..].into()),
+		}),
+	};
+
+	let bytes = encode(&message);
+	assert_eq!(&bytes[2..7], &[0xA0, 0x03, 0x02, 0x01, 0x03]);
+	assert_eq!(decode::<Message>(&bytes).unwrap(), message);
+
+	let message = Message{
+		version: Int(1),
+		serial: None,
+		names: Name::Email(Octetstring(b"root@example.com"[..].into())),
+		extension: None,
+		..message
+	};
+
+	let bytes = encode(&message);
+	assert_eq!(&bytes[2..4], &[0x30, 0x05]);
+	assert_eq!(&bytes[9..11], &[0x82, 0x10]);
+	assert_eq!(decode::<Message>(&bytes).unwrap(), message);
+
+	// Implicitly tagged structures
+	let mut w = Writer::new();
+	algorithm().encode(Some(Tag::Composed(Class::Context, 5)), &mut w).unwrap();
+	assert_eq!(w.as_bytes()[0], 0xA5);
+
+	// Tags of choices are explicit
+	let contact = Contact{name: Name::Email(Octetstring(b"abc"[..].into()))};
+	let bytes = encode(&contact);
+	assert_eq!(bytes, vec![0x30, 0x07, 0xA5, 0x05, 0x82, 0x03, b'a', b'b', b'c']);
+	assert_eq!(decode::<Contact>(&bytes).unwrap(), contact);
+	assert!(decode::<Contact>(&[0x30, 0x05, 0x85, 0x03, b'a', b'b', b'c']).is_err());
+}
+
+#[test]
+fn set() {
+	let attrs = Attributes{
+		count: Int(7),
+		flag: Some(Bool(true)),
+		id: oid(&[2, 5, 4, 3]),
+		version: Int(2),
+	};
+
+	let bytes = encode(&attrs);
+	assert_eq!(bytes, vec![
+		0x31, 0x0E,
+		0x06, 0x03, 0x55, 0x04, 0x03,
+		0x80, 0x01, 0xFF,
+		0x81, 0x01, 0x02,
+		0x82, 0x01, 0x07,
+	]);
+	assert_eq!(decode::<Attributes>(&bytes).unwrap(), attrs);
+
+	let attrs = Attributes{flag: None, version: Int(0), ..attrs};
+	let bytes = encode(&attrs);
+	assert_eq!(bytes.len(), 10);
+	assert_eq!(decode::<Attributes>(&bytes).unwrap(), attrs);
+
+	// Missing and repeated components
+	let mut w = Writer::new();
+	w.constructed(Tag::Set, |w| w.write(&oid(&[2, 5, 4, 3]))).unwrap();
+
+	let why = decode::<Attributes>(w.as_bytes()).unwrap_err();
+	assert_eq!(*why.kind(), MalformedToken);
+	assert_eq!(why.fields(), vec!["count"]);
+	assert_eq!(why.location().unwrap().offset, 0);
+	assert_eq!(why.location().unwrap().tag, Some(Tag::Set));
+
+	let mut w = Writer::new();
+	w.constructed(Tag::Set, |w| {
+		try!(w.write(&oid(&[2, 5, 4, 3])));
+		w.write(&oid(&[2, 5, 4, 3]))
+	}).unwrap();
+
+	let why = decode::<Attributes>(w.as_bytes()).unwrap_err();
+	assert_eq!(*why.kind(), MalformedToken);
+	assert_eq!(why.location().unwrap().offset, 7);
+
+	// Components out of order are only permitted by BER
+	let mut w = Writer::new();
+	w.constructed(Tag::Set, |w| {
+		w.primitive(Tag::Composed(Class::Context, 2), &[0x07]);
+		w.write(&oid(&[2, 5, 4, 3]))
+	}).unwrap();
+
+	let why = decode::<Attributes>(w.as_bytes()).unwrap_err();
+	assert_eq!(*why.kind(), UnsortedSet);
+	assert_eq!(why.location().unwrap().offset, 5);
+	assert_eq!(decode_with_rules::<Attributes>(w.as_bytes(), Rules::Ber).unwrap().count, Int(7));
+}
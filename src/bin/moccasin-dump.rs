@@ -8,16 +8,46 @@ use moccasin::prelude::*;
 
 #[cfg(not(test))]
 fn main() {
+	use moccasin::Rules;
+	use moccasin::query::Query;
+
 	let mut args = env::args();
 	args.next().unwrap();
 
-	for arg in args {
-		let bytes = arg.from_hex().unwrap();
+	let mut query = None;
+	let mut inputs = Vec::new();
+
+	while let Some(arg) = args.next() {
+		match &arg[..] {
+			"-q" => {
+				let q = args.next().expect("-q requires a query");
+				query = Some(Query::new(&q).unwrap_or_else(|why| panic!("{}: {}", q, why)));
+			},
+			_ => inputs.push(arg)
+		}
+	}
+
+	for input in inputs {
+		let bytes = input.from_hex().unwrap();
+
+		if let Some(ref query) = query {
+			match query.find(&bytes, Rules::Der) {
+				Ok(tokens) => for t in tokens {
+					println!("{:?}", t);
+				},
+				Err(why) => println!("error: {}", why.resolve_path(&bytes))
+			}
+
+			continue;
+		}
 
 		let p = Parser::new(&bytes);
 
 		for t in p {
-			println!("{:?}", t);
+			match t {
+				Ok(t)    => println!("{:?}", t),
+				Err(why) => println!("error: {}", why.resolve_path(&bytes))
+			}
 		}
 	}
-}
\ No newline at end of file
+}
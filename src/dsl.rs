@@ -1,11 +1,18 @@
 use std::iter::Peekable;
+use std::marker::PhantomData;
 
-use {Token, Tag, Error};
-use Error::{TokenMismatch, PrematureEof};
-use types::TokenType;
+use {Parser, Token, Tag, Encoding, Rules, Error, Location};
+use Error::{TokenMismatch, PrematureEof, MalformedToken, EncodedDefault};
+use types::{TokenType, Sequence, Set};
 
+/// Matches the next token against a type, at `depth` and with the
+/// universal tag of the type, or a replacement tag (8.14). Explicitly
+/// tagged values are wrapped in a constructed token carrying the tag, with
+/// the value as its only child.
 pub struct Matcher {
 	tag: Option<Tag>,
+	universal: Option<Tag>,
+	explicit: bool,
 	depth: u8
 }
 
@@ -13,36 +20,91 @@ impl Matcher {
 	pub fn new(depth: u8) -> Matcher {
 		Matcher {
 			depth: depth,
-			tag: None
+			tag: None,
+			universal: None,
+			explicit: false
 		}
 	}
 
+	/// Matches an implicitly tagged value, such as `[0] IMPLICIT INTEGER`.
+	/// Types decoded differently depending on their tag, such as `String`
+	/// and `Time`, need `implicit` instead.
 	pub fn with_tag(depth: u8, tag: Tag) -> Matcher {
 		Matcher {
 			depth: depth,
-			tag: Some(tag)
+			tag: Some(tag),
+			universal: None,
+			explicit: false
 		}
 	}
 
+	/// Matches an implicitly tagged value, which is decoded as if it had
+	/// the `universal` tag it replaces, such as `[1] IMPLICIT IA5String`.
+	pub fn implicit(depth: u8, tag: Tag, universal: Tag) -> Matcher {
+		Matcher {
+			depth: depth,
+			tag: Some(tag),
+			universal: Some(universal),
+			explicit: false
+		}
+	}
+
+	/// Matches an explicitly tagged value, such as `[0] EXPLICIT INTEGER`.
+	pub fn explicit(depth: u8, tag: Tag) -> Matcher {
+		Matcher {
+			depth: depth,
+			tag: Some(tag),
+			universal: None,
+			explicit: true
+		}
+	}
+
+	pub fn depth(&self) -> u8 {
+		self.depth
+	}
+
+	pub fn tag(&self) -> Option<Tag> {
+		self.tag
+	}
+
+	/// The universal tag replaced by the tag of an implicitly tagged value.
+	pub fn universal(&self) -> Option<Tag> {
+		self.universal
+	}
+
+	pub fn is_explicit(&self) -> bool {
+		self.explicit
+	}
+
 	pub fn required<'a, I, T>(self, parser: &mut I) -> Result<T, Error>
 		where I: Iterator<Item=Result<Token<'a>, Error>>, T: TokenType<'a>
 	{
 		self.required_at(parser, "<unknown>", 0, 0)
 	}
 
+	/// Reads the value, failing with a `TokenMismatch` at the given
+	/// position in the decoder if the next token does not match.
 	pub fn required_at<'a, I, T>(self, parser: &mut I, file: &'static str, line: u32, col: u32) -> Result<T, Error>
 		where I: Iterator<Item=Result<Token<'a>, Error>>, T: TokenType<'a>
 	{
+		let mismatch = TokenMismatch{file: file, line: line, col: col};
+
 		match parser.next() {
 			Some(Ok(token)) => {
-				match self.matches::<T>(&token) {
-					true  => Ok(try!(T::from_token(&token))),
-					false => Err(TokenMismatch{
-						file: file,
-						line: line,
-						col: col
-					})
+				if self.explicit {
+					if !self.wrapper().matches::<Sequence>(&token) {
+						return Err(mismatch.at(Location::of(&token)));
+					}
+
+					try!(wrapped(&token));
+					return Matcher::new(self.depth + 1).required_at(parser, file, line, col);
+				}
+
+				if !self.matches::<T>(&token) {
+					return Err(mismatch.at(Location::of(&token)));
 				}
+
+				self.from_token(&token).map_err(|why| why.at(Location::of(&token)))
 			},
 			Some(Err(why)) => Err(why),
 			None => Err(PrematureEof)
@@ -57,30 +119,431 @@ impl Matcher {
 			_ => return None
 		};
 
-		if matches {
-			return match T::from_token(&parser.next().unwrap().unwrap()) {
-				Ok(ty) => Some(ty),
-				_      => None
-			}
+		if !matches {
+			return None;
+		}
+
+		if self.explicit {
+			parser.next();
+			return Matcher::new(self.depth + 1).optional(parser);
 		}
 
-		None
+		match self.from_token(&parser.next().unwrap().unwrap()) {
+			Ok(ty) => Some(ty),
+			_      => None
+		}
+	}
+
+	// Matches the constructed token wrapping an explicitly tagged value
+	fn wrapper(&self) -> Matcher {
+		Matcher {
+			depth: self.depth,
+			tag: self.tag,
+			universal: None,
+			explicit: false
+		}
 	}
 
-	fn matches<'a, T>(&self, token: &Token) -> bool
+	// Decodes the value of a matching token, with its universal tag in
+	// place of the tag replacing it
+	fn from_token<'a, T>(&self, token: &Token<'a>) -> Result<T, Error>
 		where T: TokenType<'a>
 	{
-		token.depth == self.depth    &&
-		token.enc   == T::encoding() &&
+		match self.universal {
+			Some(tag) => T::from_token(&Token{tag: tag, ..token.clone()}),
+			None      => T::from_token(token)
+		}
+	}
+
+	pub fn matches<'a, T>(&self, token: &Token) -> bool
+		where T: TokenType<'a>
+	{
+		if token.depth != self.depth {
+			return false;
+		}
+
+		// The value of explicitly tagged values is read from the body of
+		// the wrapper, as it follows the wrapper in the input
+		if self.explicit {
+			return self.wrapper().matches::<Sequence>(token) && match wrapped(token) {
+				Ok(Some(ref value)) => Matcher::new(self.depth + 1).matches::<T>(value),
+				_                   => false
+			};
+		}
+
+		// BER permits strings to be split into constructed segments
+		let segmented = token.rules != Rules::Der &&
+			self.universal.unwrap_or(token.tag).is_string() && T::encoding() == Encoding::Primitive;
+
+		(token.enc == T::encoding() || segmented) &&
 		match self.tag {
-			Some(tag) => token.tag == tag,
+			Some(tag) => token.tag == tag && self.universal.map_or(true, T::matches),
 			None      => T::matches(token.tag)
 		}
 	}
 }
 
+/// Values decoded from a token and its descendants, such as the structures
+/// generated by `#[derive(Decode)]` from moccasin-derive.
+///
+/// Errors are located at the offending token without its ancestors, unless
+/// decoded with `decode`. Otherwise `Error::resolve_path` fills them in.
+pub trait Decode<'a>: Sized {
+	/// Decodes the value from the next tokens, the first of which has to
+	/// match `matcher`.
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<Self, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>;
+
+	/// Whether the value can start with `token`, which decides whether
+	/// optional values and alternatives of a choice are present.
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool;
+}
+
+impl<'a, T> Decode<'a> for T
+	where T: TokenType<'a>
+{
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<T, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		matcher.required(parser)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		matcher.matches::<T>(token)
+	}
+}
+
+/// An explicitly tagged value, which is wrapped in a constructed token
+/// carrying the tag of the matcher (8.14).
+#[derive(Debug, PartialEq)]
+pub struct Explicit<T>(pub T);
+
+impl<'a, T> Decode<'a> for Explicit<T>
+	where T: Decode<'a>
+{
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<Explicit<T>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		match matcher.tag {
+			Some(tag) => required(Matcher::explicit(matcher.depth, tag), parser).map(Explicit),
+			None      => Err(TokenMismatch{file: file!(), line: line!(), col: column!()})
+		}
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		match matcher.tag {
+			Some(tag) => accepts::<T>(&Matcher::explicit(matcher.depth, tag), token),
+			None      => false
+		}
+	}
+}
+
+/// The values of a SEQUENCE OF, each of which is decoded from a child of the
+/// constructed token (8.10).
+#[derive(Debug, PartialEq)]
+pub struct SequenceOf<T>(pub Vec<T>);
+
+impl<'a, T> Decode<'a> for SequenceOf<T>
+	where T: Decode<'a>
+{
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<SequenceOf<T>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth;
+		try!(matcher.required::<_, Sequence>(parser));
+		each(Matcher::new(depth + 1), parser).collect::<Result<_, _>>().map(SequenceOf)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		matcher.matches::<Sequence>(token)
+	}
+}
+
+/// The values of a SET OF (8.12). Under CER and DER the elements have to be
+/// ordered by their encodings (11.6).
+#[derive(Debug, PartialEq)]
+pub struct SetOf<T>(pub Vec<T>);
+
+impl<'a, T> Decode<'a> for SetOf<T>
+	where T: Decode<'a>
+{
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<SetOf<T>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth;
+		let token = match parser.peek() {
+			Some(&Ok(ref token)) => token.clone(),
+			Some(&Err(ref why))  => return Err(why.clone()),
+			None                 => return Err(PrematureEof)
+		};
+
+		try!(matcher.required::<_, Set>(parser));
+
+		if token.rules != Rules::Ber {
+			try!(Set::check_elements(&token));
+		}
+
+		each(Matcher::new(depth + 1), parser).collect::<Result<_, _>>().map(SetOf)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		matcher.matches::<Set>(token)
+	}
+}
+
+/// Decodes the values matching `matcher` up to the end of their parent,
+/// such as the elements of a SEQUENCE OF whose own token has been read.
+/// Each value has to be decoded completely, including its descendants.
+pub struct Each<'p, I: 'p + Iterator, T> {
+	parser: &'p mut Peekable<I>,
+	depth: u8,
+	tag: Option<Tag>,
+	universal: Option<Tag>,
+	explicit: bool,
+	done: bool,
+	ty: PhantomData<T>,
+}
+
+pub fn each<'a, 'p, I, T>(matcher: Matcher, parser: &'p mut Peekable<I>) -> Each<'p, I, T>
+	where I: Iterator<Item=Result<Token<'a>, Error>>, T: Decode<'a>
+{
+	Each {
+		parser: parser,
+		depth: matcher.depth,
+		tag: matcher.tag,
+		universal: matcher.universal,
+		explicit: matcher.explicit,
+		done: false,
+		ty: PhantomData,
+	}
+}
+
+impl<'a, 'p, I, T> Iterator for Each<'p, I, T>
+	where I: Iterator<Item=Result<Token<'a>, Error>>, T: Decode<'a>
+{
+	type Item = Result<T, Error>;
+
+	fn next(&mut self) -> Option<Result<T, Error>> {
+		if self.done {
+			return None;
+		}
+
+		match self.parser.peek() {
+			Some(&Ok(ref token)) if token.depth >= self.depth => (),
+			Some(&Err(_)) => (),
+			_ => return None
+		}
+
+		let matcher = Matcher {
+			depth: self.depth,
+			tag: self.tag,
+			universal: self.universal,
+			explicit: self.explicit,
+		};
+
+		// Errors end the iteration
+		let result = required(matcher, self.parser);
+		self.done = result.is_err();
+
+		Some(result)
+	}
+}
+
+/// A value of any type, such as the parameters of an algorithm (ANY
+/// DEFINED BY), which is left undecoded. The body of constructed values
+/// holds their children.
+#[derive(Debug, PartialEq)]
+pub struct Any<'a> {
+	pub tag: Tag,
+	pub enc: Encoding,
+	pub body: &'a [u8],
+}
+
+impl<'a> Decode<'a> for Any<'a> {
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<Any<'a>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let token = match parser.next() {
+			Some(Ok(token)) => token,
+			Some(Err(why))  => return Err(why),
+			None            => return Err(PrematureEof)
+		};
+
+		if !Any::accepts(&matcher, &token) {
+			let why = TokenMismatch{file: file!(), line: line!(), col: column!()};
+			return Err(why.at(Location::of(&token)));
+		}
+
+		try!(skip(token.depth, parser));
+
+		Ok(Any{
+			tag: token.tag,
+			enc: token.enc,
+			body: token.body,
+		})
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		token.depth == matcher.depth && matcher.tag.map_or(true, |tag| token.tag == tag)
+	}
+}
+
+/// Decodes a value which has to be present.
+pub fn required<'a, I, T>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<T, Error>
+	where I: Iterator<Item=Result<Token<'a>, Error>>, T: Decode<'a>
+{
+	if !matcher.explicit {
+		return T::decode(matcher, parser);
+	}
+
+	// The wrapper of an explicitly tagged value holds nothing else
+	let depth = matcher.depth;
+	try!(matcher.wrapper().required::<_, Sequence>(parser));
+
+	let value = try!(T::decode(Matcher::new(depth + 1), parser));
+	try!(end(depth, parser));
+
+	Ok(value)
+}
+
+/// Decodes a value if the next token starts one. Unlike
+/// `Matcher::optional`, errors are returned instead of treating the value
+/// as absent.
+pub fn optional<'a, I, T>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<Option<T>, Error>
+	where I: Iterator<Item=Result<Token<'a>, Error>>, T: Decode<'a>
+{
+	let accepted = match parser.peek() {
+		Some(&Ok(ref token)) => accepts::<T>(&matcher, token),
+		Some(&Err(ref why))  => return Err(why.clone()),
+		None                 => false
+	};
+
+	match accepted {
+		true  => required(matcher, parser).map(Some),
+		false => Ok(None)
+	}
+}
+
+/// Decodes a value with a DEFAULT, which is returned if the value is absent.
+/// CER and DER require values equal to their default to be omitted (11.5).
+pub fn default<'a, I, T>(matcher: Matcher, parser: &mut Peekable<I>, default: T) -> Result<T, Error>
+	where I: Iterator<Item=Result<Token<'a>, Error>>, T: Decode<'a> + PartialEq
+{
+	let (rules, location) = match parser.peek() {
+		Some(&Ok(ref token)) if accepts::<T>(&matcher, token) => (token.rules, Location::of(token)),
+		Some(&Err(ref why)) => return Err(why.clone()),
+		_ => return Ok(default)
+	};
+
+	let value = try!(required(matcher, parser));
+
+	if rules != Rules::Ber && value == default {
+		return Err(EncodedDefault.at(location));
+	}
+
+	Ok(value)
+}
+
+/// Whether a value matching `matcher` can start with `token`. Unlike
+/// `Decode::accepts`, this also handles explicitly tagged values.
+pub fn accepts<'a, T>(matcher: &Matcher, token: &Token<'a>) -> bool
+	where T: Decode<'a>
+{
+	if !matcher.explicit {
+		return T::accepts(matcher, token);
+	}
+
+	matcher.wrapper().matches::<Sequence>(token) && match wrapped(token) {
+		Ok(Some(ref value)) => T::accepts(&Matcher::new(matcher.depth + 1), value),
+		_                   => false
+	}
+}
+
+// The value wrapped in an explicit tag, which has to be the only child of
+// the wrapper
+fn wrapped<'a>(wrapper: &Token<'a>) -> Result<Option<Token<'a>>, Error> {
+	let mut children = wrapper.parser();
+
+	let value = match children.next() {
+		Some(value) => try!(value),
+		None        => return Ok(None)
+	};
+
+	children.skip_token(&value);
+
+	match children.next() {
+		Some(Ok(token)) => Err(MalformedToken.at(Location::of(&token))),
+		Some(Err(why))  => Err(why),
+		None            => Ok(Some(value))
+	}
+}
+
+/// Checks that the constructed value at `depth` has no children left.
+pub fn end<'a, I>(depth: u8, parser: &mut Peekable<I>) -> Result<(), Error>
+	where I: Iterator<Item=Result<Token<'a>, Error>>
+{
+	match parser.peek() {
+		Some(&Ok(ref token)) if token.depth > depth => Err(MalformedToken.at(Location::of(token))),
+		Some(&Err(ref why)) => Err(why.clone()),
+		_ => Ok(())
+	}
+}
+
+/// Skips the remaining children of the constructed value at `depth`, such
+/// as the additions to an extensible type.
+pub fn skip<'a, I>(depth: u8, parser: &mut Peekable<I>) -> Result<(), Error>
+	where I: Iterator<Item=Result<Token<'a>, Error>>
+{
+	loop {
+		match parser.peek() {
+			Some(&Ok(ref token)) if token.depth > depth => (),
+			Some(&Err(ref why)) => return Err(why.clone()),
+			_ => return Ok(())
+		}
+
+		parser.next();
+	}
+}
+
+/// Decodes a document consisting of a single value.
+pub fn decode<'a, T>(bytes: &'a [u8]) -> Result<T, Error>
+	where T: Decode<'a>
+{
+	decode_with_rules(bytes, Rules::Der)
+}
+
+pub fn decode_with_rules<'a, T>(bytes: &'a [u8], rules: Rules) -> Result<T, Error>
+	where T: Decode<'a>
+{
+	let mut parser = Parser::with_rules(bytes, rules).peekable();
+
+	let result = T::decode(Matcher::new(0), &mut parser).and_then(|value| {
+		match parser.next() {
+			None            => Ok(value),
+			Some(Ok(token)) => Err(MalformedToken.at(Location::of(&token))),
+			Some(Err(why))  => Err(why)
+		}
+	});
+
+	// Decoders only know the location of the token they failed on
+	result.map_err(|why| why.resolve_path(bytes))
+}
+
+/// Reads the value at `$depth`, which may be implicitly tagged, as in
+/// `req!(p => 1, Int<i64> > (Context, 1))`, or explicitly tagged, as in
+/// `req!(p => 1, Int<i64> > explicit (Context, 0))`.
 #[macro_export]
 macro_rules! req {
+	( $p:expr => $depth:expr, $ty:ty > explicit ($class:expr, $id:expr) ) => {{
+		let tag = $crate::Tag::Composed($class, $id);
+		let matcher = $crate::dsl::Matcher::explicit($depth, tag);
+
+		if cfg!(debug_assertions) {
+			try!(matcher.required_at::<_, $ty>($p, file!(), line!(), column!()))
+		} else {
+			try!(matcher.required::<_, $ty>($p))
+		}
+	}};
 	( $p:expr => $depth:expr, $ty:ty > ($class:expr, $id:expr) ) => {{
 		let tag = $crate::Tag::Composed($class, $id);
 		let matcher = $crate::dsl::Matcher::with_tag($depth, tag);
@@ -104,6 +567,10 @@ macro_rules! req {
 
 #[macro_export]
 macro_rules! opt {
+	( $p:expr => $depth:expr, $ty:ty > explicit ($class:expr, $id:expr) ) => {{
+		let tag = $crate::Tag::Composed($class, $id);
+		$crate::dsl::Matcher::explicit($depth, tag).optional::<_, $ty>($p)
+	}};
 	( $p:expr => $depth:expr, $ty:ty > ($class:expr, $id:expr) ) => {{
 		let tag = $crate::Tag::Composed($class, $id);
 		$crate::dsl::Matcher::with_tag($depth, tag).optional::<_, $ty>($p)
@@ -112,3 +579,412 @@ macro_rules! opt {
 		$crate::dsl::Matcher::new($depth).optional::<_, $ty>($p)
 	}}
 }
+
+/// Reads the value at `$depth`, tagged like the values of `req!`, or
+/// returns the value following `=` if it is absent. Under CER and DER the
+/// value may not be encoded if it equals the default, as in
+/// `def!(p => 1, Bool = Bool(false))`.
+#[macro_export]
+macro_rules! def {
+	( $p:expr => $depth:expr, $ty:ty > explicit ($class:expr, $id:expr) = $default:expr ) => {{
+		let tag = $crate::Tag::Composed($class, $id);
+		try!($crate::dsl::default::<_, $ty>($crate::dsl::Matcher::explicit($depth, tag), $p, $default))
+	}};
+	( $p:expr => $depth:expr, $ty:ty > ($class:expr, $id:expr) = $default:expr ) => {{
+		let tag = $crate::Tag::Composed($class, $id);
+		try!($crate::dsl::default::<_, $ty>($crate::dsl::Matcher::with_tag($depth, tag), $p, $default))
+	}};
+	( $p:expr => $depth:expr, $ty:ty = $default:expr ) => {{
+		try!($crate::dsl::default::<_, $ty>($crate::dsl::Matcher::new($depth), $p, $default))
+	}}
+}
+
+/// Iterates over the values at `$depth` up to the end of their parent, such
+/// as the elements of a SEQUENCE OF or SET OF read by `req!`. Elements may
+/// be tagged like the values of `req!`, and are collected with
+/// `try!(each!(...).collect::<Result<Vec<_>, _>>())`.
+#[macro_export]
+macro_rules! each {
+	( $p:expr => $depth:expr, $ty:ty > explicit ($class:expr, $id:expr) ) => {{
+		let tag = $crate::Tag::Composed($class, $id);
+		$crate::dsl::each::<_, $ty>($crate::dsl::Matcher::explicit($depth, tag), $p)
+	}};
+	( $p:expr => $depth:expr, $ty:ty > ($class:expr, $id:expr) ) => {{
+		let tag = $crate::Tag::Composed($class, $id);
+		$crate::dsl::each::<_, $ty>($crate::dsl::Matcher::with_tag($depth, tag), $p)
+	}};
+	( $p:expr => $depth:expr, $ty:ty ) => {{
+		$crate::dsl::each::<_, $ty>($crate::dsl::Matcher::new($depth), $p)
+	}}
+}
+
+/// Declares an enum for a CHOICE, with a variant holding the value of each
+/// alternative. Alternatives are tried in order, and may be tagged like the
+/// values of `req!`:
+///
+/// ```ignore
+/// choice! {
+///     pub enum GeneralName<'a> {
+///         Email(Octetstring<'a> > (Context, 1)),
+///         Directory(Sequence > (Context, 4)),
+///         Registered(Oid > (Context, 8)),
+///     }
+/// }
+///
+/// match try!(dsl::required::<_, GeneralName>(Matcher::new(2), &mut p)) { ... }
+/// ```
+///
+/// The enum implements `Decode`. Constructed alternatives such as
+/// `Sequence` only consume their own token, so their children are decoded
+/// following the choice.
+#[macro_export]
+macro_rules! choice {
+	( @matcher $depth:expr ) => {
+		$crate::dsl::Matcher::new($depth)
+	};
+	( @matcher $depth:expr, $class:expr, $id:expr ) => {
+		$crate::dsl::Matcher::with_tag($depth, $crate::Tag::Composed($class, $id))
+	};
+	( @matcher $depth:expr, explicit, $class:expr, $id:expr ) => {
+		$crate::dsl::Matcher::explicit($depth, $crate::Tag::Composed($class, $id))
+	};
+	( @impl $lt:tt, $name:ident, $self_ty:ty, $( $variant:ident ( $ty:ty $( > $($mode:ident)* ($class:expr, $id:expr) )* ) ),* ) => {
+		impl<$lt> $crate::dsl::Decode<$lt> for $self_ty {
+			fn decode<I>(matcher: $crate::dsl::Matcher, parser: &mut ::std::iter::Peekable<I>) -> ::std::result::Result<Self, $crate::Error>
+				where I: ::std::iter::Iterator<Item=::std::result::Result<$crate::Token<$lt>, $crate::Error>>
+			{
+				// Tags of a CHOICE are always explicit (X.680 31.2.9)
+				if let Some(tag) = matcher.tag() {
+					return $crate::dsl::required::<_, Self>($crate::dsl::Matcher::explicit(matcher.depth(), tag), parser);
+				}
+
+				let depth = matcher.depth();
+
+				let token = match parser.peek() {
+					Some(&Ok(ref token)) => token.clone(),
+					Some(&Err(ref why))  => return Err(why.clone()),
+					None                 => return Err($crate::Error::PrematureEof)
+				};
+
+				$(
+					let alternative = choice!(@matcher depth $(, $($mode,)* $class, $id)*);
+
+					if $crate::dsl::accepts::<$ty>(&alternative, &token) {
+						return $crate::dsl::required::<_, $ty>(alternative, parser).map($name::$variant);
+					}
+				)*
+
+				let why = $crate::Error::TokenMismatch{file: file!(), line: line!(), col: column!()};
+				Err(why.at($crate::Location::of(&token)))
+			}
+
+			fn accepts(matcher: &$crate::dsl::Matcher, token: &$crate::Token<$lt>) -> bool {
+				if let Some(tag) = matcher.tag() {
+					return $crate::dsl::accepts::<Self>(&$crate::dsl::Matcher::explicit(matcher.depth(), tag), token);
+				}
+
+				let depth = matcher.depth();
+				$( $crate::dsl::accepts::<$ty>(&choice!(@matcher depth $(, $($mode,)* $class, $id)*), token) )||*
+			}
+		}
+	};
+	( $(#[$attr:meta])* $vis:vis enum $name:ident<$lt:lifetime> { $( $variant:ident ( $ty:ty $( > $($mode:ident)* ($class:expr, $id:expr) )* ) ),* $(,)* } ) => {
+		$(#[$attr])*
+		$vis enum $name<$lt> {
+			$( $variant($ty) ),*
+		}
+
+		choice!(@impl $lt, $name, $name<$lt>, $( $variant($ty $( > $($mode)* ($class, $id) )*) ),*);
+	};
+	( $(#[$attr:meta])* $vis:vis enum $name:ident { $( $variant:ident ( $ty:ty $( > $($mode:ident)* ($class:expr, $id:expr) )* ) ),* $(,)* } ) => {
+		$(#[$attr])*
+		$vis enum $name {
+			$( $variant($ty) ),*
+		}
+
+		choice!(@impl 'a, $name, $name, $( $variant($ty $( > $($mode)* ($class, $id) )*) ),*);
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::Datelike;
+
+	use prelude::*;
+	use {Parser, Writer, Tag, Rules, Error};
+	use super::{Matcher, SequenceOf, SetOf, required, optional, end};
+
+	choice! {
+		#[derive(Debug, PartialEq)]
+		enum GeneralName<'a> {
+			Email(Octetstring<'a> > (Context, 1)),
+			Directory(Sequence > (Context, 4)),
+			Registered(Oid > (Context, 8)),
+		}
+	}
+
+	choice! {
+		#[derive(Debug, PartialEq)]
+		enum Validity {
+			Utc(Time > (Universal, 23)),
+			Generalized(Time > (Universal, 24)),
+		}
+	}
+
+	#[test]
+	fn choice() {
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| {
+			w.primitive(Tag::Composed(Context, 8), &[0x2A, 0x03]);
+			try!(w.constructed(Tag::Composed(Context, 4), |w| w.write(&Null)));
+			w.primitive(Tag::Composed(Context, 1), b"root@example.com");
+			w.primitive(Tag::GeneralizedTime, b"20500101000000Z");
+			w.primitive(Tag::Composed(Context, 2), b"example.com");
+			Ok(())
+		}).unwrap();
+
+		let data = w.into_bytes();
+		let mut p = Parser::new(&data).peekable();
+		required::<_, Sequence>(Matcher::new(0), &mut p).unwrap();
+
+		let name = required::<_, GeneralName>(Matcher::new(1), &mut p).unwrap();
+		assert_eq!(name, GeneralName::Registered(Oid::from_arcs(&[1, 2, 3]).unwrap()));
+
+		// Children of constructed alternatives follow
+		assert_eq!(required::<_, GeneralName>(Matcher::new(1), &mut p).unwrap(), GeneralName::Directory(Sequence));
+		required::<_, Null>(Matcher::new(2), &mut p).unwrap();
+
+		match required::<_, GeneralName>(Matcher::new(1), &mut p).unwrap() {
+			GeneralName::Email(email) => assert_eq!(&email.0[..], b"root@example.com"),
+			name => panic!("{:?}", name)
+		}
+
+		match required::<_, Validity>(Matcher::new(1), &mut p).unwrap() {
+			Validity::Generalized(time) => assert_eq!(time.year(), 2050),
+			time => panic!("{:?}", time)
+		}
+
+		// None of the alternatives match
+		assert_eq!(optional::<_, GeneralName>(Matcher::new(1), &mut p).unwrap(), None);
+
+		let why = required::<_, GeneralName>(Matcher::new(1), &mut p).unwrap_err();
+		match *why.kind() {
+			Error::TokenMismatch{..} => (),
+			ref why => panic!("{:?}", why)
+		}
+		assert_eq!(why.location().unwrap().tag, Some(Tag::Composed(Context, 2)));
+
+		p.next();
+		end(0, &mut p).unwrap();
+	}
+
+	#[test]
+	fn each() {
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| {
+			try!(w.constructed(Tag::Sequence, |w| {
+				try!(w.write(&Oid::from_arcs(&[2, 5, 4, 3]).unwrap()));
+				w.write(&Oid::from_arcs(&[2, 5, 4, 10]).unwrap())
+			}));
+			try!(w.constructed(Tag::Set, |w| {
+				w.primitive(Tag::Composed(Context, 0), &[0x01]);
+				w.primitive(Tag::Composed(Context, 0), &[0x02]);
+				Ok(())
+			}));
+			try!(w.constructed(Tag::Sequence, |w| w.write(&Null)));
+			w.write(&Int(7i32))
+		}).unwrap();
+
+		let data = w.into_bytes();
+		let mut p = Parser::new(&data).peekable();
+		required::<_, Sequence>(Matcher::new(0), &mut p).unwrap();
+
+		required::<_, Sequence>(Matcher::new(1), &mut p).unwrap();
+		let oids = each!(&mut p => 2, Oid).collect::<Result<Vec<_>, _>>().unwrap();
+		assert_eq!(oids, vec![Oid::from_arcs(&[2, 5, 4, 3]).unwrap(), Oid::from_arcs(&[2, 5, 4, 10]).unwrap()]);
+
+		required::<_, Set>(Matcher::new(1), &mut p).unwrap();
+		let ints = each!(&mut p => 2, Int<i32> > (Context, 0)).map(|i| i.unwrap().0).collect::<Vec<_>>();
+		assert_eq!(ints, vec![1, 2]);
+
+		// Errors end the iteration
+		required::<_, Sequence>(Matcher::new(1), &mut p).unwrap();
+		let mut ints = each!(&mut p => 2, Int<i32>);
+		assert!(ints.next().unwrap().is_err());
+		assert!(ints.next().is_none());
+
+		let mut p = Parser::new(&data).peekable();
+		p.next();
+		let names = required::<_, SequenceOf<Oid>>(Matcher::new(1), &mut p).unwrap();
+		assert_eq!(names.0.len(), 2);
+		assert!(required::<_, SequenceOf<Oid>>(Matcher::new(1), &mut p).is_err());
+	}
+
+	#[test]
+	fn tagging() {
+		// Version [0] EXPLICIT INTEGER, extensions [3] IMPLICIT SEQUENCE
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| {
+			try!(w.constructed(Tag::Composed(Context, 0), |w| w.write(&Int(2i32))));
+			try!(w.write(&Oid::from_arcs(&[2, 5, 4, 3]).unwrap()));
+			w.constructed(Tag::Composed(Context, 3), |w| {
+				try!(w.constructed(Tag::Composed(Context, 1), |w| w.write(&Null)));
+				w.constructed(Tag::Composed(Context, 1), |w| w.write(&Null))
+			})
+		}).unwrap();
+
+		let data = w.into_bytes();
+		let mut p = Parser::new(&data).peekable();
+		required::<_, Sequence>(Matcher::new(0), &mut p).unwrap();
+
+		let version = (|| Ok::<_, Error>(req!(&mut p => 1, Int<i32> > explicit (Context, 0))))().unwrap();
+		assert_eq!(version.0, 2);
+		assert!(opt!(&mut p => 1, Int<i32> > explicit (Context, 1)).is_none());
+		assert!(Matcher::with_tag(1, Tag::Composed(Context, 0)).optional::<_, Oid>(&mut p).is_none());
+		Matcher::new(1).required::<_, Oid>(&mut p).unwrap();
+
+		Matcher::with_tag(1, Tag::Composed(Context, 3)).required::<_, Sequence>(&mut p).unwrap();
+		let nulls = each!(&mut p => 2, Null > explicit (Context, 1));
+		assert_eq!(nulls.collect::<Result<Vec<_>, _>>().unwrap().len(), 2);
+		end(0, &mut p).unwrap();
+
+		// The value has to be the only child of its wrapper
+		let mut p = Parser::new(&data[2..]).peekable();
+		let matcher = Matcher::explicit(0, Tag::Composed(Context, 0));
+		assert_eq!(required::<_, Int<i32>>(matcher, &mut p).unwrap().0, 2);
+
+		let mut w = Writer::new();
+		w.constructed(Tag::Composed(Context, 0), |w| {
+			try!(w.write(&Int(2i32)));
+			w.write(&Null)
+		}).unwrap();
+
+		let data = w.into_bytes();
+		let mut p = Parser::new(&data).peekable();
+		let matcher = Matcher::explicit(0, Tag::Composed(Context, 0));
+		assert!(required::<_, Int<i32>>(matcher, &mut p).is_err());
+
+		choice! {
+			#[derive(Debug, PartialEq)]
+			enum Version {
+				Explicit(Int<i32> > explicit (Context, 0)),
+				Implicit(Int<i32> > (Context, 0)),
+			}
+		}
+
+		let mut p = Parser::new(&data[2..]).peekable();
+		assert!(super::optional::<_, Version>(Matcher::new(0), &mut p).unwrap().is_none());
+
+		// Tags of choices are explicit
+		let mut p = Parser::new(&[0xA5, 0x05, 0xA0, 0x03, 0x02, 0x01, 0x02]).peekable();
+		let matcher = Matcher::with_tag(0, Tag::Composed(Context, 5));
+		assert_eq!(required::<_, Version>(matcher, &mut p).unwrap(), Version::Explicit(Int(2)));
+
+		let mut p = Parser::new(&[0x85, 0x01, 0x02]).peekable();
+		let matcher = Matcher::with_tag(0, Tag::Composed(Context, 5));
+		assert!(required::<_, Version>(matcher, &mut p).is_err());
+
+		let mut p = Parser::new(&[0xA0, 0x03, 0x02, 0x01, 0x02, 0x80, 0x01, 0x03]).peekable();
+		assert_eq!(required::<_, Version>(Matcher::new(0), &mut p).unwrap(), Version::Explicit(Int(2)));
+		assert_eq!(required::<_, Version>(Matcher::new(0), &mut p).unwrap(), Version::Implicit(Int(3)));
+
+		// The value inside the wrapper is matched before anything is consumed
+		choice! {
+			#[derive(Debug, PartialEq)]
+			enum Flag {
+				Integer(Int<i32> > explicit (Context, 0)),
+				Boolean(Bool > explicit (Context, 0)),
+			}
+		}
+
+		let data = [0xA0, 0x03, 0x01, 0x01, 0xFF];
+		let mut p = Parser::new(&data).peekable();
+		let matcher = Matcher::explicit(0, Tag::Composed(Context, 0));
+		assert!(matcher.optional::<_, Int<i32>>(&mut p).is_none());
+		assert_eq!(required::<_, Flag>(Matcher::new(0), &mut p).unwrap(), Flag::Boolean(Bool(true)));
+
+		let data = [0xA0, 0x04, 0x05, 0x00, 0x05, 0x00];
+		let mut p = Parser::new(&data);
+		let matcher = Matcher::explicit(0, Tag::Composed(Context, 0));
+		let why = matcher.required_at::<_, Null>(&mut p, file!(), line!(), column!()).unwrap_err();
+		assert_eq!((why.kind(), why.location().unwrap().offset), (&Error::MalformedToken, 4));
+	}
+
+	#[test]
+	fn implicit() {
+		// [1] IMPLICIT IA5String and [0] IMPLICIT GeneralizedTime
+		let mut w = Writer::new();
+		w.primitive(Tag::Composed(Context, 1), b"root@example.com");
+		w.primitive(Tag::Composed(Context, 0), b"20500101000000Z");
+
+		let data = w.into_bytes();
+		let mut p = Parser::new(&data).peekable();
+
+		let email = Matcher::implicit(0, Tag::Composed(Context, 1), Tag::Ia5String);
+		assert_eq!(email.required::<_, String>(&mut p).unwrap(), "root@example.com");
+
+		let time = Matcher::implicit(0, Tag::Composed(Context, 0), Tag::GeneralizedTime);
+		assert_eq!(time.required::<_, Time>(&mut p).unwrap().year(), 2050);
+
+		// The universal tag decides how the value is checked
+		let mut p = Parser::new(&data).peekable();
+		let printable = Matcher::implicit(0, Tag::Composed(Context, 1), Tag::PrintableString);
+		assert_eq!(*printable.required::<_, String>(&mut p).unwrap_err().kind(), Error::MalformedToken);
+
+		let mut p = Parser::new(&data).peekable();
+		let int = Matcher::implicit(0, Tag::Composed(Context, 1), Tag::Ia5String);
+		assert!(int.optional::<_, Int<i32>>(&mut p).is_none());
+
+		// Segments of implicitly tagged strings keep their universal tag
+		let data = [0xA1, 0x80, 0x04, 0x02, b'a', b'b', 0x04, 0x01, b'c', 0x00, 0x00];
+		let mut p = Parser::with_rules(&data, Rules::Ber).peekable();
+		let segmented = Matcher::implicit(0, Tag::Composed(Context, 1), Tag::Ia5String);
+		assert_eq!(segmented.required::<_, String>(&mut p).unwrap(), "abc");
+	}
+
+	#[test]
+	fn default() {
+		fn critical(data: &[u8], rules: Rules) -> Result<Bool, Error> {
+			let mut p = Parser::with_rules(data, rules).peekable();
+			req!(&mut p => 0, Sequence);
+			let critical = def!(&mut p => 1, Bool = Bool(false));
+			req!(&mut p => 1, Octetstring);
+			Ok(critical)
+		}
+
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| w.write(&Octetstring(b"\x30\x00"[..].into()))).unwrap();
+		assert_eq!(critical(w.as_bytes(), Rules::Der), Ok(Bool(false)));
+
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| {
+			try!(w.write(&Bool(true)));
+			w.write(&Octetstring(b"\x30\x00"[..].into()))
+		}).unwrap();
+		assert_eq!(critical(w.as_bytes(), Rules::Der), Ok(Bool(true)));
+
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| {
+			try!(w.write(&Bool(false)));
+			w.write(&Octetstring(b"\x30\x00"[..].into()))
+		}).unwrap();
+
+		let why = critical(w.as_bytes(), Rules::Der).unwrap_err();
+		assert_eq!(*why.kind(), Error::EncodedDefault);
+		assert_eq!(why.location().unwrap().offset, 2);
+		assert_eq!(critical(w.as_bytes(), Rules::Ber), Ok(Bool(false)));
+	}
+
+	#[test]
+	fn set_of() {
+		let data = [0x31, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x02];
+		let ints = super::decode::<SetOf<Int<i32>>>(&data).unwrap();
+		assert_eq!(ints.0.iter().map(|i| i.0).collect::<Vec<_>>(), vec![1, 2]);
+
+		let data = [0x31, 0x06, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01];
+		let why = super::decode::<SetOf<Int<i32>>>(&data).unwrap_err();
+		assert_eq!(*why.kind(), Error::UnsortedSet);
+		assert_eq!(why.location().unwrap().path, vec![Tag::Set]);
+		assert!(super::decode_with_rules::<SetOf<Int<i32>>>(&data, Rules::Ber).is_ok());
+	}
+}
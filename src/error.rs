@@ -0,0 +1,286 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use {Parser, Token, Tag, Rules, Limits};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+	BufferTooShort,
+	InvalidMultipartTag,
+	MultipartTagOverflow,
+	MalformedToken,
+	TokenTooLong,
+	InputTooLong,
+	NestedTooDeep,
+	TokenMismatch{file: &'static str, line: u32, col: u32},
+	OutOfMemory,
+	UnsupportedString,
+	UnsupportedOid,
+	PrematureEof,
+	InvalidQuery,
+	/// A component equal to its DEFAULT value is encoded, which CER and DER
+	/// forbid (11.5)
+	EncodedDefault,
+	/// The children of a SET or SET OF are not in the order CER and DER
+	/// require (9.3, 10.3 and 11.6)
+	UnsortedSet,
+	/// An INTEGER does not fit the type it is decoded into
+	IntegerOverflow,
+	/// A GeneralizedTime holds a time which a certificate profile requires
+	/// to be a UTCTime
+	UtcTimeRequired,
+	Io(io::ErrorKind),
+	/// An error together with the position in the input it was caused at
+	Located(Box<(Error, Location)>),
+	/// An error raised while decoding a named field of a structure
+	Field{name: &'static str, error: Box<Error>}
+}
+
+/// The position of the token which caused an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+	/// Offset of the first identifier octet of the token
+	pub offset: usize,
+	pub depth: u8,
+	/// Tag of the token, if its identifier octets could be read
+	pub tag: Option<Tag>,
+	/// Tags of the constructed tokens enclosing the token, outermost first
+	pub path: Vec<Tag>,
+}
+
+impl Location {
+	/// The location of `token`. Tokens do not know their ancestors, so the
+	/// path is left empty until filled in by `Error::resolve_path`.
+	pub fn of(token: &Token) -> Location {
+		Location {
+			offset: token.offset,
+			depth: token.depth,
+			tag: Some(token.tag),
+			path: Vec::new(),
+		}
+	}
+}
+
+impl Error {
+	/// Attaches a location to the error, unless it already has one.
+	pub fn at(self, location: Location) -> Error {
+		match self.location() {
+			Some(_) => self,
+			None    => Error::Located(Box::new((self, location)))
+		}
+	}
+
+	/// Records that the error was raised while decoding the field `name`.
+	pub fn field(self, name: &'static str) -> Error {
+		Error::Field{name: name, error: Box::new(self)}
+	}
+
+	/// The error without its location and field names.
+	pub fn kind(&self) -> &Error {
+		match *self {
+			Error::Located(ref located) => located.0.kind(),
+			Error::Field{ref error, ..} => error.kind(),
+			ref why                     => why
+		}
+	}
+
+	pub fn location(&self) -> Option<&Location> {
+		match *self {
+			Error::Located(ref located) => Some(&located.1),
+			Error::Field{ref error, ..} => error.location(),
+			_                           => None
+		}
+	}
+
+	/// The names of the fields the error was raised in, outermost first.
+	pub fn fields(&self) -> Vec<&'static str> {
+		let mut fields = Vec::new();
+		let mut why = self;
+
+		loop {
+			match *why {
+				Error::Located(ref located) => why = &located.0,
+				Error::Field{name, ref error} => {
+					fields.push(name);
+					why = error;
+				},
+				_ => return fields
+			}
+		}
+	}
+
+	/// Fills in the ancestors of the offending token by walking `input`,
+	/// which the located offsets refer to. Errors raised while decoding a
+	/// single token only know the token itself, and parsers for a part of
+	/// the input only know the ancestors within it. Such errors have to be
+	/// resolved by the caller holding the whole input, as `dsl::decode`
+	/// does; errors with a complete path are returned unchanged.
+	pub fn resolve_path(self, input: &[u8]) -> Error {
+		let mut located = match self {
+			Error::Located(located)   => located,
+			Error::Field{name, error} => return error.resolve_path(input).field(name),
+			why                       => return why
+		};
+
+		if located.1.path.len() == located.1.depth as usize {
+			return Error::Located(located);
+		}
+
+		// The structure up to the offending token has been parsed before,
+		// so the most permissive rules suffice.
+		let limits = Limits{max_depth: u8::max_value(), ..Limits::new()};
+		let mut ancestors = Vec::new();
+
+		for token in Parser::with_limits(input, Rules::Ber, limits) {
+			let token = match token {
+				Ok(token) => token,
+				Err(why)  => {
+					// The offending token itself may not parse
+					match why.location() {
+						Some(location) if location.offset == located.1.offset => located.1.path = location.path.clone(),
+						_ => ()
+					}
+
+					break
+				}
+			};
+
+			if token.offset > located.1.offset {
+				break;
+			}
+
+			ancestors.truncate(token.depth as usize);
+
+			if token.offset == located.1.offset && token.depth == located.1.depth {
+				located.1.path = ancestors;
+				break;
+			}
+
+			ancestors.push(token.tag);
+		}
+
+		Error::Located(located)
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::Error::*;
+
+		match *self {
+			BufferTooShort       => write!(f, "input ends within a token"),
+			InvalidMultipartTag  => write!(f, "invalid multipart tag"),
+			MultipartTagOverflow => write!(f, "multipart tag too large"),
+			MalformedToken       => write!(f, "malformed token"),
+			TokenTooLong         => write!(f, "token too long"),
+			InputTooLong         => write!(f, "input too long"),
+			NestedTooDeep        => write!(f, "tokens nested too deeply"),
+			TokenMismatch{file, line, col} =>
+				write!(f, "unexpected token, expected at {}:{}:{}", file, line, col),
+			OutOfMemory          => write!(f, "value too large"),
+			UnsupportedString    => write!(f, "unsupported string type"),
+			UnsupportedOid       => write!(f, "unsupported object identifier"),
+			PrematureEof         => write!(f, "premature end of input"),
+			InvalidQuery         => write!(f, "invalid query"),
+			EncodedDefault       => write!(f, "default value encoded"),
+			UnsortedSet          => write!(f, "set not in canonical order"),
+			IntegerOverflow      => write!(f, "integer out of range"),
+			UtcTimeRequired      => write!(f, "GeneralizedTime used for a UTCTime"),
+			Io(kind)             => write!(f, "I/O error: {:?}", kind),
+			Field{name, ref error} => match **error {
+				Field{..} => write!(f, "{}.{}", name, error),
+				_         => write!(f, "{}: {}", name, error)
+			},
+			Located(ref located) => {
+				let (ref why, ref location) = **located;
+				try!(write!(f, "{} at offset {}", why, location.offset));
+
+				match location.tag {
+					Some(tag) => {
+						try!(write!(f, " ("));
+
+						for ancestor in &location.path {
+							try!(write!(f, "{}/", ancestor));
+						}
+
+						write!(f, "{}, depth {})", tag, location.depth)
+					},
+					None => write!(f, " (depth {})", location.depth)
+				}
+			}
+		}
+	}
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+	use {Parser, Tag, Class, Location};
+	use dsl::Matcher;
+	use types::{Int, Null};
+	use Error::*;
+
+	#[test]
+	fn display() {
+		assert_eq!(MalformedToken.to_string(), "malformed token");
+
+		let why = MalformedToken.at(Location{
+			offset: 7,
+			depth: 2,
+			tag: None,
+			path: vec![Tag::Sequence, Tag::Composed(Class::Application, 1)],
+		});
+		assert_eq!(why.to_string(), "malformed token at offset 7 (depth 2)");
+
+		// The first location is kept
+		let why = why.at(Location{offset: 0, depth: 0, tag: None, path: Vec::new()});
+		assert_eq!(why.location().unwrap().offset, 7);
+	}
+
+	#[test]
+	fn resolve_path() {
+		let data = [
+			0x30, 0x08,
+				0x05, 0x00,
+				0xA0, 0x04,
+					0x02, 0x02, 0x00, 0x05
+		];
+
+		let mut p = Parser::new(&data);
+		p.next();
+		Matcher::new(1).required::<_, Null>(&mut p).unwrap();
+		p.next();
+
+		// Padded integer (8.3.2)
+		let why = Matcher::new(2).required::<_, Int<i64>>(&mut p).unwrap_err();
+		assert_eq!(*why.kind(), MalformedToken);
+		assert_eq!(why.location().unwrap().path, vec![]);
+
+		let why = why.resolve_path(&data);
+		assert_eq!(why.location(), Some(&Location{
+			offset: 6,
+			depth: 2,
+			tag: Some(Tag::Int),
+			path: vec![Tag::Sequence, Tag::Composed(Class::Context, 0)],
+		}));
+		assert_eq!(why.to_string(), "malformed token at offset 6 (SEQUENCE/[0]/INTEGER, depth 2)");
+
+		// Parsers for a part of the input only know the ancestors within it
+		let data = [
+			0x30, 0x06,
+				0xA0, 0x04,
+					0x30, 0x02,
+						0x05, 0x01
+		];
+
+		let mut p = Parser::new(&data);
+		let root = p.next().unwrap().unwrap();
+		let why = p.descend(&root).filter_map(|t| t.err()).next().unwrap();
+		assert_eq!(why.location().unwrap().path, vec![Tag::Composed(Class::Context, 0), Tag::Sequence]);
+
+		let why = why.resolve_path(&data);
+		assert_eq!(why.location().unwrap().path, vec![Tag::Sequence, Tag::Composed(Class::Context, 0), Tag::Sequence]);
+	}
+}
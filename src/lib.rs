@@ -1,24 +1,16 @@
 extern crate num;
 extern crate chrono;
 
-pub use self::parser::{Parser, Token, Encoding, Class, Tag};
+pub use self::parser::{Parser, Token, Encoding, Class, Tag, Rules, Limits};
+pub use self::parser::{StreamParser, ReadParser, OwnedToken};
+pub use self::parser::{Node, Children};
+pub use self::writer::{Writer, Encode};
+pub use self::error::{Error, Location};
 pub mod types;
 pub mod dsl;
+pub mod query;
 pub mod prelude;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Error {
-	BufferTooShort,
-	InvalidMultipartTag,
-	MultipartTagOverflow,
-	MalformedToken,
-	TokenTooLong,
-	NestedTooDeep,
-	TokenMismatch{file: &'static str, line: u32, col: u32},
-	OutOfMemory,
-	UnsupportedString,
-	UnsupportedOid,
-	PrematureEof
-}
-
+mod error;
 mod parser;
+mod writer;
@@ -1,3 +1,4 @@
+#[derive(Clone)]
 pub struct Iter<'a> {
 	slice: &'a [u8],
 	i: usize,
@@ -19,6 +20,17 @@ impl<'a> Iter<'a> {
 		self.i
 	}
 
+	pub fn seek(&mut self, pos: usize) {
+		self.i = pos
+	}
+
+	pub fn rest(&self) -> &'a [u8] {
+		match self.slice.get(self.i..) {
+			Some(rest) => rest,
+			None       => &[]
+		}
+	}
+
 	pub fn subslice(&self, start: usize, end: usize) -> &'a [u8] {
 		&self.slice[start..end]
 	}
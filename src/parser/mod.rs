@@ -1,7 +1,15 @@
 mod iter;
 mod stack;
+mod stream;
+mod tree;
 
-use Error;
+pub use self::stream::{StreamParser, ReadParser, OwnedToken};
+pub use self::tree::{Node, Children};
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use {Error, Location};
 use Error::*;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -18,7 +26,46 @@ pub enum Encoding {
 	Constructed
 }
 
+/// The encoding rules a document is decoded under (X.690 8, 10)
 #[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Rules {
+	/// Basic Encoding Rules, accepting any valid encoding
+	Ber,
+	/// Canonical Encoding Rules, using indefinite lengths
+	Cer,
+	/// Distinguished Encoding Rules, using definite lengths
+	Der
+}
+
+/// Bounds on the input a parser accepts, to parse deeply nested documents
+/// or to restrict untrusted ones.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Limits {
+	/// Maximum number of constructed values a token may be nested in
+	pub max_depth: u8,
+	/// Maximum length of the body of a single token
+	pub max_token_length: usize,
+	/// Maximum length of the whole document
+	pub max_input_length: usize,
+}
+
+impl Limits {
+	pub fn new() -> Limits {
+		Limits {
+			max_depth: 32,
+			max_token_length: usize::max_value(),
+			max_input_length: usize::max_value(),
+		}
+	}
+}
+
+impl Default for Limits {
+	fn default() -> Limits {
+		Limits::new()
+	}
+}
+
+#[derive(Debug, Copy, Clone)]
 pub enum Tag {
 	Bool,
 	Int,
@@ -26,18 +73,66 @@ pub enum Tag {
 	Octetstring,
 	Null,
 	Oid,
+	ObjectDescriptor,
+	External,
+	Real,
+	Enumerated,
+	EmbeddedPdv,
 	Utf8String,
+	RelativeOid,
+	Time,
 	Sequence,
 	Set,
+	NumericString,
 	PrintableString,
 	T61String,
 	Ia5String,
 	UtcTime,
 	GeneralizedTime,
+	GraphicString,
 	VisibleString,
+	UniversalString,
+	BmpString,
+	Date,
+	TimeOfDay,
+	DateTime,
+	Duration,
+	OidIri,
+	RelativeOidIri,
 	Composed(Class, usize)
 }
 
+// Tags are equal if their class and number are, so that universal tags may
+// also be given as `Composed(Universal, id)`
+impl PartialEq for Tag {
+	fn eq(&self, other: &Tag) -> bool {
+		self.class() == other.class() && self.id() == other.id()
+	}
+}
+
+impl Eq for Tag {}
+
+// The canonical order of tags (X.680 8.6): universal, application, context
+// specific and private classes, each ordered by tag number.
+impl Ord for Tag {
+	fn cmp(&self, other: &Tag) -> Ordering {
+		let class = |tag: &Tag| match tag.class() {
+			Class::Universal   => 0,
+			Class::Application => 1,
+			Class::Context     => 2,
+			Class::Private     => 3,
+		};
+
+		(class(self), self.id()).cmp(&(class(other), other.id()))
+	}
+}
+
+impl PartialOrd for Tag {
+	fn partial_cmp(&self, other: &Tag) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
 const MULTIPART_ID: usize    = 31;
 
 impl Tag
@@ -76,28 +171,128 @@ impl Tag
 		};
 
 		Ok((encoding, match class {
-			Universal => match id {
-				 1 => Bool,
-				 2 => Int,
-				 3 => Bitstring,
-				 4 => Octetstring,
-				 5 => Null,
-				 6 => Oid,
-				12 => Utf8String,
-				16 => Sequence,
-				17 => Set,
-				19 => PrintableString,
-				20 => T61String,
-				22 => Ia5String,
-				23 => UtcTime,
-				24 => GeneralizedTime,
-				26 => VisibleString,
-				 _ => Composed(Universal, id),
-			},
+			Universal => Tag::universal(id),
 			_ => Composed(class, id)
 		}))
 	}
 
+	/// The universal tag numbered `id`, such as `Tag::Ia5String` for 22.
+	pub fn universal(id: usize) -> Tag {
+		use self::Tag::*;
+
+		match id {
+			 1 => Bool,
+			 2 => Int,
+			 3 => Bitstring,
+			 4 => Octetstring,
+			 5 => Null,
+			 6 => Oid,
+			 7 => ObjectDescriptor,
+			 8 => External,
+			 9 => Real,
+			10 => Enumerated,
+			11 => EmbeddedPdv,
+			12 => Utf8String,
+			13 => RelativeOid,
+			14 => Time,
+			16 => Sequence,
+			17 => Set,
+			18 => NumericString,
+			19 => PrintableString,
+			20 => T61String,
+			22 => Ia5String,
+			23 => UtcTime,
+			24 => GeneralizedTime,
+			25 => GraphicString,
+			26 => VisibleString,
+			28 => UniversalString,
+			30 => BmpString,
+			31 => Date,
+			32 => TimeOfDay,
+			33 => DateTime,
+			34 => Duration,
+			35 => OidIri,
+			36 => RelativeOidIri,
+			 _ => Composed(Class::Universal, id),
+		}
+	}
+
+	/// The class of the tag as encoded in the identifier octets.
+	pub fn class(&self) -> Class {
+		match *self {
+			Tag::Composed(class, _) => class,
+			_                       => Class::Universal
+		}
+	}
+
+	/// The tag number as encoded in the identifier octets.
+	pub fn id(&self) -> usize {
+		use self::Tag::*;
+
+		match *self {
+			Bool             =>  1,
+			Int              =>  2,
+			Bitstring        =>  3,
+			Octetstring      =>  4,
+			Null             =>  5,
+			Oid              =>  6,
+			ObjectDescriptor =>  7,
+			External         =>  8,
+			Real             =>  9,
+			Enumerated       => 10,
+			EmbeddedPdv      => 11,
+			Utf8String       => 12,
+			RelativeOid      => 13,
+			Time             => 14,
+			Sequence         => 16,
+			Set              => 17,
+			NumericString    => 18,
+			PrintableString  => 19,
+			T61String        => 20,
+			Ia5String        => 22,
+			UtcTime          => 23,
+			GeneralizedTime  => 24,
+			GraphicString    => 25,
+			VisibleString    => 26,
+			UniversalString  => 28,
+			BmpString        => 30,
+			Date             => 31,
+			TimeOfDay        => 32,
+			DateTime         => 33,
+			Duration         => 34,
+			OidIri           => 35,
+			RelativeOidIri   => 36,
+			Composed(_, id)  => id
+		}
+	}
+
+	/// Whether values of this tag may be split into segments using the
+	/// constructed encoding (8.6.4, 8.7.3 and 8.23.6).
+	pub fn is_string(&self) -> bool {
+		use self::Tag::*;
+
+		match *self {
+			Bitstring        |
+			Octetstring      |
+			ObjectDescriptor |
+			Utf8String       |
+			PrintableString  |
+			T61String        |
+			Ia5String        |
+			NumericString    |
+			GraphicString    |
+			VisibleString    |
+			UniversalString  |
+			BmpString        => true,
+			// VideotexString, GeneralString
+			Composed(Class::Universal, id) => match id {
+				21 | 27 => true,
+				_       => false
+			},
+			_ => false
+		}
+	}
+
 	// 8.1.2.4.2
 	fn read_multipart_tag<'a, I>(iter: &mut I) -> Result<usize, Error>
 		where I: Iterator<Item=&'a u8>
@@ -132,69 +327,165 @@ impl Tag
 	}
 }
 
-#[derive(Debug)]
+impl fmt::Display for Tag {
+	// The notation of X.680 8.4, as used in the ASN.1 module definitions
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::Tag::*;
+
+		let name = match *self {
+			Bool             => "BOOLEAN",
+			Int              => "INTEGER",
+			Bitstring        => "BIT STRING",
+			Octetstring      => "OCTET STRING",
+			Null             => "NULL",
+			Oid              => "OBJECT IDENTIFIER",
+			ObjectDescriptor => "ObjectDescriptor",
+			External         => "EXTERNAL",
+			Real             => "REAL",
+			Enumerated       => "ENUMERATED",
+			EmbeddedPdv      => "EMBEDDED PDV",
+			Utf8String       => "UTF8String",
+			RelativeOid      => "RELATIVE-OID",
+			Time             => "TIME",
+			Sequence         => "SEQUENCE",
+			Set              => "SET",
+			NumericString    => "NumericString",
+			PrintableString  => "PrintableString",
+			T61String        => "T61String",
+			Ia5String        => "IA5String",
+			UtcTime          => "UTCTime",
+			GeneralizedTime  => "GeneralizedTime",
+			GraphicString    => "GraphicString",
+			VisibleString    => "VisibleString",
+			UniversalString  => "UniversalString",
+			BmpString        => "BMPString",
+			Date             => "DATE",
+			TimeOfDay        => "TIME-OF-DAY",
+			DateTime         => "DATE-TIME",
+			Duration         => "DURATION",
+			OidIri           => "OID-IRI",
+			RelativeOidIri   => "RELATIVE-OID-IRI",
+			Composed(Class::Universal, id)   => return write!(f, "[UNIVERSAL {}]", id),
+			Composed(Class::Application, id) => return write!(f, "[APPLICATION {}]", id),
+			Composed(Class::Context, id)     => return write!(f, "[{}]", id),
+			Composed(Class::Private, id)     => return write!(f, "[PRIVATE {}]", id),
+		};
+
+		f.write_str(name)
+	}
+}
+
+#[derive(Debug, Clone)]
 pub struct Token<'a>{
 	pub enc: Encoding,
 	pub tag: Tag,
 	pub depth: u8,
+	/// Offset of the first identifier octet in the input of the parser
+	pub offset: usize,
+	pub rules: Rules,
+	/// Limits of the parser, which also apply when the body is parsed
+	pub limits: Limits,
 	pub header: &'a [u8],
 	pub body: &'a [u8],
 }
 
+const INDEFINITE_LENGTH: u8      = 0b10000000;
+const END_OF_CONTENTS: [u8; 2]   = [0, 0];
+/// The length CER splits strings into (9.2)
+const FRAGMENT_LENGTH: usize     = 1000;
+
 impl<'a> Token<'a> {
-	fn from_bytes<'b>(iter: &mut iter::Iter<'b>, depth: u8) -> Result<Token<'b>, Error>
+	fn from_bytes<'b>(iter: &mut iter::Iter<'b>, depth: u8, rules: Rules, limits: &Limits) -> Result<Token<'b>, Error>
 	{
-		const LENGTH_MASK: u8 = 0b01111111;
-
 		let hdr_start = iter.pos();
 
-		let (encoding, tag) = try!(Tag::from_bytes(iter));
+		let (encoding, tag, length) = try!(Self::read_header(iter, rules, limits));
 
-		// Length (8.1.3)
-		let length = {
-			let byte = try!(iter.next().ok_or(BufferTooShort));
-			let length = byte & LENGTH_MASK;
+		let pos = iter.pos();
 
-			if (byte >> 7) == 1u8 {
-				try!(Self::read_longform_length(length, iter))
-			} else {
-				length as usize
-			}
-		};
+		let end = match length {
+			Some(length) => {
+				let (min_remaining, _) = iter.size_hint();
 
-		let (min_remaining, _) = iter.size_hint();
+				if min_remaining < length {
+					return Err(BufferTooShort)
+				}
+
+				pos + length
+			},
+			None => try!(Self::find_end_of_contents(iter, depth, rules, limits))
+		};
 
-		if min_remaining < length {
-			return Err(BufferTooShort)
+		if end - pos > limits.max_token_length {
+			return Err(TokenTooLong)
 		}
 
-		let pos = iter.pos();
 		let header = iter.subslice(hdr_start, pos);
-		let body = iter.subslice(pos, pos + length);
+		let body = iter.subslice(pos, end);
 
 		Ok(Token{
 			enc: encoding,
 			tag: tag,
 			depth: depth,
+			offset: hdr_start,
+			rules: rules,
+			limits: *limits,
 			header: header,
 			body: body,
 		})
 	}
 
-	fn read_longform_length<'b, I>(num_bytes: u8, iter: &mut I) -> Result<usize, Error>
+	// Reads the identifier and length octets, returning no length for the
+	// indefinite form.
+	fn read_header<'b, I>(iter: &mut I, rules: Rules, limits: &Limits) -> Result<(Encoding, Tag, Option<usize>), Error>
+		where I: Iterator<Item=&'b u8>
+	{
+		const LENGTH_MASK: u8 = 0b01111111;
+
+		let (encoding, tag) = try!(Tag::from_bytes(iter));
+
+		// Length (8.1.3)
+		let length = {
+			let byte = *try!(iter.next().ok_or(BufferTooShort));
+			let length = byte & LENGTH_MASK;
+
+			if byte == INDEFINITE_LENGTH {
+				// 8.1.3.2 (a) and 10.1
+				if rules == Rules::Der || encoding == Encoding::Primitive {
+					return Err(MalformedToken)
+				}
+
+				None
+			} else if (byte >> 7) == 1u8 {
+				Some(try!(Self::read_longform_length(length, iter, rules)))
+			} else {
+				Some(length as usize)
+			}
+		};
+
+		// 9.1
+		// CER uses the indefinite form for all constructed values
+		if rules == Rules::Cer && encoding == Encoding::Constructed && length.is_some() {
+			return Err(MalformedToken)
+		}
+
+		match length {
+			Some(length) if length > limits.max_token_length => Err(TokenTooLong),
+			_ => Ok((encoding, tag, length))
+		}
+	}
+
+	fn read_longform_length<'b, I>(num_bytes: u8, iter: &mut I, rules: Rules) -> Result<usize, Error>
 		where I: Iterator<Item=&'b u8>
 	{
 		use std::mem::size_of;
 
 		const MIN_LONG_LENGTH: usize = 128;
 
-		if num_bytes == 0 {
-			// Indefinite form is forbidden (X.690 11/2008 8.1.3.6)
-			return Err(MalformedToken)
-		} else if num_bytes == 127 {
+		if num_bytes == 127 {
 			// X.690 11/2008 item 8.1.3.5 (c)
 			return Err(MalformedToken)
-		} else if num_bytes as usize > size_of::<usize>() {
+		} else if rules != Rules::Ber && num_bytes as usize > size_of::<usize>() {
 			return Err(TokenTooLong);
 		}
 
@@ -202,74 +493,375 @@ impl<'a> Token<'a> {
 		for _ in 0..num_bytes {
 			let byte = *try!(iter.next().ok_or(BufferTooShort)) as usize;
 
+			// BER permits leading zero octets beyond the size of usize
+			if length.leading_zeros() < 8 {
+				return Err(TokenTooLong);
+			}
+
 			length = (length << 8) | byte;
 		}
 
-		// 10.1
-		if length < MIN_LONG_LENGTH {
-			return Err(MalformedToken)
+		// 9.1 and 10.1
+		// CER and DER require the shortest possible length encoding
+		if rules != Rules::Ber {
+			if length < MIN_LONG_LENGTH || length.leading_zeros() as usize / 8 != size_of::<usize>() - num_bytes as usize {
+				return Err(MalformedToken)
+			}
 		}
 
 		Ok(length)
 	}
+
+	// 8.1.3.6
+	// Returns the position of the end-of-contents octets terminating an
+	// indefinite length value, which starts at the current position.
+	fn find_end_of_contents(iter: &iter::Iter, depth: u8, rules: Rules, limits: &Limits) -> Result<usize, Error> {
+		if depth >= limits.max_depth {
+			return Err(NestedTooDeep)
+		}
+
+		let mut scan = iter.clone();
+
+		loop {
+			if scan.rest().starts_with(&END_OF_CONTENTS) {
+				return Ok(scan.pos());
+			}
+
+			let token = try!(Token::from_bytes(&mut scan, depth + 1, rules, limits));
+			let end = scan.pos() + token.body.len() + token.trailer_len();
+
+			scan.seek(end);
+		}
+	}
+
+	/// Whether the token uses the indefinite length form, where the body
+	/// is followed by end-of-contents octets (8.1.3.6).
+	pub fn is_indefinite(&self) -> bool {
+		// Multipart identifiers end with the first octet with bit 8 unset
+		let id_len = match self.header.first() {
+			Some(byte) if byte & 0b00011111 == 0b00011111 => {
+				match self.header[1..].iter().position(|b| b & 0b10000000 == 0) {
+					Some(i) => i + 2,
+					None    => return false
+				}
+			},
+			Some(_) => 1,
+			None    => return false
+		};
+
+		self.header[id_len..] == [INDEFINITE_LENGTH]
+	}
+
+	/// The children of a constructed token, each along with its complete
+	/// encoding, such as the components of a SET.
+	pub fn children(&self) -> Result<Vec<(Token<'a>, &'a [u8])>, Error> {
+		let start = self.offset + self.header.len();
+		let mut children: Vec<Token<'a>> = Vec::new();
+
+		for token in Parser::scoped(self.body, start, self.depth + 1, self.rules, self.limits) {
+			let token = try!(token);
+
+			if token.depth == self.depth + 1 {
+				children.push(token);
+			}
+		}
+
+		// The end-of-contents octets of the token itself are not part of the
+		// last child
+		let mut encodings = Vec::new();
+		for token in &children {
+			let offset = token.offset - start;
+			let end = offset + token.header.len() + token.body.len() + token.trailer_len();
+			encodings.push(&self.body[offset..end]);
+		}
+
+		Ok(children.into_iter().zip(encodings).collect())
+	}
+
+	/// A parser for the body of the token, which yields its descendants at
+	/// their offset in the whole input, like `Parser::descend`.
+	pub fn parser(&self) -> Parser<'a> {
+		let offset = self.offset + self.header.len();
+		Parser::scoped(self.body, offset, self.depth + 1, self.rules, self.limits)
+	}
+
+	/// Splits the body of a constructed string into its primitive segments,
+	/// each of which must be tagged as `segment` (8.6.4, 8.7.3 and 8.23.6).
+	pub fn segments(&self, segment: Tag) -> Result<Vec<Token<'a>>, Error> {
+		let mut segments = Vec::new();
+
+		// Parsers of the constructed segments being split, innermost last
+		let mut parsers = vec![try!(self.segment_parser())];
+
+		loop {
+			let token = match parsers.last_mut() {
+				Some(parser) => parser.next(),
+				None         => break
+			};
+
+			let token = match token {
+				// Errors are reported for the string as a whole
+				Some(token) => try!(token.map_err(|why| why.kind().clone())),
+				None        => { parsers.pop(); continue }
+			};
+
+			if token.tag != segment {
+				return Err(MalformedToken)
+			}
+
+			match token.enc {
+				Encoding::Primitive   => segments.push(token),
+				Encoding::Constructed => parsers.push(try!(token.segment_parser()))
+			}
+		}
+
+		Ok(segments)
+	}
+
+	// A parser for the segments of a constructed string
+	fn segment_parser(&self) -> Result<Parser<'a>, Error> {
+		if self.depth >= self.limits.max_depth {
+			return Err(NestedTooDeep)
+		}
+
+		let offset = self.offset + self.header.len();
+		Ok(Parser::scoped(self.body, offset, self.depth + 1, self.rules, self.limits))
+	}
+
+	fn trailer_len(&self) -> usize {
+		match self.is_indefinite() {
+			true  => END_OF_CONTENTS.len(),
+			false => 0
+		}
+	}
 }
 
 pub struct Parser<'a> {
 	iter: iter::Iter<'a>,
+	offset: usize,
+	depth: u8,
+	rules: Rules,
+	limits: Limits,
 	err: bool,
-	stack: stack::FixedStack
+	stack: stack::Stack
 }
 
 impl<'a> Parser<'a> {
 	pub fn new(bytes: &'a [u8]) -> Parser<'a> {
+		Parser::with_rules(bytes, Rules::Der)
+	}
+
+	pub fn with_rules(bytes: &'a [u8], rules: Rules) -> Parser<'a> {
+		Parser::with_limits(bytes, rules, Limits::new())
+	}
+
+	pub fn with_limits(bytes: &'a [u8], rules: Rules, limits: Limits) -> Parser<'a> {
 		Parser {
 			iter: iter::Iter::new(bytes),
+			offset: 0,
+			depth: 0,
+			rules: rules,
+			limits: limits,
 			err: false,
-			stack: stack::FixedStack::new()
+			stack: stack::Stack::new(limits.max_depth)
+		}
+	}
+
+	// Parses a part of a larger input, which starts at `offset` and holds
+	// tokens nested `depth` levels deep
+	fn scoped(bytes: &'a [u8], offset: usize, depth: u8, rules: Rules, limits: Limits) -> Parser<'a> {
+		Parser {
+			offset: offset,
+			depth: depth,
+			stack: stack::Stack::new(limits.max_depth.saturating_sub(depth)),
+			..Parser::with_limits(bytes, rules, limits)
 		}
 	}
 
 	fn parse(&mut self) -> Result<Token<'a>, Error> {
 		use self::Encoding::*;
 
-		let token = try!(Token::from_bytes(&mut self.iter, self.stack.depth()));
+		if self.iter.len() > self.limits.max_input_length {
+			return Err(InputTooLong);
+		}
+
+		let depth = self.depth + self.stack.depth();
+		let mut token = try!(Token::from_bytes(&mut self.iter, depth, self.rules, &self.limits));
+		token.offset += self.offset;
 		let token_end = self.iter.pos() + token.body.len();
+		let indefinite = token.is_indefinite();
 
 		// If this is not a root token, make sure it fits within its parent
-		if let Some(parent_end) = self.stack.peek() {
-			if token_end > parent_end {
+		if let Some(parent) = self.stack.peek() {
+			if token_end + token.trailer_len() > parent.end {
 				return Err(MalformedToken);
 			}
 		}
 
-		match token.enc {
-			Primitive => {
-				// Skip contents for primitive tokens
-				for _ in 0..token.body.len() {
-					if let None = self.iter.next() {
-						return Err(BufferTooShort);
-					}
-				}
-			},
-			Constructed => {
-				if let Err(_) = self.stack.push(token_end) {
-					return Err(NestedTooDeep)
-				}
+		// 8.1.5
+		// End-of-contents octets may only terminate indefinite length values
+		if token.tag == Tag::Composed(Class::Universal, 0) {
+			return Err(MalformedToken);
+		}
+
+		let leaf = match token.enc {
+			Primitive => true,
+			// 10.2
+			// Constructed strings are forbidden under DER, BER treats them
+			// as a single value.
+			Constructed if token.tag.is_string() => match self.rules {
+				Rules::Der => return Err(MalformedToken),
+				_          => true
 			},
+			Constructed => false
+		};
+
+		if self.rules == Rules::Cer && token.tag.is_string() {
+			try!(Self::check_fragments(&token));
 		}
 
-		let pos = self.iter.pos();
+		if leaf {
+			// Skip contents for leaf tokens
+			self.iter.seek(token_end + token.trailer_len());
+		} else if let Err(_) = self.stack.push(token_end, indefinite, token.tag) {
+			return Err(NestedTooDeep)
+		}
 
-		// Discard parent tokens which end at this position
-		while let Some(parent_end) = self.stack.peek() {
-			if pos == parent_end {
-				self.stack.discard();
-			} else {
+		self.discard_frames();
+		Ok(token)
+	}
+
+	// Discards parent tokens which end at this position, along with their
+	// end-of-contents octets
+	fn discard_frames(&mut self) {
+		while let Some(parent) = self.stack.peek() {
+			if self.iter.pos() != parent.end {
 				break;
 			}
+
+			self.stack.discard();
+
+			if parent.indefinite {
+				let pos = self.iter.pos();
+				self.iter.seek(pos + END_OF_CONTENTS.len());
+			}
 		}
+	}
 
-		Ok(token)
+	/// Skips the remainder of the innermost constructed value which has not
+	/// been read completely. Directly after a constructed token has been
+	/// returned, this is the token itself.
+	pub fn skip_current(&mut self) {
+		if let Some(frame) = self.stack.peek() {
+			self.stack.discard();
+			self.skip_to(frame);
+		}
+	}
+
+	/// Skips the remainder of a constructed token returned by this parser,
+	/// including all of its descendants. Tokens which have been read
+	/// completely already are ignored.
+	pub fn skip_token(&mut self, token: &Token<'a>) {
+		if token.depth < self.depth {
+			return;
+		}
+
+		// Tokens of an enclosing parser may start before this one
+		let start = match token.offset.checked_sub(self.offset) {
+			Some(start) => start,
+			None        => return
+		};
+
+		let index = (token.depth - self.depth) as usize;
+		let end = start + token.header.len() + token.body.len();
+
+		let frame = match self.stack.frames().get(index) {
+			Some(frame) if frame.end == end && frame.tag == token.tag => *frame,
+			_ => return
+		};
+
+		self.stack.truncate(index);
+		self.skip_to(frame);
+	}
+
+	/// A parser for the body of `token`, which yields its children as well
+	/// as values encapsulated in primitive tokens, such as the extensions
+	/// within the octet strings of a certificate.
+	pub fn descend(&self, token: &Token<'a>) -> Parser<'a> {
+		let offset = token.offset + token.header.len();
+		Parser::scoped(token.body, offset, token.depth + 1, token.rules, self.limits)
+	}
+
+	// Moves past the end of a discarded frame
+	fn skip_to(&mut self, frame: stack::Frame) {
+		let trailer = match frame.indefinite {
+			true  => END_OF_CONTENTS.len(),
+			false => 0
+		};
+
+		self.iter.seek(frame.end + trailer);
+		self.discard_frames();
+	}
+
+	// 9.2
+	// CER splits strings longer than 1000 octets into primitive fragments of
+	// exactly 1000 octets, except for the last one.
+	fn check_fragments(token: &Token) -> Result<(), Error> {
+		use self::Encoding::*;
+
+		if token.enc == Primitive {
+			return match token.body.len() > FRAGMENT_LENGTH {
+				true  => Err(MalformedToken),
+				false => Ok(())
+			}
+		}
+
+		let fragment = Self::fragment_tag(token.tag);
+
+		let mut total = 0;
+		let mut last = FRAGMENT_LENGTH;
+
+		let offset = token.offset + token.header.len();
+
+		for segment in Parser::scoped(token.body, offset, token.depth + 1, Rules::Cer, token.limits) {
+			let segment = try!(segment.map_err(|why| why.kind().clone()));
+
+			if segment.tag != fragment || segment.enc != Primitive || last != FRAGMENT_LENGTH {
+				return Err(MalformedToken)
+			}
+
+			last = segment.body.len();
+			total += last;
+		}
+
+		match total > FRAGMENT_LENGTH {
+			true  => Ok(()),
+			false => Err(MalformedToken)
+		}
+	}
+
+	// The tag of the fragments of a string, which are octet strings except
+	// for those of bit strings
+	fn fragment_tag(tag: Tag) -> Tag {
+		match tag {
+			Tag::Bitstring => Tag::Bitstring,
+			_              => Tag::Octetstring
+		}
+	}
+
+	// Attaches the position of the token starting at `start` to an error
+	fn locate(&self, why: Error, start: usize) -> Error {
+		let mut iter = self.iter.clone();
+		iter.seek(start);
+
+		let tag = Tag::from_bytes(&mut iter).ok().map(|(_, tag)| tag);
+
+		why.at(Location{
+			offset: self.offset + start,
+			depth: self.depth + self.stack.depth(),
+			tag: tag,
+			path: self.stack.frames().iter().map(|frame| frame.tag).collect(),
+		})
 	}
 }
 
@@ -281,10 +873,11 @@ impl<'a> Iterator for Parser<'a> {
 			return None;
 		}
 
+		let start = self.iter.pos();
 		let result = self.parse();
 
 		self.err = result.is_err();
-		Some(result)
+		Some(result.map_err(|why| self.locate(why, start)))
 	}
 }
 
@@ -1,52 +1,51 @@
-pub struct FixedStack {
-	data: [usize; 10],
-	i: Option<u8>
+use Tag;
+
+#[derive(Copy, Clone)]
+pub struct Frame {
+	pub end: usize,
+	pub indefinite: bool,
+	pub tag: Tag
+}
+
+pub struct Stack {
+	data: Vec<Frame>,
+	max: u8
 }
 
-impl FixedStack {
-	pub fn new() -> FixedStack {
-		FixedStack{
-			data: [0; 10],
-			i: None
+impl Stack {
+	pub fn new(max: u8) -> Stack {
+		Stack{
+			data: Vec::new(),
+			max: max
 		}
 	}
 
 	pub fn depth(&self) -> u8 {
-		match self.i {
-			Some(n) => n + 1,
-			None => 0
-		}
+		self.data.len() as u8
 	}
 
-	pub fn peek(&self) -> Option<usize> {
-		match self.i {
-			Some(i) => Some(self.data[i as usize]),
-			_ => None
-		}
+	pub fn frames(&self) -> &[Frame] {
+		&self.data
 	}
 
-	pub fn discard(&mut self) {
-		if let Some(i) = self.i {
-			self.i = i.checked_sub(1)
-		}
+	pub fn peek(&self) -> Option<Frame> {
+		self.data.last().cloned()
 	}
 
-	pub fn push(&mut self, val: usize) -> Result<(), ()> {
-		let i = match self.i {
-			None => Some(0),
-			Some(i) => i.checked_add(1)
-		};
+	pub fn discard(&mut self) {
+		self.data.pop();
+	}
 
-		if let Some(i) = i {
-			if i as usize >= self.data.len() {
-				return Err(())
-			}
+	pub fn truncate(&mut self, depth: usize) {
+		self.data.truncate(depth);
+	}
 
-			self.i = Some(i);
-			self.data[i as usize] = val;
-			return Ok(())
+	pub fn push(&mut self, end: usize, indefinite: bool, tag: Tag) -> Result<(), ()> {
+		if self.data.len() >= self.max as usize {
+			return Err(())
 		}
 
-		Err(())
+		self.data.push(Frame{end: end, indefinite: indefinite, tag: tag});
+		Ok(())
 	}
 }
@@ -0,0 +1,674 @@
+use std::io::{self, Read};
+
+use {Parser, Token, Tag, Class, Encoding, Rules, Limits, Error, Location};
+use Error::*;
+use super::{iter, stack, END_OF_CONTENTS, FRAGMENT_LENGTH};
+
+const CHUNK_SIZE: usize = 4096;
+
+/// A token copied out of the input of a `StreamParser`.
+///
+/// Constructed tokens are emitted as soon as their header is complete,
+/// which leaves their body empty. Their children follow as separate tokens,
+/// including the segments of constructed strings under BER and CER.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedToken {
+	pub enc: Encoding,
+	pub tag: Tag,
+	pub depth: u8,
+	pub offset: usize,
+	pub rules: Rules,
+	pub limits: Limits,
+	pub header: Vec<u8>,
+	pub body: Vec<u8>,
+}
+
+impl OwnedToken {
+	/// The token borrowing from this one. Constructed strings are rejected
+	/// with `UnsupportedString`, as their segments are separate tokens and
+	/// their value would be empty.
+	pub fn as_token<'a>(&'a self) -> Result<Token<'a>, Error> {
+		if self.enc == Encoding::Constructed && self.tag.is_string() {
+			return Err(UnsupportedString);
+		}
+
+		Ok(Token{
+			enc: self.enc,
+			tag: self.tag,
+			depth: self.depth,
+			offset: self.offset,
+			rules: self.rules,
+			limits: self.limits,
+			header: &self.header,
+			body: &self.body,
+		})
+	}
+}
+
+struct Pending {
+	enc: Encoding,
+	tag: Tag,
+	depth: u8,
+	offset: usize,
+	header: Vec<u8>,
+	length: Option<usize>,
+}
+
+// The fragments of a constructed string read so far under CER (9.2)
+struct Fragments {
+	tag: Tag,
+	depth: u8,
+	last: usize,
+	total: usize,
+}
+
+/// An incremental parser which is fed the input in chunks.
+///
+/// Consumed input is discarded, and a token whose header has been read is
+/// not parsed again when its body arrives in a later chunk.
+pub struct StreamParser {
+	buf: Vec<u8>,
+	/// Position in `buf` of the first octet not consumed yet
+	read: usize,
+	/// Offset of the first octet not consumed yet in the whole input
+	pos: usize,
+	rules: Rules,
+	limits: Limits,
+	stack: stack::Stack,
+	pending: Option<Pending>,
+	fragments: Option<Fragments>,
+	err: Option<Error>,
+}
+
+impl StreamParser {
+	pub fn new() -> StreamParser {
+		StreamParser::with_rules(Rules::Der)
+	}
+
+	pub fn with_rules(rules: Rules) -> StreamParser {
+		StreamParser::with_limits(rules, Limits::new())
+	}
+
+	pub fn with_limits(rules: Rules, limits: Limits) -> StreamParser {
+		StreamParser {
+			buf: Vec::new(),
+			read: 0,
+			pos: 0,
+			rules: rules,
+			limits: limits,
+			stack: stack::Stack::new(limits.max_depth),
+			pending: None,
+			fragments: None,
+			err: None,
+		}
+	}
+
+	pub fn feed(&mut self, chunk: &[u8]) {
+		self.buf.extend_from_slice(chunk);
+	}
+
+	/// Returns the next token, or `None` if more input has to be fed to
+	/// complete it. Errors are final.
+	pub fn next_token(&mut self) -> Result<Option<OwnedToken>, Error> {
+		if let Some(ref why) = self.err {
+			return Err(why.clone());
+		}
+
+		let result = self.parse().map_err(|why| {
+			let tag = Tag::from_bytes(&mut self.rest().iter()).ok().map(|(_, tag)| tag);
+			self.locate(why, self.pos, tag)
+		});
+
+		if let Err(ref why) = result {
+			self.err = Some(why.clone());
+		}
+
+		result
+	}
+
+	/// Checks that the input fed so far ends after a complete document.
+	pub fn finish(&self) -> Result<(), Error> {
+		if let Some(ref why) = self.err {
+			return Err(why.clone());
+		}
+
+		// Definite length tokens ending here are complete, even though they
+		// are only discarded when the next token is read
+		let complete = self.stack.frames().iter().all(|frame| !frame.indefinite && frame.end == self.pos);
+
+		if self.rest().is_empty() && self.pending.is_none() && complete {
+			return Ok(());
+		}
+
+		let (offset, tag) = match self.pending {
+			Some(ref pending) => (pending.offset, Some(pending.tag)),
+			None => (self.pos, Tag::from_bytes(&mut self.rest().iter()).ok().map(|(_, tag)| tag))
+		};
+
+		Err(self.locate(PrematureEof, offset, tag))
+	}
+
+	fn parse(&mut self) -> Result<Option<OwnedToken>, Error> {
+		if self.pos + self.rest().len() > self.limits.max_input_length {
+			return Err(InputTooLong);
+		}
+
+		let pending = match self.pending.take() {
+			Some(pending) => pending,
+			None => {
+				if !try!(self.discard_frames()) {
+					return Ok(None);
+				}
+
+				match try!(self.read_header()) {
+					Some(pending) => pending,
+					None          => return Ok(None)
+				}
+			}
+		};
+
+		let body = match (pending.enc, pending.length) {
+			(Encoding::Constructed, length) => {
+				let end = self.pos + length.unwrap_or(0);
+
+				if let Err(_) = self.stack.push(end, length.is_none(), pending.tag) {
+					// The header has been consumed already
+					return Err(self.locate(NestedTooDeep, pending.offset, Some(pending.tag)));
+				}
+
+				Vec::new()
+			},
+			(Encoding::Primitive, Some(length)) => {
+				if self.rest().len() < length {
+					self.pending = Some(pending);
+					return Ok(None);
+				}
+
+				self.consume(length)
+			},
+			(Encoding::Primitive, None) => unreachable!()
+		};
+
+		Ok(Some(OwnedToken{
+			enc: pending.enc,
+			tag: pending.tag,
+			depth: pending.depth,
+			offset: pending.offset,
+			rules: self.rules,
+			limits: self.limits,
+			header: pending.header,
+			body: body,
+		}))
+	}
+
+	// Discards constructed tokens which end at the current position, along
+	// with their end-of-contents octets. Returns false if more input is
+	// needed to decide.
+	fn discard_frames(&mut self) -> Result<bool, Error> {
+		while let Some(frame) = self.stack.peek() {
+			if !frame.indefinite {
+				if frame.end != self.pos {
+					break;
+				}
+
+				self.stack.discard();
+				continue;
+			}
+
+			if self.rest().len() < END_OF_CONTENTS.len() {
+				// Might be the start of end-of-contents octets
+				return Ok(self.rest().first().map_or(false, |b| *b != 0));
+			}
+
+			if self.rest()[..END_OF_CONTENTS.len()] != END_OF_CONTENTS {
+				break;
+			}
+
+			self.stack.discard();
+			try!(self.end_fragments());
+
+			if self.exceeds_bound(END_OF_CONTENTS.len()) {
+				return Err(MalformedToken);
+			}
+
+			self.consume(END_OF_CONTENTS.len());
+		}
+
+		Ok(true)
+	}
+
+	fn read_header(&mut self) -> Result<Option<Pending>, Error> {
+		let (enc, tag, length, header_len) = {
+			let mut iter = iter::Iter::new(self.rest());
+
+			match Token::read_header(&mut iter, self.rules, &self.limits) {
+				Ok((enc, tag, length)) => (enc, tag, length, iter.pos()),
+				Err(BufferTooShort)    => return Ok(None),
+				Err(why)               => return Err(why)
+			}
+		};
+
+		// The token has to fit within its parents
+		if self.exceeds_bound(header_len + length.unwrap_or(0)) {
+			return Err(MalformedToken);
+		}
+
+		// 8.1.5
+		if tag == Tag::Composed(Class::Universal, 0) {
+			return Err(MalformedToken);
+		}
+
+		// 10.2
+		if self.rules == Rules::Der && enc == Encoding::Constructed && tag.is_string() {
+			return Err(MalformedToken);
+		}
+
+		if self.rules == Rules::Cer {
+			try!(self.check_fragment(enc, tag, length));
+		}
+
+		Ok(Some(Pending{
+			enc: enc,
+			tag: tag,
+			depth: self.stack.depth(),
+			offset: self.pos,
+			header: self.consume(header_len),
+			length: length,
+		}))
+	}
+
+	// Whether the next `len` octets extend past the innermost constructed
+	// token of definite length.
+	fn exceeds_bound(&self, len: usize) -> bool {
+		let bound = self.stack.frames().iter()
+			.rev()
+			.filter(|frame| !frame.indefinite)
+			.map(|frame| frame.end)
+			.next();
+
+		match bound {
+			Some(end) => self.pos + len > end,
+			None      => false
+		}
+	}
+
+	fn locate(&self, why: Error, offset: usize, tag: Option<Tag>) -> Error {
+		why.at(Location{
+			offset: offset,
+			depth: self.stack.depth(),
+			tag: tag,
+			path: self.stack.frames().iter().map(|frame| frame.tag).collect(),
+		})
+	}
+
+	// 9.2
+	// CER splits strings longer than 1000 octets into primitive fragments of
+	// exactly 1000 octets, except for the last one.
+	fn check_fragment(&mut self, enc: Encoding, tag: Tag, length: Option<usize>) -> Result<(), Error> {
+		let depth = self.stack.depth();
+		let length = length.unwrap_or(0);
+
+		if let Some(ref mut fragments) = self.fragments {
+			if depth != fragments.depth + 1 {
+				return Ok(());
+			}
+
+			if tag != fragments.tag || enc != Encoding::Primitive || fragments.last != FRAGMENT_LENGTH || length > FRAGMENT_LENGTH {
+				return Err(MalformedToken);
+			}
+
+			fragments.last = length;
+			fragments.total += length;
+			return Ok(());
+		}
+
+		if !tag.is_string() {
+			return Ok(());
+		}
+
+		match enc {
+			Encoding::Primitive if length > FRAGMENT_LENGTH => Err(MalformedToken),
+			Encoding::Primitive => Ok(()),
+			Encoding::Constructed => {
+				self.fragments = Some(Fragments {
+					tag: Parser::fragment_tag(tag),
+					depth: depth,
+					last: FRAGMENT_LENGTH,
+					total: 0,
+				});
+
+				Ok(())
+			}
+		}
+	}
+
+	// Checks the fragments of a constructed string once it has ended, which
+	// has to be longer than a single fragment
+	fn end_fragments(&mut self) -> Result<(), Error> {
+		let ended = match self.fragments {
+			Some(ref fragments) => fragments.depth == self.stack.depth(),
+			None                => false
+		};
+
+		if !ended {
+			return Ok(());
+		}
+
+		match self.fragments.take() {
+			Some(ref fragments) if fragments.total > FRAGMENT_LENGTH => Ok(()),
+			_ => Err(MalformedToken)
+		}
+	}
+
+	// The input which has not been consumed yet
+	fn rest(&self) -> &[u8] {
+		&self.buf[self.read..]
+	}
+
+	fn consume(&mut self, len: usize) -> Vec<u8> {
+		let consumed = self.buf[self.read..self.read + len].to_vec();
+		self.read += len;
+		self.pos += len;
+
+		// Consumed input is discarded once it makes up most of the buffer,
+		// so that each octet is moved only a few times
+		if self.read >= CHUNK_SIZE && self.read * 2 >= self.buf.len() {
+			self.buf.drain(..self.read);
+			self.read = 0;
+		}
+
+		consumed
+	}
+}
+
+impl Default for StreamParser {
+	fn default() -> StreamParser {
+		StreamParser::new()
+	}
+}
+
+/// Parses tokens from a reader, reading as much input as needed.
+pub struct ReadParser<R> {
+	reader: R,
+	parser: StreamParser,
+	done: bool,
+}
+
+impl<R: Read> ReadParser<R> {
+	pub fn new(reader: R) -> ReadParser<R> {
+		ReadParser::with_parser(reader, StreamParser::new())
+	}
+
+	pub fn with_parser(reader: R, parser: StreamParser) -> ReadParser<R> {
+		ReadParser {
+			reader: reader,
+			parser: parser,
+			done: false,
+		}
+	}
+}
+
+impl<R: Read> Iterator for ReadParser<R> {
+	type Item = Result<OwnedToken, Error>;
+
+	fn next(&mut self) -> Option<Result<OwnedToken, Error>> {
+		if self.done {
+			return None;
+		}
+
+		let mut chunk = [0u8; CHUNK_SIZE];
+
+		loop {
+			match self.parser.next_token() {
+				Ok(Some(token)) => return Some(Ok(token)),
+				Ok(None)        => (),
+				Err(why)        => {
+					self.done = true;
+					return Some(Err(why));
+				}
+			}
+
+			match self.reader.read(&mut chunk) {
+				Ok(0) => {
+					self.done = true;
+
+					return match self.parser.finish() {
+						Ok(())   => None,
+						Err(why) => Some(Err(why))
+					}
+				},
+				Ok(n) => self.parser.feed(&chunk[..n]),
+				Err(ref why) if why.kind() == io::ErrorKind::Interrupted => (),
+				Err(why) => {
+					self.done = true;
+					return Some(Err(Io(why.kind())));
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use {Parser, Writer, Tag, Rules, Limits, Location};
+	use Encoding::*;
+	use Error::*;
+	use super::{StreamParser, ReadParser, OwnedToken};
+
+	fn drain(p: &mut StreamParser, tokens: &mut Vec<OwnedToken>) {
+		while let Some(token) = p.next_token().unwrap() {
+			tokens.push(token);
+		}
+	}
+
+	#[test]
+	fn x509_google_bytewise() {
+		let data = include_bytes!("../../tests/certs/google.der");
+
+		let mut p = StreamParser::new();
+		let mut tokens = Vec::new();
+
+		for byte in data.iter() {
+			p.feed(&[*byte]);
+			drain(&mut p, &mut tokens);
+		}
+
+		p.finish().unwrap();
+
+		let expected: Vec<_> = Parser::new(data).map(|t| t.unwrap()).collect();
+		assert_eq!(tokens.len(), expected.len());
+
+		for (token, expected) in tokens.iter().zip(expected.iter()) {
+			assert_eq!(token.enc,    expected.enc);
+			assert_eq!(token.tag,    expected.tag);
+			assert_eq!(token.depth,  expected.depth);
+			assert_eq!(token.offset, expected.offset);
+			assert_eq!(token.header, expected.header);
+
+			if token.enc == Primitive {
+				assert_eq!(token.body, expected.body);
+			}
+		}
+	}
+
+	#[test]
+	fn need_more_data() {
+		let mut p = StreamParser::new();
+
+		p.feed(&[0x04, 0x82, 0x01]);
+		assert_eq!(p.next_token(), Ok(None));
+		assert_eq!(*p.finish().unwrap_err().kind(), PrematureEof);
+
+		p.feed(&[0x00]);
+		p.feed(&[0xAA; 200]);
+		assert_eq!(p.next_token(), Ok(None));
+
+		p.feed(&[0xAA; 56]);
+		let token = p.next_token().unwrap().unwrap();
+		assert_eq!(token.body.len(), 256);
+		assert_eq!(token.header, &[0x04, 0x82, 0x01, 0x00]);
+
+		assert_eq!(p.next_token(), Ok(None));
+		p.finish().unwrap();
+
+		// Finishing right after the last token of nested definite lengths
+		let mut p = StreamParser::new();
+		p.feed(&[0x30, 0x05, 0x30, 0x03, 0x02, 0x01, 0x05]);
+
+		for _ in 0..3 {
+			assert!(p.next_token().unwrap().is_some());
+		}
+
+		p.finish().unwrap();
+
+		let mut p = StreamParser::new();
+		p.feed(&[0x30, 0x05, 0x30, 0x03, 0x02, 0x01]);
+
+		for _ in 0..2 {
+			assert!(p.next_token().unwrap().is_some());
+		}
+
+		assert_eq!(*p.finish().unwrap_err().kind(), PrematureEof);
+	}
+
+	#[test]
+	fn malformed() {
+		let mut p = StreamParser::new();
+
+		// The child token is longer than the parent.
+		p.feed(&[0x30, 0x01, 0x04, 0x01]);
+		assert!(p.next_token().unwrap().is_some());
+		let why = p.next_token().unwrap_err();
+		assert_eq!(*why.kind(), MalformedToken);
+		assert_eq!(why.location(), Some(&Location{
+			offset: 2,
+			depth: 1,
+			tag: Some(Tag::Octetstring),
+			path: vec![Tag::Sequence],
+		}));
+
+		assert_eq!(p.next_token(), Err(why.clone()));
+		assert_eq!(p.finish(), Err(why));
+	}
+
+	#[test]
+	fn indefinite_length() {
+		let data = [
+			0x30, 0x80,
+				0x24, 0x80,
+					0x04, 0x01, 0xAA,
+				0x00, 0x00,
+				0x30, 0x03,
+					0x02, 0x01, 0x05,
+			0x00, 0x00,
+			0x05, 0x00
+		];
+
+		let mut p = StreamParser::with_rules(Rules::Ber);
+		let mut tokens = Vec::new();
+
+		for chunk in data.chunks(3) {
+			p.feed(chunk);
+			drain(&mut p, &mut tokens);
+		}
+
+		p.finish().unwrap();
+
+		let summary: Vec<_> = tokens.iter().map(|t| (t.enc, t.depth, t.body.len())).collect();
+		assert_eq!(summary, vec![
+			(Constructed, 0, 0),
+			(Constructed, 1, 0),
+			(Primitive,   2, 1),
+			(Constructed, 1, 0),
+			(Primitive,   2, 1),
+			(Primitive,   0, 0),
+		]);
+
+		assert!(tokens[0].as_token().unwrap().is_indefinite());
+		assert!(!tokens[3].as_token().unwrap().is_indefinite());
+
+		// The segments of constructed strings are separate tokens
+		assert_eq!(tokens[1].as_token().unwrap_err(), UnsupportedString);
+
+		// End-of-contents octets must not extend past a definite parent
+		let mut p = StreamParser::with_rules(Rules::Ber);
+		p.feed(&[0x30, 0x03, 0x30, 0x80, 0x00, 0x00]);
+		assert!(p.next_token().unwrap().is_some());
+		assert!(p.next_token().unwrap().is_some());
+		assert_eq!(*p.next_token().unwrap_err().kind(), MalformedToken);
+	}
+
+	#[test]
+	fn cer_fragments() {
+		let data: Vec<u8> = (0..9000).map(|i| i as u8).collect();
+
+		let mut w = Writer::with_rules(Rules::Cer);
+		w.primitive(Tag::Octetstring, &data);
+
+		let mut p = StreamParser::with_rules(Rules::Cer);
+		let mut tokens = Vec::new();
+
+		for chunk in w.as_bytes().chunks(700) {
+			p.feed(chunk);
+			drain(&mut p, &mut tokens);
+		}
+
+		p.finish().unwrap();
+
+		let contents: Vec<u8> = tokens[1..].iter().flat_map(|t| t.body.iter().cloned()).collect();
+		assert_eq!(tokens.len(), 10);
+		assert_eq!(contents, data);
+
+		// Primitive strings longer than a fragment
+		let mut p = StreamParser::with_rules(Rules::Cer);
+		p.feed(&[0x04, 0x82, 0x03, 0xE9]);
+		assert_eq!(*p.next_token().unwrap_err().kind(), MalformedToken);
+
+		// Fragments following a short one
+		let mut p = StreamParser::with_rules(Rules::Cer);
+		p.feed(&[0x24, 0x80, 0x04, 0x01, 0xAA, 0x04, 0x01, 0xBB]);
+		assert!(p.next_token().unwrap().is_some());
+		assert!(p.next_token().unwrap().is_some());
+		assert_eq!(*p.next_token().unwrap_err().kind(), MalformedToken);
+
+		// Strings short enough to be primitive
+		let mut p = StreamParser::with_rules(Rules::Cer);
+		p.feed(&[0x24, 0x80, 0x04, 0x01, 0xAA, 0x00, 0x00, 0x05, 0x00]);
+		assert!(p.next_token().unwrap().is_some());
+		assert!(p.next_token().unwrap().is_some());
+		assert_eq!(*p.next_token().unwrap_err().kind(), MalformedToken);
+	}
+
+	#[test]
+	fn limits() {
+		let limits = Limits{max_token_length: 10, ..Limits::new()};
+		let mut p = StreamParser::with_limits(Rules::Der, limits);
+
+		p.feed(&[0x04, 0x0B]);
+		assert_eq!(*p.next_token().unwrap_err().kind(), TokenTooLong);
+
+		let limits = Limits{max_input_length: 4, ..Limits::new()};
+		let mut p = StreamParser::with_limits(Rules::Der, limits);
+
+		p.feed(&[0x05, 0x00, 0x05, 0x00]);
+		drain(&mut p, &mut Vec::new());
+
+		p.feed(&[0x05]);
+		assert_eq!(*p.next_token().unwrap_err().kind(), InputTooLong);
+	}
+
+	#[test]
+	fn reader() {
+		let data = include_bytes!("../../tests/certs/google.der");
+
+		let tokens: Vec<_> = ReadParser::new(Cursor::new(&data[..])).map(|t| t.unwrap()).collect();
+		assert_eq!(tokens.len(), Parser::new(data).count());
+
+		let mut truncated = ReadParser::new(Cursor::new(&data[..100]));
+		let why = truncated.find(|t| t.is_err()).unwrap().unwrap_err();
+		assert_eq!(*why.kind(), PrematureEof);
+		assert!(truncated.next().is_none());
+	}
+}
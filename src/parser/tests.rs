@@ -1,4 +1,4 @@
-use {Tag, Parser, Token};
+use {Tag, Parser, Token, Rules, Limits, Location};
 use Encoding::*;
 use Class::*;
 use Tag::*;
@@ -219,3 +219,405 @@ fn x509_google() {
 		}
 	}
 }
+
+// Summarizes the tokens in data, or returns the error without its location
+fn summarize(data: &[u8], rules: Rules) -> Result<Vec<(Tag, u8, usize)>, ::Error> {
+	Parser::with_rules(data, rules)
+		.map(|t| t.map(|t| (t.tag, t.depth, t.body.len())).map_err(|why| why.kind().clone()))
+		.collect()
+}
+
+#[test]
+fn indefinite_length() {
+	let data = [
+		0x30, 0x80,
+			0x30, 0x80,
+				0x05, 0x00,
+			0x00, 0x00,
+			0x02, 0x01, 0x05,
+		0x00, 0x00,
+		0x01, 0x01, 0xFF
+	];
+
+	assert_eq!(summarize(&data, Rules::Ber).unwrap(), vec![
+		(Sequence, 0, 9),
+		(Sequence, 1, 2),
+		(Null,     2, 0),
+		(Int,      1, 1),
+		(Bool,     0, 1),
+	]);
+
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+	assert!(token.is_indefinite());
+	assert_eq!(token.header, &[0x30, 0x80]);
+
+	assert_eq!(summarize(&data, Rules::Der).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn missing_end_of_contents() {
+	let data = [0x30, 0x80, 0x02, 0x01, 0x05];
+	assert_eq!(summarize(&data, Rules::Ber).unwrap_err(), BufferTooShort);
+
+	// The inner sequence consumes the only end-of-contents octets
+	let data = [0x30, 0x80, 0x30, 0x80, 0x00, 0x00];
+	assert_eq!(summarize(&data, Rules::Ber).unwrap_err(), BufferTooShort);
+}
+
+#[test]
+fn invalid_indefinite_length() {
+	// Primitive values must use the definite form
+	let data = [0x04, 0x80, 0xAA, 0x00, 0x00];
+	assert_eq!(summarize(&data, Rules::Ber).unwrap_err(), MalformedToken);
+
+	// Stray end-of-contents octets
+	let data = [0x00, 0x00];
+	assert_eq!(summarize(&data, Rules::Ber).unwrap_err(), MalformedToken);
+
+	// End-of-contents octets outside of their parent
+	let data = [0x30, 0x02, 0x30, 0x80, 0x00, 0x00];
+	assert_eq!(summarize(&data, Rules::Ber).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn nonminimal_length() {
+	let short = [0x04, 0x81, 0x03, 0xAA, 0xBB, 0xCC];
+	assert_eq!(summarize(&short, Rules::Ber).unwrap(), vec![(Octetstring, 0, 3)]);
+	assert_eq!(summarize(&short, Rules::Der).unwrap_err(), MalformedToken);
+
+	let mut padded = vec![0x04, 0x82, 0x00, 0x80];
+	padded.extend_from_slice(&[0xAA; 128]);
+	assert_eq!(summarize(&padded, Rules::Ber).unwrap(), vec![(Octetstring, 0, 128)]);
+	assert_eq!(summarize(&padded, Rules::Der).unwrap_err(), MalformedToken);
+
+	let mut long = vec![0x04, 0x8A, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x80];
+	long.extend_from_slice(&[0xAA; 128]);
+	assert_eq!(summarize(&long, Rules::Ber).unwrap(), vec![(Octetstring, 0, 128)]);
+	assert_eq!(summarize(&long, Rules::Der).unwrap_err(), TokenTooLong);
+}
+
+#[test]
+fn constructed_string() {
+	let data = [
+		0x30, 0x80,
+			0x24, 0x80,
+				0x04, 0x02, 0xAA, 0xBB,
+				0x24, 0x03,
+					0x04, 0x01, 0xCC,
+			0x00, 0x00,
+			0x05, 0x00,
+		0x00, 0x00
+	];
+
+	// Segments are not visited by the parser
+	assert_eq!(summarize(&data, Rules::Ber).unwrap(), vec![
+		(Sequence,    0, 15),
+		(Octetstring, 1, 9),
+		(Null,        1, 0),
+	]);
+
+	let token = Parser::with_rules(&data, Rules::Ber).nth(1).unwrap().unwrap();
+	let segments = token.segments(Octetstring).unwrap();
+	assert_eq!(segments.iter().map(|s| s.body).collect::<Vec<_>>(), vec![&[0xAA, 0xBB][..], &[0xCC][..]]);
+
+	assert_eq!(token.segments(Bitstring).unwrap_err(), MalformedToken);
+
+	// 10.2
+	let data = [0x24, 0x03, 0x04, 0x01, 0xCC];
+	assert_eq!(summarize(&data, Rules::Der).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn cer_indefinite_length() {
+	let indefinite = [0x30, 0x80, 0x02, 0x01, 0x05, 0x00, 0x00];
+	assert_eq!(summarize(&indefinite, Rules::Cer).unwrap(), vec![(Sequence, 0, 3), (Int, 1, 1)]);
+
+	// 9.1
+	let definite = [0x30, 0x03, 0x02, 0x01, 0x05];
+	assert_eq!(summarize(&definite, Rules::Cer).unwrap_err(), MalformedToken);
+
+	let nonminimal = [0x30, 0x80, 0x02, 0x81, 0x01, 0x05, 0x00, 0x00];
+	assert_eq!(summarize(&nonminimal, Rules::Cer).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn cer_fragments() {
+	fn fragmented(lengths: &[usize]) -> Vec<u8> {
+		let mut data = vec![0x24, 0x80];
+
+		for len in lengths {
+			match *len {
+				0 ... 127 => data.extend_from_slice(&[0x04, *len as u8]),
+				_         => data.extend_from_slice(&[0x04, 0x82, (*len >> 8) as u8, *len as u8])
+			}
+			data.extend(::std::iter::repeat(0xAA).take(*len));
+		}
+
+		data.extend_from_slice(&[0x00, 0x00]);
+		data
+	}
+
+	let data = fragmented(&[1000, 1000, 1]);
+	assert_eq!(summarize(&data, Rules::Cer).unwrap(), vec![(Octetstring, 0, 2011)]);
+
+	// 9.2
+	let data = fragmented(&[999, 1000]);
+	assert_eq!(summarize(&data, Rules::Cer).unwrap_err(), MalformedToken);
+
+	let data = fragmented(&[1000]);
+	assert_eq!(summarize(&data, Rules::Cer).unwrap_err(), MalformedToken);
+
+	let mut data = vec![0x04, 0x82, 0x03, 0xE9];
+	data.extend(::std::iter::repeat(0xAA).take(1001));
+	assert_eq!(summarize(&data, Rules::Cer).unwrap_err(), MalformedToken);
+	assert_eq!(summarize(&data, Rules::Der).unwrap(), vec![(Octetstring, 0, 1001)]);
+}
+
+fn nested(depth: usize) -> Vec<u8> {
+	let mut data = vec![0x05, 0x00];
+
+	for _ in 0..depth {
+		let mut outer = vec![0x30, data.len() as u8];
+		outer.extend_from_slice(&data);
+		data = outer;
+	}
+
+	data
+}
+
+#[test]
+fn max_depth() {
+	let data = nested(32);
+	assert_eq!(summarize(&data, Rules::Der).unwrap().last(), Some(&(Null, 32, 0)));
+
+	let data = nested(33);
+	assert_eq!(summarize(&data, Rules::Der).unwrap_err(), NestedTooDeep);
+
+	let limits = Limits{max_depth: 100, ..Limits::new()};
+	let tokens: Vec<_> = Parser::with_limits(&data, Rules::Der, limits).map(|t| t.unwrap()).collect();
+	assert_eq!(tokens.last().unwrap().depth, 33);
+
+	let limits = Limits{max_depth: 2, ..Limits::new()};
+	let mut p = Parser::with_limits(&data, Rules::Der, limits);
+	assert!(p.next().unwrap().is_ok());
+	assert!(p.next().unwrap().is_ok());
+	assert_eq!(*p.next().unwrap().unwrap_err().kind(), NestedTooDeep);
+}
+
+#[test]
+fn max_depth_indefinite() {
+	let mut data = Vec::new();
+
+	for _ in 0..3 {
+		data.extend_from_slice(&[0x30, 0x80]);
+	}
+	for _ in 0..3 {
+		data.extend_from_slice(&[0x00, 0x00]);
+	}
+
+	let limits = Limits{max_depth: 2, ..Limits::new()};
+	let mut p = Parser::with_limits(&data, Rules::Ber, limits);
+	assert_eq!(*p.next().unwrap().unwrap_err().kind(), NestedTooDeep);
+
+	let limits = Limits{max_depth: 3, ..Limits::new()};
+	assert_eq!(Parser::with_limits(&data, Rules::Ber, limits).count(), 3);
+}
+
+#[test]
+fn max_lengths() {
+	let data = [0x30, 0x06, 0x04, 0x04, 0xAA, 0xBB, 0xCC, 0xDD];
+
+	let limits = Limits{max_token_length: 4, ..Limits::new()};
+	let mut p = Parser::with_limits(&data, Rules::Der, limits);
+	assert_eq!(*p.next().unwrap().unwrap_err().kind(), TokenTooLong);
+
+	let limits = Limits{max_token_length: 6, ..Limits::new()};
+	assert_eq!(Parser::with_limits(&data, Rules::Der, limits).filter(|t| t.is_ok()).count(), 2);
+
+	let limits = Limits{max_input_length: 7, ..Limits::new()};
+	let mut p = Parser::with_limits(&data, Rules::Der, limits);
+	assert_eq!(*p.next().unwrap().unwrap_err().kind(), InputTooLong);
+	assert!(p.next().is_none());
+}
+
+#[test]
+fn token_limits() {
+	// Segments and children are parsed with the limits of the parser
+	let data = [0x24, 0x80, 0x24, 0x80, 0x04, 0x01, 0xAA, 0x00, 0x00, 0x00, 0x00];
+
+	let limits = Limits{max_depth: 40, ..Limits::new()};
+	let token = Parser::with_limits(&data, Rules::Ber, limits).next().unwrap().unwrap();
+	assert_eq!(token.limits, limits);
+	assert_eq!(token.segments(Octetstring).unwrap().len(), 1);
+
+	let token = Token{limits: Limits{max_depth: 1, ..Limits::new()}, ..token};
+	assert_eq!(token.segments(Octetstring).unwrap_err(), NestedTooDeep);
+
+	let data = [0x30, 0x06, 0x04, 0x04, 0xAA, 0xBB, 0xCC, 0xDD];
+	let token = Token{limits: Limits{max_token_length: 2, ..Limits::new()}, ..Parser::new(&data).next().unwrap().unwrap()};
+	assert_eq!(*token.children().unwrap_err().kind(), TokenTooLong);
+}
+
+#[test]
+fn children() {
+	// The last child of an indefinite length token ends before the
+	// end-of-contents octets of the token
+	let data = [0x31, 0x80, 0x01, 0x01, 0xFF, 0x30, 0x80, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00];
+	let token = Parser::with_rules(&data, Rules::Cer).next().unwrap().unwrap();
+
+	let encodings: Vec<_> = token.children().unwrap().into_iter().map(|(_, encoding)| encoding).collect();
+	assert_eq!(encodings, [&data[2..5], &data[5..11]]);
+}
+
+#[test]
+fn error_location() {
+	let data = [
+		0x30, 0x0F,
+			0x30, 0x0D,
+				0xA3, 0x0B,
+					0x30, 0x09,
+						0x02, 0x01, 0x05,
+						0x24, 0x04, 0x04, 0x02, 0xAA, 0xBB
+	];
+
+	let why = Parser::new(&data).find(|t| t.is_err()).unwrap().unwrap_err();
+	assert_eq!(*why.kind(), MalformedToken);
+	assert_eq!(why.location(), Some(&Location{
+		offset: 11,
+		depth: 4,
+		tag: Some(Octetstring),
+		path: vec![Sequence, Sequence, Composed(Context, 3), Sequence],
+	}));
+
+	assert_eq!(why.to_string(), "malformed token at offset 11 (SEQUENCE/SEQUENCE/[3]/SEQUENCE/OCTET STRING, depth 4)");
+
+	// Errors within the header still carry the offset
+	let data = [0x30, 0x04, 0x05, 0x00, 0x1F, 0x80];
+	let why = Parser::new(&data).find(|t| t.is_err()).unwrap().unwrap_err();
+	assert_eq!(*why.kind(), InvalidMultipartTag);
+	assert_eq!(why.location().unwrap().offset, 4);
+	assert_eq!(why.location().unwrap().tag, None);
+	assert_eq!(why.location().unwrap().path, vec![Sequence]);
+}
+
+#[test]
+fn skip() {
+	let data = [
+		0x30, 0x80,
+			0x30, 0x80,
+				0x02, 0x01, 0x05,
+				0x30, 0x02,
+					0x05, 0x00,
+			0x00, 0x00,
+			0x01, 0x01, 0xFF,
+		0x00, 0x00,
+		0x05, 0x00
+	];
+
+	let mut p = Parser::with_rules(&data, Rules::Ber);
+	p.next();
+	let inner = p.next().unwrap().unwrap();
+	p.skip_token(&inner);
+	assert_eq!(p.map(|t| t.unwrap().tag).collect::<Vec<_>>(), vec![Bool, Null]);
+
+	// Tokens which have been read completely are not skipped again
+	let mut p = Parser::with_rules(&data, Rules::Ber);
+	let tokens: Vec<_> = p.by_ref().take(5).map(|t| t.unwrap()).collect();
+	p.skip_token(&tokens[1]);
+	p.skip_token(&tokens[2]);
+	assert_eq!(p.next().unwrap().unwrap().tag, Bool);
+
+	let mut p = Parser::with_rules(&data, Rules::Ber);
+	let outer = p.next().unwrap().unwrap();
+	p.next();
+	p.next();
+	p.skip_current();
+	assert_eq!(p.next().unwrap().unwrap().tag, Bool);
+
+	p.skip_token(&outer);
+	let null = p.next().unwrap().unwrap();
+	assert_eq!((null.tag, null.offset, null.depth), (Null, 18, 0));
+	assert!(p.next().is_none());
+
+	// Tokens starting before a descended parser are ignored
+	let data = [0x30, 0x08, 0x30, 0x02, 0x05, 0x00, 0x30, 0x02, 0x05, 0x00];
+	let tokens: Vec<_> = Parser::new(&data).map(|t| t.unwrap()).collect();
+
+	let mut p = Parser::new(&data).descend(&tokens[3]);
+	p.skip_token(&tokens[2]);
+	assert_eq!(p.next().unwrap().unwrap().offset, 8);
+}
+
+#[test]
+fn descend() {
+	let data = include_bytes!("../../tests/certs/google.der");
+
+	let mut p = Parser::new(data);
+	p.next();
+	let tbs = p.next().unwrap().unwrap();
+
+	let mut tbs_p = p.descend(&tbs);
+	let extensions = loop {
+		let token = tbs_p.next().unwrap().unwrap();
+
+		if token.tag == Composed(Context, 3) {
+			break token;
+		}
+
+		tbs_p.skip_token(&token);
+	};
+
+	assert_eq!(extensions.depth, 2);
+
+	let expected: Vec<_> = Parser::new(data)
+		.map(|t| t.unwrap())
+		.filter(|t| t.depth == 4 && t.offset > extensions.offset)
+		.map(|t| t.offset)
+		.collect();
+
+	let mut seq = p.descend(&extensions);
+	let list = seq.next().unwrap().unwrap();
+	let mut ext_p = seq.descend(&list);
+	let mut offsets = Vec::new();
+
+	while let Some(ext) = ext_p.next() {
+		let ext = ext.unwrap();
+		offsets.push(ext.offset);
+		ext_p.skip_token(&ext);
+	}
+
+	assert_eq!(offsets, expected);
+
+	// Values encapsulated in octet strings can be parsed in place
+	let mut ext_p = seq.descend(&list);
+	let ext = ext_p.next().unwrap().unwrap();
+	let value = ext_p.descend(&ext).find(|t| t.as_ref().unwrap().tag == Octetstring).unwrap().unwrap();
+	let inner = p.descend(&value).next().unwrap().unwrap();
+	assert_eq!(inner.depth, 6);
+	assert_eq!(inner.offset, value.offset + value.header.len());
+
+	p.skip_token(&tbs);
+	let alg = p.next().unwrap().unwrap();
+	assert_eq!((alg.tag, alg.depth), (Sequence, 1));
+	assert_eq!(alg.offset, tbs.offset + tbs.header.len() + tbs.body.len());
+}
+
+#[test]
+fn tag_equality() {
+	assert_eq!(Composed(Universal, 16), Sequence);
+	assert_eq!(UtcTime, Composed(Universal, 23));
+	assert!(Composed(Context, 16) != Sequence);
+	assert!(Composed(Universal, 30) != Composed(Private, 30));
+}
+
+#[test]
+fn tag_order() {
+	assert!(Bool < Int);
+	assert!(Sequence < Composed(Application, 0));
+	assert!(Composed(Application, 30) < Composed(Context, 0));
+	assert!(Composed(Context, 200) < Composed(Private, 1));
+
+	let mut tags = vec![Composed(Context, 1), Set, Composed(Private, 0), Oid, Composed(Context, 0)];
+	tags.sort();
+	assert_eq!(tags, vec![Oid, Set, Composed(Context, 0), Composed(Context, 1), Composed(Private, 0)]);
+}
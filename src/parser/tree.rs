@@ -0,0 +1,301 @@
+use std::rc::Rc;
+
+use {Token, Tag, Encoding, Rules, Limits, Error};
+use super::Parser;
+
+// The input a node and its siblings are parsed from
+#[derive(Debug, Copy, Clone)]
+struct Scope<'a> {
+	bytes: &'a [u8],
+	offset: usize,
+	depth: u8,
+	rules: Rules,
+	limits: Limits,
+}
+
+/// A token within a document, which knows its position among its siblings
+/// and its ancestors.
+///
+/// Nodes are created on demand while navigating the document, and borrow
+/// their contents from the input. Children are parsed each time they are
+/// requested, skipping over the descendants of preceding children.
+#[derive(Debug, Clone)]
+pub struct Node<'a> {
+	token: Token<'a>,
+	index: usize,
+	parent: Option<Rc<Node<'a>>>,
+	scope: Scope<'a>,
+}
+
+impl<'a> Node<'a> {
+	/// The values at the top level of a document.
+	pub fn roots(bytes: &'a [u8], rules: Rules) -> Children<'a> {
+		Node::roots_with_limits(bytes, rules, Limits::new())
+	}
+
+	pub fn roots_with_limits(bytes: &'a [u8], rules: Rules, limits: Limits) -> Children<'a> {
+		Children::new(Scope{
+			bytes: bytes,
+			offset: 0,
+			depth: 0,
+			rules: rules,
+			limits: limits,
+		}, None)
+	}
+
+	pub fn token(&self) -> &Token<'a> {
+		&self.token
+	}
+
+	pub fn tag(&self) -> Tag {
+		self.token.tag
+	}
+
+	/// The position of the node among its siblings.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+
+	pub fn parent(&self) -> Option<&Node<'a>> {
+		self.parent.as_ref().map(|parent| &**parent)
+	}
+
+	/// The tags of the ancestors of the node and of the node itself,
+	/// outermost first.
+	pub fn path(&self) -> Vec<Tag> {
+		let mut path = match self.parent {
+			Some(ref parent) => parent.path(),
+			None             => Vec::new()
+		};
+
+		path.push(self.token.tag);
+		path
+	}
+
+	/// Whether the node may have children. Constructed strings are treated
+	/// as a single value, their segments are available from the token.
+	pub fn is_leaf(&self) -> bool {
+		self.token.enc == Encoding::Primitive || self.token.tag.is_string()
+	}
+
+	pub fn children(&self) -> Children<'a> {
+		let bytes = match self.is_leaf() {
+			true  => &[][..],
+			false => self.token.body
+		};
+
+		Children::new(Scope{
+			bytes: bytes,
+			offset: self.token.offset + self.token.header.len(),
+			depth: self.token.depth + 1,
+			rules: self.token.rules,
+			limits: self.scope.limits,
+		}, Some(Rc::new(self.clone())))
+	}
+
+	/// The child at `index`, if there are enough children.
+	pub fn child(&self, index: usize) -> Option<Result<Node<'a>, Error>> {
+		self.children().nth(index)
+	}
+
+	/// The node itself and its siblings.
+	pub fn siblings(&self) -> Children<'a> {
+		Children::new(self.scope, self.parent.clone())
+	}
+
+	pub fn next_sibling(&self) -> Option<Result<Node<'a>, Error>> {
+		let end = self.token.offset + self.token.header.len() +
+			self.token.body.len() + self.token.trailer_len();
+
+		let mut siblings = self.siblings();
+		siblings.parser.iter.seek(end - self.scope.offset);
+		siblings.index = self.index + 1;
+
+		siblings.next()
+	}
+
+	pub fn prev_sibling(&self) -> Option<Result<Node<'a>, Error>> {
+		match self.index {
+			0     => None,
+			index => self.siblings().nth(index - 1)
+		}
+	}
+}
+
+/// Iterates over the children of a node, or the roots of a document.
+pub struct Children<'a> {
+	parser: Parser<'a>,
+	scope: Scope<'a>,
+	parent: Option<Rc<Node<'a>>>,
+	index: usize,
+}
+
+impl<'a> Children<'a> {
+	fn new(scope: Scope<'a>, parent: Option<Rc<Node<'a>>>) -> Children<'a> {
+		Children {
+			parser: Parser::scoped(scope.bytes, scope.offset, scope.depth, scope.rules, scope.limits),
+			scope: scope,
+			parent: parent,
+			index: 0,
+		}
+	}
+}
+
+impl<'a> Iterator for Children<'a> {
+	type Item = Result<Node<'a>, Error>;
+
+	fn next(&mut self) -> Option<Result<Node<'a>, Error>> {
+		let token = match self.parser.next() {
+			Some(Ok(token)) => token,
+			Some(Err(why))  => {
+				// The parser only knows the ancestors within its scope
+				return Some(Err(match (why, &self.parent) {
+					(Error::Located(mut located), &Some(ref parent)) => {
+						let mut path = parent.path();
+						path.extend(located.1.path.drain(..));
+						located.1.path = path;
+
+						Error::Located(located)
+					},
+					(why, _) => why
+				}))
+			}
+			None => return None
+		};
+
+		self.parser.skip_token(&token);
+
+		let node = Node{
+			token: token,
+			index: self.index,
+			parent: self.parent.clone(),
+			scope: self.scope,
+		};
+
+		self.index += 1;
+		Some(Ok(node))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use {Parser, Tag, Rules, Limits};
+	use Error::*;
+	use super::Node;
+
+	fn walk<'a>(node: Node<'a>, offsets: &mut Vec<(usize, u8)>) {
+		offsets.push((node.token().offset, node.token().depth));
+
+		for child in node.children() {
+			walk(child.unwrap(), offsets);
+		}
+	}
+
+	#[test]
+	fn x509_google() {
+		let data = include_bytes!("../../tests/certs/google.der");
+
+		let mut offsets = Vec::new();
+		for root in Node::roots(data, Rules::Der) {
+			walk(root.unwrap(), &mut offsets);
+		}
+
+		let expected: Vec<_> = Parser::new(data)
+			.map(|t| t.unwrap())
+			.map(|t| (t.offset, t.depth))
+			.collect();
+
+		assert_eq!(offsets, expected);
+	}
+
+	#[test]
+	fn navigate() {
+		let data = [
+			0x30, 0x0C,
+				0x30, 0x05,
+					0x02, 0x01, 0x05,
+					0x05, 0x00,
+				0x30, 0x00,
+				0x04, 0x01, 0xAA,
+			0x01, 0x01, 0xFF
+		];
+
+		let roots: Vec<_> = Node::roots(&data, Rules::Der).map(|n| n.unwrap().tag()).collect();
+		assert_eq!(roots, vec![Tag::Sequence, Tag::Bool]);
+
+		let root = Node::roots(&data, Rules::Der).next().unwrap().unwrap();
+		let children: Vec<_> = root.children().map(|n| n.unwrap().tag()).collect();
+		assert_eq!(children, vec![Tag::Sequence, Tag::Sequence, Tag::Octetstring]);
+
+		let null = root.child(0).unwrap().unwrap().child(1).unwrap().unwrap();
+		assert_eq!(null.tag(), Tag::Null);
+		assert_eq!(null.index(), 1);
+		assert_eq!(null.token().offset, 7);
+		assert_eq!(null.token().depth, 2);
+		assert_eq!(null.path(), vec![Tag::Sequence, Tag::Sequence, Tag::Null]);
+		assert_eq!(null.parent().unwrap().parent().unwrap().tag(), Tag::Sequence);
+
+		assert!(null.next_sibling().is_none());
+		assert_eq!(null.prev_sibling().unwrap().unwrap().tag(), Tag::Int);
+
+		let empty = root.child(1).unwrap().unwrap();
+		assert!(empty.children().next().is_none());
+
+		let string = empty.next_sibling().unwrap().unwrap();
+		assert_eq!(string.tag(), Tag::Octetstring);
+		assert_eq!(string.index(), 2);
+		assert_eq!(string.token().body, &[0xAA]);
+		assert!(string.is_leaf());
+
+		assert_eq!(root.next_sibling().unwrap().unwrap().tag(), Tag::Bool);
+		assert!(root.child(3).is_none());
+	}
+
+	#[test]
+	fn indefinite_length() {
+		let data = [
+			0x30, 0x80,
+				0x30, 0x80,
+					0x05, 0x00,
+				0x00, 0x00,
+				0x24, 0x80,
+					0x04, 0x01, 0xAA,
+				0x00, 0x00,
+				0x02, 0x01, 0x05,
+			0x00, 0x00
+		];
+
+		let root = Node::roots(&data, Rules::Ber).next().unwrap().unwrap();
+		let children: Vec<_> = root.children().map(|n| n.unwrap().tag()).collect();
+		assert_eq!(children, vec![Tag::Sequence, Tag::Octetstring, Tag::Int]);
+
+		let first = root.child(0).unwrap().unwrap();
+		assert_eq!(first.next_sibling().unwrap().unwrap().token().offset, 8);
+		assert!(first.child(0).unwrap().unwrap().is_leaf());
+		assert!(root.next_sibling().is_none());
+	}
+
+	#[test]
+	fn errors() {
+		let data = [
+			0x30, 0x07,
+				0x05, 0x00,
+				0x30, 0x03,
+					0x00, 0x00, 0xFF
+		];
+
+		// Invalid descendants are only found when visited
+		let root = Node::roots(&data, Rules::Der).next().unwrap().unwrap();
+		let inner = root.child(1).unwrap().unwrap();
+
+		let why = inner.child(0).unwrap().unwrap_err();
+		assert_eq!(*why.kind(), MalformedToken);
+		assert_eq!(why.location().unwrap().offset, 6);
+		assert_eq!(why.location().unwrap().path, vec![Tag::Sequence, Tag::Sequence]);
+
+		// Limits apply to the whole document
+		let limits = Limits{max_depth: 1, ..Limits::new()};
+		let root = Node::roots_with_limits(&data, Rules::Der, limits).next().unwrap().unwrap();
+		assert_eq!(*root.child(1).unwrap().unwrap_err().kind(), NestedTooDeep);
+	}
+}
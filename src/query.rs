@@ -0,0 +1,331 @@
+//! Queries locating values within a document.
+//!
+//! A query is a list of steps separated by `/`, each of which selects
+//! among the children of the values matched by the previous step, starting
+//! with the values at the top level of the document:
+//!
+//! * `2` selects the third child
+//! * `INTEGER`, `OCTET STRING` or `[3]` select children by their tag
+//! * `*` selects all children
+//! * `**` selects the values themselves and all of their descendants
+//!
+//! Tag and wildcard steps may be followed by `{2.5.29.17}`, which restricts
+//! them to constructed values whose first child is the given object
+//! identifier, and by `[1]`, which selects the second of the children
+//! matched by the step. For example, `SEQUENCE[0]/INTEGER[1]` finds the
+//! second integer within the first sequence, and
+//! `**/SEQUENCE{2.5.29.17}/OCTET STRING` the value of the subject
+//! alternative name extension of a certificate.
+
+use {Token, Tag, Class, Encoding, Rules, Error, Node};
+use Error::InvalidQuery;
+use types::{Oid, ToToken};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+	Descendants,
+	Children{tag: Option<Tag>, key: Option<Vec<u8>>, index: Option<usize>},
+}
+
+/// A compiled query, which can be run against any number of documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+	steps: Vec<Step>,
+}
+
+impl Query {
+	pub fn new(query: &str) -> Result<Query, Error> {
+		let query = query.trim();
+		let query = match query.starts_with('/') {
+			true  => &query[1..],
+			false => query
+		};
+
+		if query.is_empty() {
+			return Err(InvalidQuery);
+		}
+
+		let mut steps = Vec::new();
+
+		for step in query.split('/') {
+			steps.push(try!(Self::parse_step(step.trim())));
+		}
+
+		Ok(Query{steps: steps})
+	}
+
+	/// Returns the tokens matched by the query, in the order they appear in
+	/// the document.
+	pub fn find<'a>(&self, bytes: &'a [u8], rules: Rules) -> Result<Vec<Token<'a>>, Error> {
+		// The document itself is represented by None
+		let mut context: Vec<Option<Node<'a>>> = vec![None];
+
+		for step in &self.steps {
+			let mut matched = Vec::new();
+
+			for node in &context {
+				let children = match *node {
+					Some(ref node) => node.children(),
+					None           => Node::roots(bytes, rules)
+				};
+
+				match *step {
+					Step::Descendants => {
+						matched.push(node.clone());
+						try!(Self::descendants(children, &mut matched));
+					},
+					Step::Children{ref tag, ref key, index} => {
+						let mut n = 0;
+
+						for child in children {
+							let child = try!(child);
+
+							if !try!(Self::matches(&child, tag, key)) {
+								continue;
+							}
+
+							match index {
+								Some(index) if index != n => (),
+								_ => matched.push(Some(child))
+							}
+
+							n += 1;
+						}
+					}
+				}
+			}
+
+			// Overlapping values may be matched more than once
+			matched.sort_by_key(|node| node.as_ref().map(|node| node.token().offset));
+			matched.dedup_by_key(|node| node.as_ref().map(|node| node.token().offset));
+
+			context = matched;
+		}
+
+		Ok(context.into_iter()
+			.flatten()
+			.map(|node| node.token().clone())
+			.collect())
+	}
+
+	fn descendants<'a, I>(children: I, matched: &mut Vec<Option<Node<'a>>>) -> Result<(), Error>
+		where I: Iterator<Item=Result<Node<'a>, Error>>
+	{
+		for child in children {
+			let child = try!(child);
+			let grandchildren = child.children();
+
+			matched.push(Some(child));
+			try!(Self::descendants(grandchildren, matched));
+		}
+
+		Ok(())
+	}
+
+	fn matches(node: &Node, tag: &Option<Tag>, key: &Option<Vec<u8>>) -> Result<bool, Error> {
+		if let Some(tag) = *tag {
+			if node.tag() != tag {
+				return Ok(false);
+			}
+		}
+
+		let key = match *key {
+			Some(ref key) => key,
+			None          => return Ok(true)
+		};
+
+		if node.is_leaf() {
+			return Ok(false);
+		}
+
+		// Object identifiers have a single valid encoding (8.19.2)
+		match node.child(0) {
+			Some(first) => {
+				let first = try!(first);
+				let token = first.token();
+
+				Ok(token.tag == Tag::Oid && token.enc == Encoding::Primitive && token.body == &key[..])
+			},
+			None => Ok(false)
+		}
+	}
+
+	fn parse_step(step: &str) -> Result<Step, Error> {
+		if step == "**" {
+			return Ok(Step::Descendants);
+		}
+
+		if let Ok(index) = step.parse::<usize>() {
+			return Ok(Step::Children{tag: None, key: None, index: Some(index)});
+		}
+
+		// Context specific tags start with a bracket just like indices
+		let tag_len = match step.starts_with('[') {
+			true  => step.find(']').map(|i| i + 1),
+			false => step.find(|c| c == '{' || c == '[').or(Some(step.len()))
+		};
+
+		let (name, mut rest) = match tag_len {
+			Some(len) => (step[..len].trim(), &step[len..]),
+			None      => return Err(InvalidQuery)
+		};
+
+		let tag = match name {
+			"*"  => None,
+			name => Some(try!(parse_tag(name).ok_or(InvalidQuery)))
+		};
+
+		let mut key = None;
+		if rest.starts_with('{') {
+			let end = try!(rest.find('}').ok_or(InvalidQuery));
+			key = Some(try!(parse_oid(&rest[1..end])));
+			rest = &rest[end + 1..];
+		}
+
+		let mut index = None;
+		if rest.starts_with('[') && rest.ends_with(']') {
+			index = Some(try!(rest[1..rest.len() - 1].trim().parse().map_err(|_| InvalidQuery)));
+			rest = "";
+		}
+
+		match rest.is_empty() {
+			true  => Ok(Step::Children{tag: tag, key: key, index: index}),
+			false => Err(InvalidQuery)
+		}
+	}
+}
+
+// Parses a tag as it is displayed
+fn parse_tag(name: &str) -> Option<Tag> {
+	use Tag::*;
+
+	const NAMED: [Tag; 32] = [
+		Bool, Int, Bitstring, Octetstring, Null, Oid, ObjectDescriptor, External,
+		Real, Enumerated, EmbeddedPdv, Utf8String, RelativeOid, Time, Sequence,
+		Set, NumericString, PrintableString, T61String, Ia5String, UtcTime,
+		GeneralizedTime, GraphicString, VisibleString, UniversalString,
+		BmpString, Date, TimeOfDay, DateTime, Duration, OidIri, RelativeOidIri
+	];
+
+	if let Some(tag) = NAMED.iter().find(|tag| tag.to_string() == name) {
+		return Some(*tag);
+	}
+
+	if !name.starts_with('[') || !name.ends_with(']') {
+		return None;
+	}
+
+	let inner = &name[1..name.len() - 1];
+	let (class, id) = match inner.find(' ') {
+		Some(i) => {
+			let class = match &inner[..i] {
+				"UNIVERSAL"   => Class::Universal,
+				"APPLICATION" => Class::Application,
+				"PRIVATE"     => Class::Private,
+				_             => return None
+			};
+
+			(class, &inner[i + 1..])
+		},
+		None => (Class::Context, inner)
+	};
+
+	let id = match id.trim().parse() {
+		Ok(id) => id,
+		Err(_) => return None
+	};
+
+	match class {
+		Class::Universal => Some(NAMED.iter()
+			.find(|tag| tag.id() == id)
+			.cloned()
+			.unwrap_or(Composed(Class::Universal, id))),
+		class => Some(Composed(class, id))
+	}
+}
+
+// Parses an object identifier in dotted notation into its encoding
+fn parse_oid(oid: &str) -> Result<Vec<u8>, Error> {
+	let mut arcs = Vec::new();
+
+	for arc in oid.trim().split('.') {
+		arcs.push(try!(arc.parse::<u32>().map_err(|_| InvalidQuery)));
+	}
+
+	Oid::from_arcs(&arcs)
+		.and_then(|oid| oid.to_der())
+		.map_err(|_| InvalidQuery)
+}
+
+#[cfg(test)]
+mod tests {
+	use {Tag, Class, Rules};
+	use Error::*;
+	use super::Query;
+
+	fn find(query: &str, data: &[u8]) -> Vec<(Tag, usize)> {
+		Query::new(query).unwrap()
+			.find(data, Rules::Der).unwrap()
+			.iter()
+			.map(|t| (t.tag, t.offset))
+			.collect()
+	}
+
+	const DATA: [u8; 23] = [
+		0x30, 0x15,
+			0x02, 0x01, 0x01,
+			0x30, 0x0B,
+				0x06, 0x03, 0x55, 0x1D, 0x11,
+				0x01, 0x01, 0xFF,
+				0x04, 0x01, 0xAA,
+			0xA3, 0x03,
+				0x02, 0x01, 0x02
+	];
+
+	#[test]
+	fn indices() {
+		assert_eq!(find("0", &DATA), vec![(Tag::Sequence, 0)]);
+		assert_eq!(find("0/1/2", &DATA), vec![(Tag::Octetstring, 15)]);
+		assert_eq!(find("/SEQUENCE[0]/INTEGER", &DATA), vec![(Tag::Int, 2)]);
+		assert_eq!(find("SEQUENCE/[3]/INTEGER[0]", &DATA), vec![(Tag::Int, 20)]);
+		assert!(find("SEQUENCE/INTEGER[1]", &DATA).is_empty());
+		assert!(find("0/5", &DATA).is_empty());
+	}
+
+	#[test]
+	fn wildcards() {
+		assert_eq!(find("*/*", &DATA), vec![(Tag::Int, 2), (Tag::Sequence, 5), (Tag::Composed(Class::Context, 3), 18)]);
+		assert_eq!(find("**/INTEGER", &DATA), vec![(Tag::Int, 2), (Tag::Int, 20)]);
+		assert_eq!(find("**/INTEGER[1]", &DATA), vec![]);
+		assert_eq!(find("**/**/BOOLEAN", &DATA), vec![(Tag::Bool, 12)]);
+		assert_eq!(find("**", &DATA).len(), 8);
+	}
+
+	#[test]
+	fn oid_keys() {
+		assert_eq!(find("**/SEQUENCE{2.5.29.17}/OCTET STRING", &DATA), vec![(Tag::Octetstring, 15)]);
+		assert_eq!(find("**/*{2.5.29.17}[0]/2", &DATA), vec![(Tag::Octetstring, 15)]);
+		assert!(find("**/SEQUENCE{2.5.29.15}", &DATA).is_empty());
+	}
+
+	#[test]
+	fn x509_google() {
+		let data = include_bytes!("../tests/certs/google.der");
+
+		let names = find("0/0/**/SEQUENCE{2.5.29.17}/OCTET STRING", data);
+		assert_eq!(names.len(), 1);
+
+		let version = find("0/0/[0]/INTEGER", data);
+		assert_eq!(version.len(), 1);
+	}
+
+	#[test]
+	fn invalid() {
+		for query in &["", "/", "0//1", "FOO", "INTEGER{1.2", "INTEGER[x]", "*{5.1}", "[3", "INTEGER]"] {
+			assert_eq!(Query::new(query), Err(InvalidQuery), "{}", query);
+		}
+
+		assert!(Query::new("[APPLICATION 2]/[UNIVERSAL 16]/[PRIVATE 1]").is_ok());
+		assert_eq!(Query::new("[UNIVERSAL 16]"), Query::new("SEQUENCE"));
+	}
+}
@@ -0,0 +1,57 @@
+use num;
+
+pub use num::bigint::Sign;
+
+use {Token, Tag, Encoding, Error};
+use types::{TokenType, ToToken};
+
+/// An INTEGER of arbitrary size, such as an RSA modulus or the serial
+/// number of a certificate.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BigInt(pub num::BigInt);
+
+impl BigInt {
+	/// The integer with the given sign and big-endian magnitude, such as the
+	/// unsigned value of an EC coordinate.
+	pub fn from_bytes_be(sign: Sign, magnitude: &[u8]) -> BigInt {
+		BigInt(num::BigInt::from_bytes_be(sign, magnitude))
+	}
+
+	/// The sign and big-endian magnitude of the integer, without leading
+	/// zeros.
+	pub fn to_bytes_be(&self) -> (Sign, Vec<u8>) {
+		self.0.to_bytes_be()
+	}
+}
+
+impl From<i64> for BigInt {
+	fn from(value: i64) -> BigInt {
+		BigInt(num::BigInt::from(value))
+	}
+}
+
+impl<'a> TokenType<'a> for BigInt {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::Int
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token) -> Result<BigInt, Error> {
+		let body = try!(super::int_contents(token));
+		Ok(BigInt(num::BigInt::from_signed_bytes_be(body)))
+	}
+}
+
+impl ToToken for BigInt {
+	fn tag(&self) -> Tag {
+		Tag::Int
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		let bytes = self.0.to_signed_bytes_be();
+		Ok(super::trim_int(&bytes).to_vec())
+	}
+}
@@ -1,10 +1,12 @@
-use {Token, Tag, Error};
-use types::TokenType;
+use std::borrow::Cow;
+
+use {Token, Tag, Rules, Error};
+use types::{TokenType, ToToken};
 use Error::MalformedToken;
 use Encoding;
 
-#[derive(Debug)]
-pub struct Bitstring<'a>(&'a[u8], u8);
+#[derive(Debug, PartialEq)]
+pub struct Bitstring<'a>(Cow<'a, [u8]>, u8);
 
 impl<'a> TokenType<'a> for Bitstring<'a> {
 	fn matches(tag: Tag) -> bool {
@@ -16,14 +18,57 @@ impl<'a> TokenType<'a> for Bitstring<'a> {
 	}
 
 	fn from_token(token: &Token<'a>) -> Result<Bitstring<'a>, Error> {
+		if token.enc == Encoding::Constructed {
+			return Self::from_segments(token);
+		}
+
+		let (bytes, unused) = try!(Self::from_body(token.body, token.rules));
+		Ok(Bitstring(Cow::Borrowed(bytes), unused))
+	}
+}
+
+impl<'a> Bitstring<'a> {
+	/// Creates a bitstring from `bytes`, ignoring the `unused` least
+	/// significant bits of the last byte.
+	pub fn new(bytes: &'a [u8], unused: u8) -> Bitstring<'a> {
+		Bitstring(Cow::Borrowed(bytes), unused)
+	}
+
+	/// Creates the value of a BIT STRING with named bits, in which the bits
+	/// at `positions` are set. Its length ends with the last bit set, since
+	/// trailing 0 bits are omitted (11.2.2).
+	pub fn from_named_bits(positions: &[usize]) -> Bitstring<'static> {
+		let len_bits = positions.iter().max().map_or(0, |last| last + 1);
+		let len_bytes = (len_bits + 7) / 8;
+		let mut bytes = vec![0; len_bytes];
+
+		for pos in positions {
+			bytes[pos / 8] |= 1 << (7 - pos % 8);
+		}
+
+		Bitstring(Cow::Owned(bytes), (len_bytes * 8 - len_bits) as u8)
+	}
+
+	/// Checks that the value of a BIT STRING with named bits ends with a set
+	/// bit, as CER and DER require (11.2.2). Values without named bits may
+	/// end with 0 bits.
+	pub fn check_named_bits(&self) -> Result<(), Error> {
+		match self.len_bits() {
+			0                           => Ok(()),
+			len if self.is_set(len - 1) => Ok(()),
+			_                           => Err(MalformedToken)
+		}
+	}
+
+	fn from_body(body: &[u8], rules: Rules) -> Result<(&[u8], u8), Error> {
 		// 8.6.2.2 and 10.2
 		// At least one byte of body (which specifies unused bits in last byte)
 		// and of primitive encoding.
-		if token.body.len() < 1 {
+		if body.len() < 1 {
 			return Err(MalformedToken);
 		}
 
-		let unused = token.body[0];
+		let unused = body[0];
 
 		// 8.6.2.2
 		// DER mandates minimal encoding and with unused > 7 we could have
@@ -35,34 +80,53 @@ impl<'a> TokenType<'a> for Bitstring<'a> {
 		// 8.6.2.3
 		// An empty bitstring is of length 1 (for unused byte) with no unused
 		// bits.
-		if token.body.len() == 1 {
+		if body.len() == 1 {
 			return match unused {
-				0 => Ok(Bitstring(&[], 0)),
+				0 => Ok((&[], 0)),
 				_ => Err(MalformedToken)
 			}
 		}
 
-		// 11.2.1
-		// Unused bits must be set to 0
-		if unused > 0 {
-			let mask = (1 << unused) - 1;
+		if rules != Rules::Ber {
+			// 11.2.1
+			// Unused bits must be set to 0
+			if unused > 0 {
+				let mask = (1 << unused) - 1;
 
-			if token.body[token.body.len()-1] & mask != 0 {
-				return Err(MalformedToken);
+				if body[body.len()-1] & mask != 0 {
+					return Err(MalformedToken);
+				}
 			}
 		}
 
-		// 11.2.2
-		// Trailing 0 bits should be omitted
-		if token.body[token.body.len()-1] == 0 {
+		Ok((&body[1..], unused))
+	}
+
+	// 8.6.4
+	// Only the last segment of a constructed bitstring may have unused bits
+	fn from_segments(token: &Token<'a>) -> Result<Bitstring<'a>, Error> {
+		if token.rules == Rules::Der {
 			return Err(MalformedToken);
 		}
 
-		Ok(Bitstring(&token.body[1..], unused))
+		let segments = try!(token.segments(Tag::Bitstring));
+		let mut bytes = Vec::new();
+		let mut unused = 0;
+
+		for (i, segment) in segments.iter().enumerate() {
+			let (data, segment_unused) = try!(Self::from_body(segment.body, token.rules));
+
+			if segment_unused != 0 && i != segments.len() - 1 {
+				return Err(MalformedToken);
+			}
+
+			bytes.extend_from_slice(data);
+			unused = segment_unused;
+		}
+
+		Ok(Bitstring(Cow::Owned(bytes), unused))
 	}
-}
 
-impl<'a> Bitstring<'a> {
 	pub fn len_bits(&self) -> usize {
 		self.0.len() * 8 - (self.1 as usize)
 	}
@@ -80,3 +144,31 @@ impl<'a> Bitstring<'a> {
 		self.0[i] & mask != 0
 	}
 }
+
+impl<'a> ToToken for Bitstring<'a> {
+	fn tag(&self) -> Tag {
+		Tag::Bitstring
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		if self.1 > 7 || (self.0.len() == 0 && self.1 != 0) {
+			return Err(MalformedToken);
+		}
+
+		let unused = self.1;
+
+		let mut body = Vec::with_capacity(self.0.len() + 1);
+		body.push(unused);
+		body.extend_from_slice(&self.0);
+
+		// 11.2.1
+		// Unused bits are set to 0
+		if let Some(last) = body.last_mut() {
+			if unused > 0 {
+				*last &= !((1 << unused) - 1);
+			}
+		}
+
+		Ok(body)
+	}
+}
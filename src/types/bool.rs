@@ -1,9 +1,9 @@
 use {Token, Tag, Error};
-use types::TokenType;
+use types::{TokenType, ToToken};
 use Error::MalformedToken;
-use Encoding;
+use {Encoding, Rules};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Bool(pub bool);
 
 impl<'a> TokenType<'a> for Bool {
@@ -20,10 +20,27 @@ impl<'a> TokenType<'a> for Bool {
 			return Err(MalformedToken);
 		}
 
-		match token.body[0] {
-			0x00 => Ok(Bool(false)),
-			0xFF => Ok(Bool(true)),
-			_    => Err(MalformedToken)
+		// 8.2.2 and 11.1
+		// BER accepts any non-zero value as true
+		match (token.body[0], token.rules) {
+			(0x00, _)          => Ok(Bool(false)),
+			(0xFF, _)          => Ok(Bool(true)),
+			(_,    Rules::Ber) => Ok(Bool(true)),
+			_                  => Err(MalformedToken)
+		}
+	}
+}
+
+impl ToToken for Bool {
+	fn tag(&self) -> Tag {
+		Tag::Bool
+	}
+
+	// 11.1
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		match self.0 {
+			true  => Ok(vec![0xFF]),
+			false => Ok(vec![0x00])
 		}
 	}
 }
@@ -0,0 +1,266 @@
+use std::str;
+
+use chrono::{NaiveDate, NaiveTime, NaiveDateTime};
+
+use {Token, Tag, Encoding, Error};
+use types::{TokenType, ToToken};
+use Error::MalformedToken;
+
+// The time types are encoded as the characters of their value notation
+// (8.26), which uses the extended format of ISO 8601.
+
+/// A DATE, such as "2017-05-31".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date(pub NaiveDate);
+
+/// A TIME-OF-DAY, such as "23:59:59".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay(pub NaiveTime);
+
+/// A DATE-TIME, such as "2017-05-31T23:59:59".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime(pub NaiveDateTime);
+
+/// A DURATION, such as "P1Y2M10DT2H30M" or "P6W", of which the last
+/// component may have a fraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Duration<'a>(pub &'a str);
+
+/// A value of the TIME type, which may be any ISO 8601 time, interval or
+/// recurrence. Only its alphabet is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsoTime<'a>(pub &'a str);
+
+// Checks that `s` has the digits and separators of `pattern`, in which `n`
+// stands for a digit
+fn parse_fields(s: &str, pattern: &str) -> Result<Vec<u32>, Error> {
+	if s.len() != pattern.len() {
+		return Err(MalformedToken);
+	}
+
+	let mut fields = Vec::new();
+	let mut field: Option<u32> = None;
+
+	for (c, p) in s.chars().zip(pattern.chars()) {
+		match (c.to_digit(10), p) {
+			(Some(digit), 'n') => field = Some(field.unwrap_or(0) * 10 + digit),
+			(None, p) if p == c => if let Some(value) = field.take() {
+				fields.push(value);
+			},
+			_ => return Err(MalformedToken)
+		}
+	}
+
+	fields.extend(field);
+	Ok(fields)
+}
+
+fn date(s: &str) -> Result<NaiveDate, Error> {
+	let fields = try!(parse_fields(s, "nnnn-nn-nn"));
+	NaiveDate::from_ymd_opt(fields[0] as i32, fields[1], fields[2]).ok_or(MalformedToken)
+}
+
+fn time_of_day(s: &str) -> Result<NaiveTime, Error> {
+	let fields = try!(parse_fields(s, "nn:nn:nn"));
+
+	// Leap seconds are not representable
+	match fields[2] {
+		60 => Err(MalformedToken),
+		_  => NaiveTime::from_hms_opt(fields[0], fields[1], fields[2]).ok_or(MalformedToken)
+	}
+}
+
+impl<'a> TokenType<'a> for Date {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::Date
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token) -> Result<Date, Error> {
+		date(try!(str::from_utf8(token.body))).map(Date)
+	}
+}
+
+impl<'a> TokenType<'a> for TimeOfDay {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::TimeOfDay
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token) -> Result<TimeOfDay, Error> {
+		time_of_day(try!(str::from_utf8(token.body))).map(TimeOfDay)
+	}
+}
+
+impl<'a> TokenType<'a> for DateTime {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::DateTime
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token) -> Result<DateTime, Error> {
+		let s = try!(str::from_utf8(token.body));
+
+		match s.find('T') {
+			Some(i) => Ok(DateTime(NaiveDateTime::new(try!(date(&s[..i])), try!(time_of_day(&s[i + 1..]))))),
+			None    => Err(MalformedToken)
+		}
+	}
+}
+
+impl<'a> TokenType<'a> for Duration<'a> {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::Duration
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	// X.680 38.4.4
+	fn from_token(token: &Token<'a>) -> Result<Duration<'a>, Error> {
+		let s = try!(str::from_utf8(token.body));
+
+		if !s.starts_with('P') {
+			return Err(MalformedToken);
+		}
+
+		// Weeks may not be combined with other components
+		let (date, time) = match s[1..].find('T') {
+			Some(i) => (&s[1..i + 1], Some(&s[i + 2..])),
+			None    => (&s[1..], None)
+		};
+
+		let mut components = 0;
+		let mut fraction = false;
+
+		for &(part, designators) in &[(date, "YMWD"), (time.unwrap_or(""), "HMS")] {
+			let mut rest = part;
+			let mut allowed = designators;
+
+			while !rest.is_empty() {
+				let end = match rest.find(|c: char| !c.is_digit(10) && c != '.' && c != ',') {
+					Some(end) => end,
+					None      => return Err(MalformedToken)
+				};
+
+				let (number, designator) = (&rest[..end], rest[end..].chars().next().unwrap());
+
+				let position = match allowed.find(designator) {
+					Some(position) => position,
+					None           => return Err(MalformedToken)
+				};
+
+				let digits = number.trim_matches(|c: char| c.is_digit(10));
+				let valid_number = match digits {
+					""        => !number.is_empty(),
+					"." | "," => !number.starts_with(digits) && !number.ends_with(digits),
+					_         => false
+				};
+
+				// Only the last component may have a fraction
+				if !valid_number || fraction {
+					return Err(MalformedToken);
+				}
+
+				fraction = digits != "";
+				components += 1;
+
+				allowed = &allowed[position + 1..];
+				rest = &rest[end + 1..];
+			}
+		}
+
+		let weeks = date.contains('W');
+
+		if components == 0 || time == Some("") || (weeks && (components > 1 || time.is_some())) {
+			return Err(MalformedToken);
+		}
+
+		Ok(Duration(s))
+	}
+}
+
+impl<'a> TokenType<'a> for IsoTime<'a> {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::Time
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token<'a>) -> Result<IsoTime<'a>, Error> {
+		let s = try!(str::from_utf8(token.body));
+
+		let valid = !s.is_empty() && s.chars().all(|c| match c {
+			'0' ... '9' | '+' | '-' | ':' | '.' | ',' | '/' |
+			'T' | 'W' | 'Z' | 'P' | 'R' | 'Y' | 'M' | 'D' | 'H' | 'S' | 'C' | 'L' | 'E' => true,
+			_ => false
+		});
+
+		match valid {
+			true  => Ok(IsoTime(s)),
+			false => Err(MalformedToken)
+		}
+	}
+}
+
+impl ToToken for Date {
+	fn tag(&self) -> Tag {
+		Tag::Date
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.format("%Y-%m-%d").to_string().into_bytes())
+	}
+}
+
+impl ToToken for TimeOfDay {
+	fn tag(&self) -> Tag {
+		Tag::TimeOfDay
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.format("%H:%M:%S").to_string().into_bytes())
+	}
+}
+
+impl ToToken for DateTime {
+	fn tag(&self) -> Tag {
+		Tag::DateTime
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.format("%Y-%m-%dT%H:%M:%S").to_string().into_bytes())
+	}
+}
+
+impl<'a> ToToken for Duration<'a> {
+	fn tag(&self) -> Tag {
+		Tag::Duration
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.as_bytes().to_vec())
+	}
+}
+
+impl<'a> ToToken for IsoTime<'a> {
+	fn tag(&self) -> Tag {
+		Tag::Time
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.as_bytes().to_vec())
+	}
+}
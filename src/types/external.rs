@@ -0,0 +1,189 @@
+use std::iter::Peekable;
+
+use {Token, Tag, Class, Error, Location};
+use dsl::{Decode, Matcher, Any, required, optional, end};
+use types::{Sequence, Oid, Int, Null, String, Octetstring, Bitstring};
+use Error::{TokenMismatch, PrematureEof};
+
+/// A value of the EXTERNAL type, encoded with the sequence of X.690 8.18.1.
+#[derive(Debug, PartialEq)]
+pub struct External<'a> {
+	pub direct_reference: Option<Oid>,
+	pub indirect_reference: Option<Int<i64>>,
+	pub data_value_descriptor: Option<String<'a>>,
+	pub encoding: ExternalEncoding<'a>,
+}
+
+/// The data value of an EXTERNAL, as a single ASN.1 type, or in octets or
+/// bits of the transfer syntax.
+#[derive(Debug, PartialEq)]
+pub enum ExternalEncoding<'a> {
+	SingleType(Any<'a>),
+	OctetAligned(Octetstring<'a>),
+	Arbitrary(Bitstring<'a>),
+}
+
+/// A value of the EMBEDDED PDV type (8.19), holding the encoding of a value
+/// in the transfer syntax identified by `identification`.
+#[derive(Debug, PartialEq)]
+pub struct EmbeddedPdv<'a> {
+	pub identification: Identification,
+	pub data_value: Octetstring<'a>,
+}
+
+/// How the abstract and transfer syntaxes of an EMBEDDED PDV are identified
+/// (X.680 36.5).
+#[derive(Debug, PartialEq)]
+pub enum Identification {
+	Syntaxes { abstract_syntax: Oid, transfer: Oid },
+	Syntax(Oid),
+	PresentationContextId(Int<i64>),
+	ContextNegotiation { presentation_context_id: Int<i64>, transfer_syntax: Oid },
+	TransferSyntax(Oid),
+	Fixed,
+}
+
+fn context(depth: u8, id: usize) -> Matcher {
+	Matcher::with_tag(depth, Tag::Composed(Class::Context, id))
+}
+
+// The matcher of a value tagged with `tag` unless tagged implicitly
+fn outer(matcher: &Matcher, tag: Tag) -> Matcher {
+	Matcher::with_tag(matcher.depth(), matcher.tag().unwrap_or(tag))
+}
+
+fn peek<'a, I>(parser: &mut Peekable<I>) -> Result<Token<'a>, Error>
+	where I: Iterator<Item=Result<Token<'a>, Error>>
+{
+	match parser.peek() {
+		Some(&Ok(ref token)) => Ok(token.clone()),
+		Some(&Err(ref why))  => Err(why.clone()),
+		None                 => Err(PrematureEof)
+	}
+}
+
+fn mismatch(token: &Token) -> Error {
+	TokenMismatch{file: file!(), line: line!(), col: column!()}.at(Location::of(token))
+}
+
+impl<'a> Decode<'a> for External<'a> {
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<External<'a>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth();
+		try!(outer(&matcher, Tag::External).required::<_, Sequence>(parser));
+
+		let external = External {
+			direct_reference: try!(optional(Matcher::new(depth + 1), parser)),
+			indirect_reference: try!(optional(Matcher::new(depth + 1), parser)),
+			data_value_descriptor: try!(optional(Matcher::with_tag(depth + 1, Tag::ObjectDescriptor), parser)),
+			encoding: try!(required(Matcher::new(depth + 1), parser)),
+		};
+
+		try!(end(depth, parser));
+		Ok(external)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		outer(matcher, Tag::External).matches::<Sequence>(token)
+	}
+}
+
+impl<'a> Decode<'a> for ExternalEncoding<'a> {
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<ExternalEncoding<'a>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth();
+		let token = try!(peek(parser));
+
+		match token.tag {
+			Tag::Composed(Class::Context, 0) => {
+				let explicit = Matcher::explicit(depth, Tag::Composed(Class::Context, 0));
+				required(explicit, parser).map(ExternalEncoding::SingleType)
+			},
+			Tag::Composed(Class::Context, 1) => required(context(depth, 1), parser).map(ExternalEncoding::OctetAligned),
+			Tag::Composed(Class::Context, 2) => required(context(depth, 2), parser).map(ExternalEncoding::Arbitrary),
+			_ => Err(mismatch(&token))
+		}
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		token.depth == matcher.depth() && match token.tag {
+			Tag::Composed(Class::Context, 0...2) => true,
+			_                                    => false
+		}
+	}
+}
+
+impl<'a> Decode<'a> for EmbeddedPdv<'a> {
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<EmbeddedPdv<'a>, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth();
+		try!(outer(&matcher, Tag::EmbeddedPdv).required::<_, Sequence>(parser));
+
+		// The data-value-descriptor [1] is always absent (X.680 36.5)
+		let pdv = EmbeddedPdv {
+			identification: try!(required(Matcher::explicit(depth + 1, Tag::Composed(Class::Context, 0)), parser)),
+			data_value: try!(required(context(depth + 1, 2), parser)),
+		};
+
+		try!(end(depth, parser));
+		Ok(pdv)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		outer(matcher, Tag::EmbeddedPdv).matches::<Sequence>(token)
+	}
+}
+
+impl<'a> Decode<'a> for Identification {
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<Identification, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth();
+		let token = try!(peek(parser));
+
+		let id = match token.tag {
+			Tag::Composed(Class::Context, id) => id,
+			_ => return Err(mismatch(&token))
+		};
+
+		let identification = match id {
+			0 | 3 => {
+				try!(context(depth, id).required::<_, Sequence>(parser));
+
+				let identification = match id {
+					0 => Identification::Syntaxes {
+						abstract_syntax: try!(required(context(depth + 1, 0), parser)),
+						transfer: try!(required(context(depth + 1, 1), parser)),
+					},
+					_ => Identification::ContextNegotiation {
+						presentation_context_id: try!(required(context(depth + 1, 0), parser)),
+						transfer_syntax: try!(required(context(depth + 1, 1), parser)),
+					}
+				};
+
+				try!(end(depth, parser));
+				identification
+			},
+			1 => Identification::Syntax(try!(required(context(depth, 1), parser))),
+			2 => Identification::PresentationContextId(try!(required(context(depth, 2), parser))),
+			4 => Identification::TransferSyntax(try!(required(context(depth, 4), parser))),
+			5 => {
+				let Null = try!(required(context(depth, 5), parser));
+				Identification::Fixed
+			},
+			_ => return Err(mismatch(&token))
+		};
+
+		Ok(identification)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		token.depth == matcher.depth() && match token.tag {
+			Tag::Composed(Class::Context, 0...5) => true,
+			_                                    => false
+		}
+	}
+}
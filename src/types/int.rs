@@ -1,18 +1,91 @@
-use std::mem::size_of;
-use std::ops::{Shl, BitXor, BitOr};
-use num::traits::{Signed, NumCast, cast};
+use std::convert::TryFrom;
 
 use {Token, Tag, Encoding, Error};
-use types::TokenType;
+use types::{TokenType, ToToken};
 use Error::*;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Int<T>(pub T)
-	// Trait bound from hell
-	where T: Copy + Signed + NumCast + Shl<u8, Output = T> + BitXor<Output = T> + BitOr<Output = T>;
+	where T: Integer;
+
+/// The primitive integers an INTEGER can be decoded into. Values which do
+/// not fit, such as negative values of unsigned types, are rejected with
+/// `IntegerOverflow`.
+pub trait Integer: Copy {
+	/// Converts minimal two's complement contents (8.3.2).
+	fn from_contents(contents: &[u8]) -> Result<Self, Error>;
+
+	/// The minimal two's complement contents of the value (8.3.2).
+	fn to_contents(self) -> Vec<u8>;
+}
+
+macro_rules! signed {
+	( $( $ty:ty ),* ) => { $(
+		impl Integer for $ty {
+			fn from_contents(contents: &[u8]) -> Result<$ty, Error> {
+				if contents.len() > 16 {
+					return Err(IntegerOverflow);
+				}
+
+				// Sign extend from the leading octet
+				let mut value = contents[0] as i8 as i128;
+
+				for byte in &contents[1..] {
+					value = (value << 8) | *byte as i128;
+				}
+
+				<$ty>::try_from(value).map_err(|_| IntegerOverflow)
+			}
+
+			fn to_contents(self) -> Vec<u8> {
+				super::trim_int(&self.to_be_bytes()).to_vec()
+			}
+		}
+	)* }
+}
+
+macro_rules! unsigned {
+	( $( $ty:ty ),* ) => { $(
+		impl Integer for $ty {
+			fn from_contents(contents: &[u8]) -> Result<$ty, Error> {
+				if contents[0] & 0x80 != 0 {
+					return Err(IntegerOverflow);
+				}
+
+				// Values with the high bit set are preceded by a zero octet
+				let magnitude = match contents[0] {
+					0 => &contents[1..],
+					_ => contents
+				};
+
+				if magnitude.len() > 16 {
+					return Err(IntegerOverflow);
+				}
+
+				let mut value = 0u128;
+
+				for byte in magnitude {
+					value = (value << 8) | *byte as u128;
+				}
+
+				<$ty>::try_from(value).map_err(|_| IntegerOverflow)
+			}
+
+			fn to_contents(self) -> Vec<u8> {
+				let mut bytes = vec![0];
+				bytes.extend_from_slice(&self.to_be_bytes());
+
+				super::trim_int(&bytes).to_vec()
+			}
+		}
+	)* }
+}
+
+signed!(i8, i16, i32, i64, i128, isize);
+unsigned!(u8, u16, u32, u64, u128, usize);
 
 impl<'a, T> TokenType<'a> for Int<T>
-	where T: Copy + Signed + NumCast + Shl<u8, Output = T> + BitXor<Output = T> + BitOr<Output = T>
+	where T: Integer
 {
 	fn matches(tag: Tag) -> bool {
 		tag == Tag::Int
@@ -23,35 +96,53 @@ impl<'a, T> TokenType<'a> for Int<T>
 	}
 
 	fn from_token(token: &Token) -> Result<Int<T>, Error> {
-		if token.body.len() == 0 {
-			return Err(MalformedToken);
-		}
+		let contents = try!(super::int_contents(token));
+		T::from_contents(contents).map(Int)
+	}
+}
 
-		if token.body.len() > size_of::<T>() {
-			return Err(OutOfMemory);
-		}
+impl<T> ToToken for Int<T>
+	where T: Integer
+{
+	fn tag(&self) -> Tag {
+		Tag::Int
+	}
 
-		if token.body.len() > 1 {
-			// 8.3.2
-			// Ensure ints are encoded as short as possible
-			let leading = {
-				((token.body[0] as u16) << 1) | ((token.body[1] as u16) >> 7)
-			};
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.to_contents())
+	}
+}
 
-			if leading == 0b0000_0000_0 || leading == 0b1111_1111_1 {
-				return Err(MalformedToken)
-			}
-		}
+/// An ENUMERATED value, which is encoded like an INTEGER (8.4).
+#[derive(Debug, PartialEq)]
+pub struct Enumerated<T>(pub T)
+	where T: Integer;
 
-		let mut result = T::zero();
+impl<'a, T> TokenType<'a> for Enumerated<T>
+	where T: Integer
+{
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::Enumerated
+	}
 
-		for byte in token.body {
-			result = (result << 8u8) | cast(*byte).unwrap();
-		}
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
 
-		// Sign extend
-		let mask = T::one() << cast(token.body.len() * 8 - 1).unwrap();
-		Ok(Int((result ^ mask) - mask))
+	fn from_token(token: &Token) -> Result<Enumerated<T>, Error> {
+		let contents = try!(super::int_contents(token));
+		T::from_contents(contents).map(Enumerated)
 	}
 }
 
+impl<T> ToToken for Enumerated<T>
+	where T: Integer
+{
+	fn tag(&self) -> Tag {
+		Tag::Enumerated
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.to_contents())
+	}
+}
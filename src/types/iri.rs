@@ -0,0 +1,91 @@
+use std::str;
+
+use {Token, Tag, Encoding, Error};
+use types::{TokenType, ToToken};
+use Error::MalformedToken;
+
+/// An OID-IRI, such as "/ISO/Registration_Authority/19785.CBEFF".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OidIri<'a>(pub &'a str);
+
+/// A RELATIVE-OID-IRI, which is an OID-IRI without the leading solidus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelativeOidIri<'a>(pub &'a str);
+
+// X.680 34.3, X.660 7.5
+fn check_arcs(s: &str) -> Result<(), Error> {
+	for arc in s.split('/') {
+		let integer = arc.chars().all(|c| c.is_digit(10));
+
+		let valid = match integer {
+			true  => !arc.is_empty() && (arc == "0" || !arc.starts_with('0')),
+			false => !arc.starts_with('-') && !arc.ends_with('-') && arc.chars().all(|c| {
+				c.is_alphanumeric() || c == '-' || c == '.' || c == '_' || c == '~'
+			})
+		};
+
+		if !valid {
+			return Err(MalformedToken);
+		}
+	}
+
+	Ok(())
+}
+
+impl<'a> TokenType<'a> for OidIri<'a> {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::OidIri
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token<'a>) -> Result<OidIri<'a>, Error> {
+		let s = try!(str::from_utf8(token.body));
+
+		if !s.starts_with('/') {
+			return Err(MalformedToken);
+		}
+
+		try!(check_arcs(&s[1..]));
+		Ok(OidIri(s))
+	}
+}
+
+impl<'a> TokenType<'a> for RelativeOidIri<'a> {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::RelativeOidIri
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token<'a>) -> Result<RelativeOidIri<'a>, Error> {
+		let s = try!(str::from_utf8(token.body));
+
+		try!(check_arcs(s));
+		Ok(RelativeOidIri(s))
+	}
+}
+
+impl<'a> ToToken for OidIri<'a> {
+	fn tag(&self) -> Tag {
+		Tag::OidIri
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.as_bytes().to_vec())
+	}
+}
+
+impl<'a> ToToken for RelativeOidIri<'a> {
+	fn tag(&self) -> Tag {
+		Tag::RelativeOidIri
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.as_bytes().to_vec())
+	}
+}
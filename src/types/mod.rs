@@ -1,23 +1,40 @@
-pub use self::string::String;
-pub use self::oid::{Oid, ConstOid};
-pub use self::int::Int;
+pub use self::string::{String, T61String};
+pub use self::oid::{Oid, ConstOid, RelativeOid};
+pub use self::int::{Int, Integer, Enumerated};
+pub use self::bigint::{BigInt, Sign};
+pub use self::real::Real;
 pub use self::bool::Bool;
 pub use self::null::Null;
 pub use self::bitstring::Bitstring;
-pub use self::time::Time;
+pub use self::time::{Time, LenientTime, CenturyWindow};
+pub use self::validity::Validity;
+pub use self::date::{Date, TimeOfDay, DateTime, Duration, IsoTime};
+pub use self::iri::{OidIri, RelativeOidIri};
+pub use self::external::{External, ExternalEncoding, EmbeddedPdv, Identification};
 pub use self::octetstring::Octetstring;
 
 mod string;
+mod t61;
 #[macro_use]
 mod oid;
 mod int;
+mod bigint;
+mod real;
 mod bool;
 mod null;
 mod bitstring;
 mod time;
+mod validity;
+mod date;
+mod iri;
+mod external;
 mod octetstring;
 
-use {Token, Tag, Encoding, Error};
+use std::borrow::Cow;
+use std::iter;
+
+use {Token, Tag, Encoding, Rules, Error, Location};
+use Error::{MalformedToken, UnsortedSet};
 
 pub trait TokenType<'a> where Self: Sized {
 	fn from_token(token: &Token<'a>) -> Result<Self, Error>;
@@ -25,6 +42,14 @@ pub trait TokenType<'a> where Self: Sized {
 	fn encoding() -> Encoding;
 }
 
+/// The encoding counterpart of `TokenType`, producing the DER body of a
+/// primitive value.
+pub trait ToToken {
+	fn tag(&self) -> Tag;
+	fn to_der(&self) -> Result<Vec<u8>, Error>;
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Sequence;
 
 impl<'a> TokenType<'a> for Sequence {
@@ -41,6 +66,7 @@ impl<'a> TokenType<'a> for Sequence {
 	}
 }
 
+#[derive(Debug, PartialEq)]
 pub struct Set;
 
 impl<'a> TokenType<'a> for Set {
@@ -57,5 +83,111 @@ impl<'a> TokenType<'a> for Set {
 	}
 }
 
+impl Set {
+	/// Checks that the components of a SET are ordered by their tags, as
+	/// CER and DER require (9.3 and 10.3). Components with equal tags are
+	/// left to the decoder. Untagged CHOICE components are checked by the
+	/// tag of the chosen alternative, as in DER, whereas CER orders them by
+	/// the smallest tag of the CHOICE, so such SETs are not supported in CER.
+	pub fn check_components(token: &Token) -> Result<(), Error> {
+		let children = try!(token.children());
+
+		for pair in children.windows(2) {
+			if pair[1].0.tag < pair[0].0.tag {
+				return Err(UnsortedSet.at(Location::of(&pair[1].0)));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Checks that the elements of a SET OF are ordered by their encodings,
+	/// the shorter of which are padded with trailing zeros, as CER and DER
+	/// require (11.6).
+	pub fn check_elements(token: &Token) -> Result<(), Error> {
+		let children = try!(token.children());
+
+		for pair in children.windows(2) {
+			let (previous, next) = (pair[0].1, pair[1].1);
+			let len = previous.len().max(next.len());
+
+			let padded = |bytes: &[u8]| bytes.iter().cloned().chain(iter::repeat(0)).take(len).collect::<Vec<_>>();
+
+			if padded(next) < padded(previous) {
+				return Err(UnsortedSet.at(Location::of(&pair[1].0)));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+// Returns the contents of a string token, concatenating the segments of the
+// constructed encoding permitted by BER (8.7.3 and 8.23.6).
+fn string_contents<'a>(token: &Token<'a>) -> Result<Cow<'a, [u8]>, Error> {
+	match token.enc {
+		Encoding::Primitive   => Ok(Cow::Borrowed(token.body)),
+		Encoding::Constructed => {
+			if token.rules == Rules::Der {
+				return Err(MalformedToken);
+			}
+
+			let segments = try!(token.segments(Tag::Octetstring));
+
+			// A single segment is borrowed
+			if segments.len() == 1 {
+				return Ok(Cow::Borrowed(segments[0].body));
+			}
+
+			let mut contents = Vec::new();
+
+			for segment in segments {
+				contents.extend_from_slice(segment.body);
+			}
+
+			Ok(Cow::Owned(contents))
+		}
+	}
+}
+
+// Returns the two's complement contents of an integer token, which have to
+// be as short as possible (8.3.2).
+fn int_contents<'a>(token: &Token<'a>) -> Result<&'a [u8], Error> {
+	let body = token.body;
+
+	if body.len() == 0 {
+		return Err(MalformedToken);
+	}
+
+	if body.len() > 1 {
+		// The first nine bits may not be all zeros or all ones
+		let leading = ((body[0] as u16) << 1) | ((body[1] as u16) >> 7);
+
+		if leading == 0b0000_0000_0 || leading == 0b1111_1111_1 {
+			return Err(MalformedToken)
+		}
+	}
+
+	Ok(body)
+}
+
+// Drops leading octets of a two's complement integer as long as its sign is
+// preserved (8.3.2).
+fn trim_int(bytes: &[u8]) -> &[u8] {
+	let mut start = 0;
+
+	while start < bytes.len() - 1 {
+		let leading = ((bytes[start] as u16) << 1) | ((bytes[start+1] as u16) >> 7);
+
+		if leading != 0b0000_0000_0 && leading != 0b1111_1111_1 {
+			break;
+		}
+
+		start += 1;
+	}
+
+	&bytes[start..]
+}
+
 #[cfg(test)]
 mod tests;
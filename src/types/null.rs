@@ -1,9 +1,9 @@
 use {Token, Tag, Error};
-use types::TokenType;
+use types::{TokenType, ToToken};
 use Error::MalformedToken;
 use Encoding;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Null;
 
 impl<'a> TokenType<'a> for Null {
@@ -23,3 +23,13 @@ impl<'a> TokenType<'a> for Null {
 		Ok(Null)
 	}
 }
+
+impl ToToken for Null {
+	fn tag(&self) -> Tag {
+		Tag::Null
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(Vec::new())
+	}
+}
@@ -1,13 +1,17 @@
+use std::borrow::Cow;
+
 use {Parser, Token, Tag, Error};
-use types::TokenType;
+use types::{TokenType, ToToken};
 use Encoding;
 
-#[derive(Debug)]
-pub struct Octetstring<'a>(pub &'a [u8]);
+/// The contents of an OCTET STRING. Contents split into several segments
+/// (8.7.3) are concatenated, and only borrowed from the input otherwise.
+#[derive(Debug, PartialEq)]
+pub struct Octetstring<'a>(pub Cow<'a, [u8]>);
 
 impl<'a> Octetstring<'a> {
-	pub fn parser(&self) -> Parser<'a> {
-		Parser::new(self.0)
+	pub fn parser<'b>(&'b self) -> Parser<'b> {
+		Parser::new(&self.0)
 	}
 }
 
@@ -21,6 +25,16 @@ impl<'a> TokenType<'a> for Octetstring<'a> {
 	}
 
 	fn from_token(token: &Token<'a>) -> Result<Self, Error> {
-		Ok(Octetstring(token.body))
+		Ok(Octetstring(try!(super::string_contents(token))))
+	}
+}
+
+impl<'a> ToToken for Octetstring<'a> {
+	fn tag(&self) -> Tag {
+		Tag::Octetstring
 	}
-}
\ No newline at end of file
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.to_vec())
+	}
+}
@@ -3,7 +3,7 @@ use std::iter::Peekable;
 use std::slice::Iter;
 
 use {Encoding, Tag, Token, Error};
-use types::TokenType;
+use types::{TokenType, ToToken};
 use Error::*;
 
 const ARC_SHIFT: u8 = 1<<7;
@@ -85,6 +85,20 @@ impl<'a> TokenType<'a> for Oid {
 }
 
 impl Oid {
+	pub fn from_arcs(arcs: &[u32]) -> Result<Oid, Error> {
+		let mut oid = Oid{arcs: [0u32; 12], n: 0};
+
+		for arc in arcs {
+			try!(oid.append(*arc));
+		}
+
+		Ok(oid)
+	}
+
+	pub fn arcs(&self) -> &[u32] {
+		&self.arcs[0 .. self.n as usize]
+	}
+
 	fn parse_arc<'a>(iter: &mut Iterator<Item=&'a u8>) -> Result<u32, Error> {
 		let mut arc = 0u32;
 
@@ -148,3 +162,122 @@ impl PartialEq<ConstOid> for Oid {
 			self.arcs[0 .. self.n as usize] == *other.0
 	}
 }
+
+impl ToToken for Oid {
+	fn tag(&self) -> Tag {
+		Tag::Oid
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		encode_arcs(self.arcs())
+	}
+}
+
+impl ToToken for ConstOid {
+	fn tag(&self) -> Tag {
+		Tag::Oid
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		encode_arcs(self.0)
+	}
+}
+
+/// A RELATIVE-OID, the arcs of an object identifier following a prefix
+/// known from the context (8.20).
+#[derive(Debug, PartialEq, Eq)]
+pub struct RelativeOid(Oid);
+
+impl RelativeOid {
+	pub fn from_arcs(arcs: &[u32]) -> Result<RelativeOid, Error> {
+		Oid::from_arcs(arcs).map(RelativeOid)
+	}
+
+	pub fn arcs(&self) -> &[u32] {
+		self.0.arcs()
+	}
+}
+
+impl<'a> TokenType<'a> for RelativeOid {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::RelativeOid
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	// 8.20.2
+	// Unlike OBJECT IDENTIFIER, each arc has a subidentifier of its own
+	fn from_token(token: &Token) -> Result<RelativeOid, Error> {
+		if token.body.len() == 0 {
+			return Err(MalformedToken)
+		}
+
+		let mut oid = Oid{arcs: [0u32; 12], n: 0};
+		let mut iter = token.body.iter().peekable();
+
+		while let Some(_) = iter.peek() {
+			let arc = try!(Oid::parse_arc(&mut iter));
+			try!(oid.append(arc));
+		}
+
+		Ok(RelativeOid(oid))
+	}
+}
+
+impl ToToken for RelativeOid {
+	fn tag(&self) -> Tag {
+		Tag::RelativeOid
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		if self.arcs().is_empty() {
+			return Err(UnsupportedOid);
+		}
+
+		let mut body = Vec::new();
+
+		for arc in self.arcs() {
+			encode_arc(*arc, &mut body);
+		}
+
+		Ok(body)
+	}
+}
+
+// 8.19
+fn encode_arcs(arcs: &[u32]) -> Result<Vec<u8>, Error> {
+	if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] > 39) {
+		return Err(UnsupportedOid);
+	}
+
+	// 8.19.4
+	// The first two arcs are combined into a single subidentifier
+	let first = match (arcs[0] * 40).checked_add(arcs[1]) {
+		Some(first) => first,
+		None        => return Err(UnsupportedOid)
+	};
+
+	let mut body = Vec::new();
+	encode_arc(first, &mut body);
+
+	for arc in &arcs[2..] {
+		encode_arc(*arc, &mut body);
+	}
+
+	Ok(body)
+}
+
+// 8.19.2
+fn encode_arc(arc: u32, body: &mut Vec<u8>) {
+	let mut groups = vec![(arc as u8) & ARC_MASK];
+	let mut rest = arc >> 7;
+
+	while rest > 0 {
+		groups.push((rest as u8) & ARC_MASK | ARC_SHIFT);
+		rest >>= 7;
+	}
+
+	body.extend(groups.iter().rev());
+}
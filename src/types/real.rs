@@ -0,0 +1,239 @@
+use std::str;
+
+use {Token, Tag, Encoding, Rules, Error};
+use types::{TokenType, ToToken};
+use Error::*;
+
+/// A REAL value (8.5). Values in the binary encoding are rounded to the
+/// nearest `f64`, as are decimal values (ISO 6093).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Real(pub f64);
+
+// 8.5.9
+const PLUS_INFINITY: u8  = 0x40;
+const MINUS_INFINITY: u8 = 0x41;
+const NOT_A_NUMBER: u8   = 0x42;
+const MINUS_ZERO: u8     = 0x43;
+
+impl<'a> TokenType<'a> for Real {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::Real
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token) -> Result<Real, Error> {
+		// 8.5.3
+		let first = match token.body.first() {
+			Some(first) => *first,
+			None        => return Ok(Real(0.0))
+		};
+
+		match first >> 6 {
+			0b10 | 0b11 => Self::binary(token.body, token.rules).map(Real),
+			0b00        => Self::decimal(token.body, token.rules).map(Real),
+			_ => match (first, token.body.len()) {
+				(PLUS_INFINITY, 1)  => Ok(Real(::std::f64::INFINITY)),
+				(MINUS_INFINITY, 1) => Ok(Real(::std::f64::NEG_INFINITY)),
+				(NOT_A_NUMBER, 1)   => Ok(Real(::std::f64::NAN)),
+				(MINUS_ZERO, 1)     => Ok(Real(-0.0)),
+				_                   => Err(MalformedToken)
+			}
+		}
+	}
+}
+
+impl Real {
+	// 8.5.7
+	fn binary(body: &[u8], rules: Rules) -> Result<f64, Error> {
+		let first = body[0];
+		let negative = first & 0b0100_0000 != 0;
+
+		let base_bits = match (first >> 4) & 0b11 {
+			0 => 1,
+			1 => 3,
+			2 => 4,
+			_ => return Err(MalformedToken)
+		};
+
+		let scale = ((first >> 2) & 0b11) as i64;
+
+		// 8.5.7.4
+		let (exponent, rest) = match first & 0b11 {
+			3 => match body.get(1) {
+				Some(&len) => try!(Self::split(&body[2..], len as usize)),
+				None       => return Err(MalformedToken)
+			},
+			format => try!(Self::split(&body[1..], format as usize + 1))
+		};
+
+		let minimal = super::trim_int(exponent).len() == exponent.len();
+
+		// Longer exponents have to be minimal under any rules
+		if !minimal && (first & 0b11 == 3 || rules != Rules::Ber) {
+			return Err(MalformedToken);
+		}
+
+		if exponent.len() > 8 {
+			return Err(IntegerOverflow);
+		}
+
+		let mut e = exponent[0] as i8 as i64;
+		for byte in &exponent[1..] {
+			e = (e << 8) | *byte as i64;
+		}
+
+		if rest.is_empty() || rest.iter().all(|b| *b == 0) {
+			return Err(MalformedToken);
+		}
+
+		// 11.3.1
+		if rules != Rules::Ber && (base_bits != 1 || scale != 0 || rest[0] == 0 || rest[rest.len() - 1] & 1 == 0) {
+			return Err(MalformedToken);
+		}
+
+		let mut value = rest.iter().fold(0.0, |n, byte| n * 256.0 + *byte as f64);
+
+		let mut shift = try!(e.checked_mul(base_bits).and_then(|e| e.checked_add(scale)).ok_or(IntegerOverflow));
+
+		// Scale in steps which cannot overflow, until the value saturates
+		while shift != 0 && value != 0.0 && value.is_finite() {
+			let step = shift.max(-1000).min(1000);
+			value *= 2f64.powi(step as i32);
+			shift -= step;
+		}
+
+		Ok(if negative { -value } else { value })
+	}
+
+	fn split(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+		match len > 0 && bytes.len() >= len {
+			true  => Ok(bytes.split_at(len)),
+			false => Err(MalformedToken)
+		}
+	}
+
+	// 8.5.8
+	fn decimal(body: &[u8], rules: Rules) -> Result<f64, Error> {
+		let form = body[0] & 0b0011_1111;
+		let s = try!(str::from_utf8(&body[1..]));
+
+		// 11.3.2
+		if rules != Rules::Ber && (form != 3 || Self::canonical_nr3(s).is_err()) {
+			return Err(MalformedToken);
+		}
+
+		// ISO 6093 permits leading spaces and a comma as decimal mark
+		let number = s.trim_left_matches(' ').replace(',', ".");
+
+		let (mantissa, exponent) = match number.find(|c| c == 'E' || c == 'e') {
+			Some(i) => (&number[..i], Some(&number[i + 1..])),
+			None    => (&number[..], None)
+		};
+
+		let digits = mantissa.trim_left_matches(|c| c == '+' || c == '-');
+		let point = digits.find('.');
+		let valid = mantissa.len() - digits.len() <= 1 &&
+			digits.chars().any(|c| c.is_digit(10)) &&
+			digits.chars().all(|c| c.is_digit(10) || c == '.') &&
+			digits.matches('.').count() <= 1;
+
+		let valid = valid && match (form, point, exponent) {
+			(1, None, None)    => true,
+			(2, Some(_), None) => true,
+			(3, _, Some(exponent)) => {
+				let digits = exponent.trim_left_matches(|c| c == '+' || c == '-');
+				exponent.len() - digits.len() <= 1 &&
+					!digits.is_empty() && digits.chars().all(|c| c.is_digit(10))
+			},
+			_ => false
+		};
+
+		match valid {
+			true  => number.parse().map_err(|_| MalformedToken),
+			false => Err(MalformedToken)
+		}
+	}
+
+	// A mantissa without leading or trailing zeros followed by a full stop,
+	// and an exponent without leading zeros, which is "+0" if zero.
+	fn canonical_nr3(s: &str) -> Result<(), Error> {
+		let s = match s.starts_with('-') {
+			true  => &s[1..],
+			false => s
+		};
+
+		let (mantissa, exponent) = match s.find(".E") {
+			Some(i) => (&s[..i], &s[i + 2..]),
+			None    => return Err(MalformedToken)
+		};
+
+		let digits = exponent.trim_left_matches('-');
+
+		let valid = !mantissa.is_empty() &&
+			mantissa.chars().all(|c| c.is_digit(10)) &&
+			!mantissa.starts_with('0') && !mantissa.ends_with('0') &&
+			(exponent == "+0" || (
+				!digits.is_empty() && exponent.len() - digits.len() <= 1 &&
+				digits.chars().all(|c| c.is_digit(10)) && !digits.starts_with('0')
+			));
+
+		match valid {
+			true  => Ok(()),
+			false => Err(MalformedToken)
+		}
+	}
+}
+
+impl ToToken for Real {
+	fn tag(&self) -> Tag {
+		Tag::Real
+	}
+
+	// 11.3.1
+	// Binary encoding with base 2, no scaling and an odd mantissa
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		let value = self.0;
+
+		if value.is_nan() {
+			return Ok(vec![NOT_A_NUMBER]);
+		} else if value.is_infinite() {
+			return Ok(vec![if value > 0.0 { PLUS_INFINITY } else { MINUS_INFINITY }]);
+		} else if value == 0.0 {
+			return Ok(if value.is_sign_negative() { vec![MINUS_ZERO] } else { Vec::new() });
+		}
+
+		let bits = value.to_bits();
+		let biased = ((bits >> 52) & 0x7FF) as i64;
+		let fraction = bits & ((1 << 52) - 1);
+
+		let (mut mantissa, mut exponent) = match biased {
+			0 => (fraction, -1074),
+			_ => (fraction | (1 << 52), biased - 1075)
+		};
+
+		while mantissa & 1 == 0 {
+			mantissa >>= 1;
+			exponent += 1;
+		}
+
+		let exponent = exponent.to_be_bytes();
+		let exponent = super::trim_int(&exponent);
+
+		let mut first = 0b1000_0000 | (exponent.len() as u8 - 1);
+		if value < 0.0 {
+			first |= 0b0100_0000;
+		}
+
+		let mantissa = mantissa.to_be_bytes();
+		let start = mantissa.iter().position(|b| *b != 0).unwrap_or(mantissa.len() - 1);
+
+		let mut body = vec![first];
+		body.extend_from_slice(exponent);
+		body.extend_from_slice(&mantissa[start..]);
+
+		Ok(body)
+	}
+}
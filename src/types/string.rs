@@ -1,8 +1,10 @@
 use std::str;
 use std::convert;
+use std::borrow::Cow;
 
 use {Token, Encoding, Tag, Error};
-use types::TokenType;
+use types::{TokenType, ToToken};
+use types::t61;
 use Error::*;
 
 impl convert::From<str::Utf8Error> for Error {
@@ -11,18 +13,23 @@ impl convert::From<str::Utf8Error> for Error {
 	}
 }
 
-#[derive(Debug)]
-pub struct String<'a>(pub &'a str);
+#[derive(Debug, PartialEq)]
+pub struct String<'a>(pub Cow<'a, str>);
 
 impl<'a> TokenType<'a> for String<'a> {
 	fn matches(tag: Tag) -> bool {
 		match tag {
-			Tag::Utf8String      |
-			Tag::PrintableString |
-			Tag::Ia5String       |
-			Tag::VisibleString   |
-			Tag::T61String       => true,
-			_                    => false
+			Tag::Utf8String       |
+			Tag::NumericString    |
+			Tag::PrintableString  |
+			Tag::Ia5String        |
+			Tag::GraphicString    |
+			Tag::VisibleString    |
+			Tag::T61String        |
+			Tag::UniversalString  |
+			Tag::BmpString        |
+			Tag::ObjectDescriptor => true,
+			_                     => false
 		}
 	}
 
@@ -31,19 +38,49 @@ impl<'a> TokenType<'a> for String<'a> {
 	}
 
 	fn from_token(token: &Token<'a>) -> Result<String<'a>, Error> {
+		let body = try!(super::string_contents(token));
+
 		match token.tag {
-			Tag::Utf8String      => Ok(String(try!{str::from_utf8(token.body)})),
-			Tag::PrintableString => Self::printable_string(token.body),
-			Tag::Ia5String       => Self::ascii_string(token.body),
-			Tag::VisibleString   => Self::ascii_string(token.body),
-			Tag::T61String       => Self::ascii_string(token.body),
-			_ => Err(UnsupportedString)
+			Tag::Utf8String       => (),
+			Tag::NumericString    => try!(Self::numeric_string(&body)),
+			Tag::PrintableString  => try!(Self::printable_string(&body)),
+			Tag::Ia5String        => try!(Self::ascii_string(&body)),
+			Tag::VisibleString    => try!(Self::ascii_string(&body)),
+			// Escape sequences which are not understood are skipped
+			Tag::T61String        => return Ok(String(try!(Self::t61_string(body, false)))),
+			// GraphicStrings, of which only ASCII is supported
+			Tag::GraphicString    => try!(Self::ascii_string(&body)),
+			Tag::ObjectDescriptor => try!(Self::ascii_string(&body)),
+			// Transcoded into an owned string
+			Tag::UniversalString  => return Self::universal_string(&body).map(|s| String(Cow::Owned(s))),
+			Tag::BmpString        => return Self::bmp_string(&body).map(|s| String(Cow::Owned(s))),
+			_ => return Err(UnsupportedString)
+		}
+
+		match body {
+			Cow::Borrowed(body) => Ok(String(Cow::Borrowed(try!(str::from_utf8(body))))),
+			Cow::Owned(body)    => match ::std::string::String::from_utf8(body) {
+				Ok(s)    => Ok(String(Cow::Owned(s))),
+				Err(why) => Err(Error::from(why.utf8_error()))
+			}
 		}
 	}
 }
 
 impl<'a> String<'a> {
-	fn printable_string(body: &'a [u8]) -> Result<String<'a>, Error> {
+	fn numeric_string(body: &[u8]) -> Result<(), Error> {
+		// Allowed characters are digits and space
+		for byte in body {
+			match *byte as char {
+				'0' ... '9' | ' ' => continue,
+				_                 => return Err(MalformedToken)
+			}
+		}
+
+		Ok(())
+	}
+
+	fn printable_string(body: &[u8]) -> Result<(), Error> {
 		// Allowed characters are ( to z, excluding *, ;, <, >, @
 		for byte in body {
 			match *byte as char {
@@ -53,14 +90,14 @@ impl<'a> String<'a> {
 			}
 		}
 
-		Ok(String(try!(str::from_utf8(body))))
+		Ok(())
 	}
 
-	fn ascii_string(body: &'a [u8]) -> Result<String<'a>, Error> {
+	fn ascii_string(body: &[u8]) -> Result<(), Error> {
 		/* Strictly speaking, control codes are allowed for Ia5String,
 		 * but since we don't have a way of dealing with code-page
 		 * switching we restrict the type. This is non-conformant to the
-		 * spec. Same goes for T61String, which can switch code pages
+		 * spec. Same goes for GraphicString, which can switch code pages
 		 * mid-stream. We assume that the initial code-page is #6
 		 * (ASCII), and flag switching as an error.
 		 */
@@ -72,7 +109,72 @@ impl<'a> String<'a> {
 			}
 		}
 
-		Ok(String(try!(str::from_utf8(body))))
+		Ok(())
+	}
+
+	fn t61_string(body: Cow<'a, [u8]>, strict: bool) -> Result<Cow<'a, str>, Error> {
+		match body {
+			Cow::Borrowed(body) => t61::decode(body, strict),
+			Cow::Owned(body)    => t61::decode(&body, strict).map(|s| Cow::Owned(s.into_owned()))
+		}
+	}
+
+	// UCS-4, in big-endian order (8.23.7)
+	fn universal_string(body: &[u8]) -> Result<::std::string::String, Error> {
+		if body.len() % 4 != 0 {
+			return Err(MalformedToken);
+		}
+
+		body.chunks(4).map(|c| {
+			let code = (c[0] as u32) << 24 | (c[1] as u32) << 16 | (c[2] as u32) << 8 | c[3] as u32;
+			char::from_u32(code).ok_or(MalformedToken)
+		}).collect()
+	}
+
+	// UCS-2, in big-endian order (8.23.8). Surrogates are not part of the
+	// Basic Multilingual Plane.
+	fn bmp_string(body: &[u8]) -> Result<::std::string::String, Error> {
+		if body.len() % 2 != 0 {
+			return Err(MalformedToken);
+		}
+
+		body.chunks(2).map(|c| {
+			char::from_u32((c[0] as u32) << 8 | c[1] as u32).ok_or(MalformedToken)
+		}).collect()
+	}
+}
+
+/// A TeletexString decoded strictly, rejecting the escape sequences of
+/// character sets other than ASCII, Latin-1 and the supplementary set of
+/// T.61. `String` skips them instead, and takes strings which are not valid
+/// T.61 to be Latin-1.
+#[derive(Debug, PartialEq)]
+pub struct T61String<'a>(pub Cow<'a, str>);
+
+impl<'a> TokenType<'a> for T61String<'a> {
+	fn matches(tag: Tag) -> bool {
+		tag == Tag::T61String
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token<'a>) -> Result<T61String<'a>, Error> {
+		let body = try!(super::string_contents(token));
+		String::t61_string(body, true).map(T61String)
+	}
+}
+
+impl<'a> ToToken for String<'a> {
+	/// Strings are always written as UTF8String, which can represent
+	/// any of the supported string types.
+	fn tag(&self) -> Tag {
+		Tag::Utf8String
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		Ok(self.0.as_bytes().to_vec())
 	}
 }
 
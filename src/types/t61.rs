@@ -0,0 +1,184 @@
+// Decoding of T.61 (TeletexString), which switches character sets with the
+// escape sequences of ISO 2022. Its default supplementary set is that of
+// ISO 6937, in which diacritical marks precede the letters they apply to.
+
+use std::borrow::Cow;
+use std::str;
+
+use Error;
+use Error::{MalformedToken, UnsupportedString};
+
+const ESC: u8 = 0x1B;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Charset {
+	/// ISO 646 IRV, which includes the T.61 primary set
+	Ascii,
+	/// The supplementary set of ISO 6937 and T.61
+	Supplementary,
+	/// The right-hand part of ISO 8859-1
+	Latin1,
+}
+
+// The characters at 0xA0 to 0xFF of the supplementary set, of which 0xC1 to
+// 0xCF are diacritical marks. Unassigned positions hold NUL.
+const SUPPLEMENTARY: [char; 96] = [
+	'\u{A0}', '¡', '¢', '£', '$', '¥', '#', '§', '¤', '‘', '“', '«', '←', '↑', '→', '↓',
+	'°', '±', '²', '³', '×', 'µ', '¶', '·', '÷', '’', '”', '»', '¼', '½', '¾', '¿',
+	'\0', '`', '´', '^', '~', '¯', '˘', '˙', '¨', '¨', '˚', '¸', '\0', '˝', '˛', 'ˇ',
+	'―', '¹', '®', '©', '™', '♪', '¬', '¦', '\0', '\0', '\0', '\0', '⅛', '⅜', '⅝', '⅞',
+	'Ω', 'Æ', 'Đ', 'ª', 'Ħ', '\0', 'Ĳ', 'Ŀ', 'Ł', 'Ø', 'Œ', 'º', 'Þ', 'Ŧ', 'Ŋ', 'ŉ',
+	'ĸ', 'æ', 'đ', 'ð', 'ħ', 'ı', 'ĳ', 'ŀ', 'ł', 'ø', 'œ', 'ß', 'þ', 'ŧ', 'ŋ', '\u{AD}',
+];
+
+// The letters each diacritical mark combines with, and the results
+const DIACRITICS: [(u8, &'static str, &'static str); 14] = [
+	(0xC1, "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+	(0xC2, "ACEILNORSUYZacegilnorsuyz", "ÁĆÉÍĹŃÓŔŚÚÝŹáćéģíĺńóŕśúýź"),
+	(0xC3, "ACEGHIJOSUWYaceghijosuwy", "ÂĈÊĜĤÎĴÔŜÛŴŶâĉêĝĥîĵôŝûŵŷ"),
+	(0xC4, "AINOUainou", "ÃĨÑÕŨãĩñõũ"),
+	(0xC5, "AEIOUaeiou", "ĀĒĪŌŪāēīōū"),
+	(0xC6, "AGUagu", "ĂĞŬăğŭ"),
+	(0xC7, "CEGIZcegz", "ĊĖĠİŻċėġż"),
+	(0xC8, "AEIOUYaeiouy", "ÄËÏÖÜŸäëïöüÿ"),
+	// The umlaut of T.61, which ISO 6937 merged with the diaeresis
+	(0xC9, "AEIOUYaeiouy", "ÄËÏÖÜŸäëïöüÿ"),
+	(0xCA, "AUau", "ÅŮåů"),
+	(0xCB, "CGKLNRSTcklnrst", "ÇĢĶĻŅŖŞŢçķļņŗşţ"),
+	(0xCD, "OUou", "ŐŰőű"),
+	(0xCE, "AEIUaeiu", "ĄĘĮŲąęįų"),
+	(0xCF, "CDELNRSTZcdelnrstz", "ČĎĚĽŇŘŠŤŽčďěľňřšťž"),
+];
+
+/// Decodes a T.61 string. If `strict`, only the escape sequences designating
+/// ASCII, the supplementary set or Latin-1 are accepted. Otherwise unknown
+/// single-byte sets are skipped, and strings which are not valid T.61 are
+/// taken to be Latin-1, as many encoders write them. Multi-byte sets are
+/// never supported.
+pub fn decode<'a>(body: &'a [u8], strict: bool) -> Result<Cow<'a, str>, Error> {
+	match Decoder::new(strict).decode(body) {
+		Err(MalformedToken) if !strict => Ok(Cow::Owned(body.iter().map(|b| *b as char).collect())),
+		result            => result
+	}
+}
+
+struct Decoder {
+	strict: bool,
+	/// The sets designated as G0 to G3
+	sets: [Option<Charset>; 4],
+	/// The set invoked into the right-hand part, 0xA0 to 0xFF
+	right: usize,
+}
+
+impl Decoder {
+	fn new(strict: bool) -> Decoder {
+		Decoder {
+			strict: strict,
+			sets: [Some(Charset::Ascii), None, Some(Charset::Supplementary), None],
+			right: 2,
+		}
+	}
+
+	fn decode<'a>(mut self, body: &'a [u8]) -> Result<Cow<'a, str>, Error> {
+		// Plain ASCII is borrowed
+		if body.iter().all(|b| *b >= 0x20 && *b < 0x7F) {
+			return Ok(Cow::Borrowed(try!(str::from_utf8(body))));
+		}
+
+		let mut s = String::with_capacity(body.len());
+		let mut i = 0;
+
+		while i < body.len() {
+			let byte = body[i];
+			i += 1;
+
+			match byte {
+				ESC => i += try!(self.escape(&body[i..])),
+				0x0A | 0x0C | 0x0D => s.push(byte as char),
+				0x20 ... 0x7E => s.push(byte as char),
+				0xA0 ... 0xFF => match self.sets[self.right] {
+					Some(Charset::Latin1) => s.push(byte as char),
+					Some(Charset::Supplementary) => match byte {
+						0xC1 ... 0xCF => {
+							s.push(try!(Self::compose(byte, body.get(i).cloned())));
+							i += 1;
+						},
+						_ => match SUPPLEMENTARY[byte as usize - 0xA0] {
+							'\0' => return Err(MalformedToken),
+							c    => s.push(c)
+						}
+					},
+					_ => return Err(MalformedToken)
+				},
+				_ => return Err(MalformedToken)
+			}
+		}
+
+		Ok(Cow::Owned(s))
+	}
+
+	// Applies the escape sequence following ESC, returning its length
+	fn escape(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+		let len = match bytes.iter().position(|b| *b < 0x20 || *b > 0x2F) {
+			Some(pos) if bytes[pos] >= 0x30 && bytes[pos] <= 0x7E => pos + 1,
+			_ => return Err(MalformedToken)
+		};
+
+		let known = match (len, bytes[0]) {
+			// Locking shifts of G1, G2 or G3 into the right-hand part
+			(1, 0x7E) => { self.right = 1; true },
+			(1, 0x7D) => { self.right = 2; true },
+			(1, 0x7C) => { self.right = 3; true },
+			// Designations of 94 and 96 character sets
+			(2, designation) => match (designation, Self::charset(designation, bytes[1])) {
+				(0x28, Some(Charset::Ascii))   => true,
+				(0x29 ... 0x2B, Some(charset)) => { self.sets[designation as usize - 0x28] = Some(charset); true },
+				(0x2D ... 0x2F, Some(charset)) => { self.sets[designation as usize - 0x2C] = Some(charset); true },
+				_ => false
+			},
+			_ => false
+		};
+
+		// Multi-byte sets, such as JIS X 0208, are designated with ESC $ and
+		// cannot be skipped, as their characters take several bytes
+		match known || (!self.strict && bytes[0] != 0x24) {
+			true  => Ok(len),
+			false => Err(UnsupportedString)
+		}
+	}
+
+	fn charset(designation: u8, set: u8) -> Option<Charset> {
+		let size = match designation {
+			0x28 ... 0x2B => 94,
+			_             => 96
+		};
+
+		// ISO-IR 6, 102, 103 and 100
+		match (size, set) {
+			(94, b'B') | (94, b'u') => Some(Charset::Ascii),
+			(94, b'v')              => Some(Charset::Supplementary),
+			(96, b'A')              => Some(Charset::Latin1),
+			_                       => None
+		}
+	}
+
+	// A diacritical mark followed by a letter, or by a space for the mark
+	// on its own
+	fn compose(mark: u8, letter: Option<u8>) -> Result<char, Error> {
+		let &(_, letters, composed) = match DIACRITICS.iter().find(|d| d.0 == mark) {
+			Some(diacritic) => diacritic,
+			None            => return Err(MalformedToken)
+		};
+
+		let letter = match letter {
+			Some(b' ')   => return Ok(SUPPLEMENTARY[mark as usize - 0xA0]),
+			Some(letter) => letter as char,
+			None         => return Err(MalformedToken)
+		};
+
+		match letters.chars().position(|c| c == letter) {
+			Some(pos) => Ok(composed.chars().nth(pos).unwrap()),
+			None      => Err(MalformedToken)
+		}
+	}
+}
@@ -1,13 +1,34 @@
-use {Token, Encoding, Tag};
-use types::{String, Oid, ConstOid, Int, Bitstring, Null, Bool, Time, TokenType};
+use std::borrow::Cow;
+
+use {Token, Encoding, Tag, Rules, Limits, Parser, Writer};
+use types::{String, T61String, Oid, ConstOid, Int, BigInt, Sign, Bitstring, Null, Bool, Time, LenientTime, CenturyWindow, Validity, Octetstring, Set, TokenType, ToToken};
+use types::{Enumerated, Real, RelativeOid, Date, TimeOfDay, DateTime, Duration, OidIri, RelativeOidIri};
+use types::{External, ExternalEncoding, EmbeddedPdv, Identification};
+use dsl::{decode, Any};
 use Error::*;
 
+fn primitive<'a>(tag: Tag, rules: Rules, body: &'a [u8]) -> Token<'a> {
+	Token{
+		enc: Encoding::Primitive,
+		tag: tag,
+		depth: 0,
+		offset: 0,
+		rules: rules,
+		limits: Limits::new(),
+		header: &[],
+		body: body,
+	}
+}
+
 #[test]
 fn utf8string() {
 	let token = Token{
 		enc: Encoding::Primitive,
 		tag: Tag::Utf8String,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "äöüß·".as_bytes(),
 	};
@@ -21,6 +42,9 @@ fn unsupported_asciistring() {
 		enc: Encoding::Primitive,
 		tag: Tag::VisibleString,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "Unsupported characters: \x07\x00\x10".as_bytes(),
 	};
@@ -36,6 +60,9 @@ fn printablestring() {
 		enc: Encoding::Primitive,
 		tag: Tag::PrintableString,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "Western Cape".as_bytes(),
 	};
@@ -49,6 +76,9 @@ fn invalid_printablestring() {
 		enc: Encoding::Primitive,
 		tag: Tag::PrintableString,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "Unsupported characters: *;<>@".as_bytes(),
 	};
@@ -56,6 +86,88 @@ fn invalid_printablestring() {
 	assert_eq!(String::from_token(&token).unwrap_err(), MalformedToken);
 }
 
+#[test]
+fn numericstring() {
+	let token = primitive(Tag::NumericString, Rules::Der, b"0123 456");
+	assert_eq!(String::from_token(&token).unwrap(), "0123 456");
+
+	let token = primitive(Tag::NumericString, Rules::Der, b"12.5");
+	assert_eq!(String::from_token(&token).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn graphicstring() {
+	let token = primitive(Tag::GraphicString, Rules::Der, b"Example CA");
+	assert_eq!(String::from_token(&token).unwrap(), "Example CA");
+
+	// Escape sequences switching the character set are not supported
+	let token = primitive(Tag::GraphicString, Rules::Der, b"\x1B\x2D\x41\xE4");
+	assert_eq!(String::from_token(&token).unwrap_err(), UnsupportedString);
+}
+
+#[test]
+fn t61string() {
+	let t61 = |body| T61String::from_token(&primitive(Tag::T61String, Rules::Der, body));
+	let lenient = |body| String::from_token(&primitive(Tag::T61String, Rules::Der, body));
+
+	match t61(b"Example CA").unwrap().0 {
+		Cow::Borrowed(s) => assert_eq!(s, "Example CA"),
+		Cow::Owned(_)    => panic!("ASCII is not borrowed")
+	}
+
+	// Diacritical marks precede their letters (ISO 6937)
+	assert_eq!(t61(b"M\xC8uller \xA4 \xC2  \xE8\xC2od\xC2z").unwrap().0, "Müller $ ´ Łódź");
+	assert_eq!(lenient(b"M\xC8uller").unwrap(), "Müller");
+
+	// Latin-1 designated as G1 and invoked into the right-hand part
+	assert_eq!(t61(b"\x1B\x2D\x41\x1B\x7EJos\xE9").unwrap().0, "José");
+
+	// Unknown single-byte sets are only skipped leniently, and multi-byte
+	// sets such as JIS X 0208 never are
+	assert_eq!(t61(b"\x1B\x28\x41abc").unwrap_err(), UnsupportedString);
+	assert_eq!(lenient(b"\x1B\x28\x41abc").unwrap(), "abc");
+	assert_eq!(t61(b"\x1B\x24\x42abc").unwrap_err(), UnsupportedString);
+	assert_eq!(lenient(b"\x1B\x24\x42abc").unwrap_err(), UnsupportedString);
+
+	// Latin-1 written without designating it
+	assert_eq!(t61(b"\xC9cole").unwrap_err(), MalformedToken);
+	assert_eq!(lenient(b"\xC9cole").unwrap(), "École");
+
+	assert_eq!(t61(b"\xC8").unwrap_err(), MalformedToken);
+	assert_eq!(t61(b"\xE5").unwrap_err(), MalformedToken);
+	assert_eq!(t61(b"\x1B").unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn bmpstring() {
+	let token = primitive(Tag::BmpString, Rules::Der, &[0x00, 0x41, 0x00, 0xE4, 0x20, 0xAC]);
+	assert_eq!(String::from_token(&token).unwrap(), "Aä€");
+
+	let token = primitive(Tag::BmpString, Rules::Der, &[0x00, 0x41, 0x00]);
+	assert_eq!(String::from_token(&token).unwrap_err(), MalformedToken);
+
+	// Surrogates are not characters
+	let token = primitive(Tag::BmpString, Rules::Der, &[0xD8, 0x3D, 0xDE, 0x00]);
+	assert_eq!(String::from_token(&token).unwrap_err(), MalformedToken);
+
+	// Segments may split characters
+	let data = [0x3E, 0x08, 0x04, 0x01, 0x00, 0x04, 0x03, 0x41, 0x20, 0xAC];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+	assert_eq!(String::from_token(&token).unwrap(), "A€");
+}
+
+#[test]
+fn universalstring() {
+	let token = primitive(Tag::UniversalString, Rules::Der, &[0x00, 0x00, 0x00, 0x41, 0x00, 0x01, 0xF6, 0x00]);
+	assert_eq!(String::from_token(&token).unwrap(), "A\u{1F600}");
+
+	let token = primitive(Tag::UniversalString, Rules::Der, &[0x00, 0x11, 0x00, 0x00]);
+	assert_eq!(String::from_token(&token).unwrap_err(), MalformedToken);
+
+	let token = primitive(Tag::UniversalString, Rules::Der, &[0x00, 0x00, 0x41]);
+	assert_eq!(String::from_token(&token).unwrap_err(), MalformedToken);
+}
+
 static MYOID: ConstOid = oid![2,2,11136];
 
 #[test]
@@ -66,6 +178,9 @@ fn oid() {
 		enc: Encoding::Primitive,
 		tag: Tag::Oid,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b0_1010010, 0b1_1010111, 0b0_0000000]
 	};
@@ -83,6 +198,9 @@ fn truncated_oid() {
 		enc: Encoding::Primitive,
 		tag: Tag::Oid,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b0_1010010, 0b1_1010111, 0b1_0000000]
 	};
@@ -96,6 +214,9 @@ fn empty_oid() {
 		enc: Encoding::Primitive,
 		tag: Tag::Oid,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[]
 	};
@@ -111,6 +232,9 @@ fn int() {
 		enc: Encoding::Primitive,
 		tag: Tag::Int,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b101]
 	};
@@ -125,6 +249,9 @@ fn negative_int() {
 		enc: Encoding::Primitive,
 		tag: Tag::Int,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b11111111]
 	};
@@ -139,6 +266,9 @@ fn empty_int() {
 		enc: Encoding::Primitive,
 		tag: Tag::Int,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[]
 	};
@@ -152,6 +282,9 @@ fn invalid_int_padding() {
 		enc: Encoding::Primitive,
 		tag: Tag::Int,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b11111111, 0b1_0000000]
 	};
@@ -162,6 +295,9 @@ fn invalid_int_padding() {
 		enc: Encoding::Primitive,
 		tag: Tag::Int,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b00000000, 0b0_0000000]
 	};
@@ -175,11 +311,107 @@ fn decode_large_int() {
 		enc: Encoding::Primitive,
 		tag: Tag::Int,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0b10000000, 0b00000000]
 	};
 
-	assert_eq!(Int::<i8>::from_token(&token).unwrap_err(), OutOfMemory);
+	assert_eq!(Int::<i8>::from_token(&token).unwrap_err(), IntegerOverflow);
+}
+
+#[test]
+fn unsigned_int() {
+	let int = |body| Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Int,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
+		header: &[],
+		body: body
+	};
+
+	// Positive values with the high bit set need a leading zero octet
+	assert_eq!(Int::<u8>::from_token(&int(&[0x00, 0xFF])).unwrap(), Int(255));
+	assert_eq!(Int::<u8>::from_token(&int(&[0x7F])).unwrap(), Int(127));
+	assert_eq!(Int::<u8>::from_token(&int(&[0x00])).unwrap(), Int(0));
+	assert_eq!(Int::<u64>::from_token(&int(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF])).unwrap(), Int(u64::max_value()));
+
+	assert_eq!(Int::<u8>::from_token(&int(&[0x01, 0x00])).unwrap_err(), IntegerOverflow);
+	assert_eq!(Int::<u32>::from_token(&int(&[0xFF])).unwrap_err(), IntegerOverflow);
+	assert_eq!(Int::<u32>::from_token(&int(&[0x00, 0x7F])).unwrap_err(), MalformedToken);
+
+	assert_eq!(Int(255u8).to_der().unwrap(), vec![0x00, 0xFF]);
+	assert_eq!(Int(127u8).to_der().unwrap(), vec![0x7F]);
+	assert_eq!(Int(0u32).to_der().unwrap(), vec![0x00]);
+	assert_eq!(Int(u64::max_value()).to_der().unwrap().len(), 9);
+}
+
+#[test]
+fn int128() {
+	let mut body = vec![0x80];
+	body.extend_from_slice(&[0; 15]);
+
+	let token = Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Int,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
+		header: &[],
+		body: &body
+	};
+
+	assert_eq!(Int::<i128>::from_token(&token).unwrap(), Int(i128::min_value()));
+	assert_eq!(Int::<i64>::from_token(&token).unwrap_err(), IntegerOverflow);
+	assert_eq!(Int::<u128>::from_token(&token).unwrap_err(), IntegerOverflow);
+	assert_eq!(Int(i128::min_value()).to_der().unwrap(), body);
+
+	let mut body = vec![0x00];
+	body.extend_from_slice(&[0xFF; 16]);
+	let token = Token{body: &body, ..token};
+
+	assert_eq!(Int::<u128>::from_token(&token).unwrap(), Int(u128::max_value()));
+	assert_eq!(Int::<i128>::from_token(&token).unwrap_err(), IntegerOverflow);
+	assert_eq!(Int(u128::max_value()).to_der().unwrap(), body);
+}
+
+#[test]
+fn bigint() {
+	let int = |body| BigInt::from_token(&Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Int,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
+		header: &[],
+		body: body
+	});
+
+	// A serial number too large for i64
+	let serial = [0x00, 0xE5, 0x0B, 0x1A, 0xA9, 0x4B, 0x64, 0x2D, 0xCE, 0x08, 0x00, 0x00, 0x00, 0x00, 0x39, 0xB9, 0xE1];
+	let value = int(&serial).unwrap();
+	assert_eq!(value.to_bytes_be(), (Sign::Plus, serial[1..].to_vec()));
+	assert_eq!(value.to_der().unwrap(), serial.to_vec());
+	assert_eq!(BigInt::from_bytes_be(Sign::Plus, &serial[1..]), value);
+
+	let minus = int(&[0xFF, 0x7F]).unwrap();
+	assert_eq!(minus.to_bytes_be(), (Sign::Minus, vec![0x81]));
+	assert_eq!(minus.to_der().unwrap(), vec![0xFF, 0x7F]);
+	assert_eq!(int(&[0x80]).unwrap().to_der().unwrap(), vec![0x80]);
+	assert_eq!(int(&[0x00]).unwrap().to_der().unwrap(), vec![0x00]);
+
+	assert!(minus < int(&[0x00]).unwrap());
+	assert!(int(&[0x7F]).unwrap() < value);
+
+	assert_eq!(int(&[]).unwrap_err(), MalformedToken);
+	assert_eq!(int(&[0x00, 0x7F]).unwrap_err(), MalformedToken);
+	assert_eq!(int(&[0xFF, 0x80]).unwrap_err(), MalformedToken);
 }
 
 #[test]
@@ -190,6 +422,9 @@ fn bool() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bool,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0xFF]
 	};
@@ -201,6 +436,9 @@ fn bool() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bool,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0x0]
 	};
@@ -215,6 +453,9 @@ fn invalid_bool() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bool,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0xAB]
 	};
@@ -225,6 +466,9 @@ fn invalid_bool() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bool,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0x0, 0x0]
 	};
@@ -240,6 +484,9 @@ fn null() {
 		enc: Encoding::Primitive,
 		tag: Tag::Null,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[]
 	};
@@ -253,6 +500,9 @@ fn invalid_null() {
 		enc: Encoding::Primitive,
 		tag: Tag::Null,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0xFF]
 	};
@@ -268,6 +518,9 @@ fn bitstring() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bitstring,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0x5, 0b10100000]
 	};
@@ -286,6 +539,9 @@ fn invalid_bitstring() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bitstring,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0x4]
 	};
@@ -296,6 +552,9 @@ fn invalid_bitstring() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bitstring,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[]
 	};
@@ -309,6 +568,9 @@ fn invalid_bitstring_padding() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bitstring,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0xC, 0xA3, 0xB0, 0x00]
 	};
@@ -319,16 +581,25 @@ fn invalid_bitstring_padding() {
 		enc: Encoding::Primitive,
 		tag: Tag::Bitstring,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0x0, 0xA3, 0xB0, 0x00]
 	};
 
-	assert_eq!(Bitstring::from_token(&extra_padding).unwrap_err(), MalformedToken);
+	// Trailing 0 bits are only omitted with named bits (11.2.2)
+	let bs = Bitstring::from_token(&extra_padding).unwrap();
+	assert_eq!(bs.len_bits(), 24);
+	assert_eq!(bs.check_named_bits().unwrap_err(), MalformedToken);
 
 	let nonzero_unused = Token{
 		enc: Encoding::Primitive,
 		tag: Tag::Bitstring,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: &[0x4, 0xA3, 0xB0, 0xBF]
 	};
@@ -344,6 +615,9 @@ fn time() {
 		enc: Encoding::Primitive,
 		tag: Tag::UtcTime,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "491020181001Z".as_bytes()
 	};
@@ -354,6 +628,9 @@ fn time() {
 		enc: Encoding::Primitive,
 		tag: Tag::UtcTime,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "991020181001Z".as_bytes()
 	};
@@ -364,6 +641,9 @@ fn time() {
 		enc: Encoding::Primitive,
 		tag: Tag::GeneralizedTime,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "20991020181001Z".as_bytes()
 	};
@@ -371,12 +651,125 @@ fn time() {
 	assert_eq!(Time::from_token(&generalized).unwrap().to_rfc3339(), "2099-10-20T18:10:01+00:00");
 }
 
+#[test]
+fn generalized_fraction() {
+	let time = |rules, body| Time::from_token(&primitive(Tag::GeneralizedTime, rules, body));
+
+	let t = time(Rules::Der, b"20170531235959.25Z").unwrap();
+	assert_eq!(t.to_rfc3339(), "2017-05-31T23:59:59.250+00:00");
+	assert_eq!(time(Rules::Der, b"20170531235959.000000001Z").unwrap().timestamp_subsec_nanos(), 1);
+
+	// 11.7.3 and 11.7.4
+	for body in &[&b"20170531235959.250Z"[..], b"20170531235959,25Z", b"20170531235959.0Z"] {
+		assert_eq!(time(Rules::Der, body).unwrap_err(), MalformedToken);
+		assert_eq!(time(Rules::Cer, body).unwrap_err(), MalformedToken);
+		assert!(time(Rules::Ber, body).is_ok());
+	}
+
+	// Other forms are only accepted by LenientTime
+	for body in &[&b"20170531235959.Z"[..], b"201705312359Z", b"20170531235959", b"20170531235959+0100", b""] {
+		assert_eq!(time(Rules::Ber, body).unwrap_err(), MalformedToken);
+	}
+}
+
+#[test]
+fn lenient_time() {
+	use chrono::{NaiveDate, FixedOffset};
+
+	let time = |body| LenientTime::from_token(&primitive(Tag::GeneralizedTime, Rules::Ber, body));
+	let date = NaiveDate::from_ymd(2017, 5, 31);
+
+	// Local time, with a fraction of an hour
+	let local = time(b"2017053112.5").unwrap();
+	assert_eq!(local, LenientTime{time: date.and_hms(12, 30, 0), offset: None});
+	assert_eq!(local.to_utc(), None);
+
+	let offset = time(b"201705311230-0130").unwrap();
+	assert_eq!(offset.offset, Some(FixedOffset::west(5400)));
+	assert_eq!(offset.to_utc().unwrap().to_rfc3339(), "2017-05-31T14:00:00+00:00");
+
+	let fraction = time(b"201705311230,25+01").unwrap();
+	assert_eq!(fraction.time, date.and_hms(12, 30, 15));
+	assert_eq!(fraction.offset, Some(FixedOffset::east(3600)));
+
+	assert_eq!(time(b"20170531235959.5Z").unwrap().time, date.and_hms_milli(23, 59, 59, 500));
+
+	for body in &[&b"20170531"[..], b"201705311", b"2017053112+1", b"2017053112+0160", b"2017053112Z0", b"2017053124", b"2017053112.5.5"] {
+		assert_eq!(time(body).unwrap_err(), MalformedToken);
+	}
+
+	// Non-ASCII zones are rejected before they are sliced
+	assert_eq!(time("2017010100+0é0".as_bytes()).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn century_window() {
+	let utc = |body| primitive(Tag::UtcTime, Rules::Der, body);
+
+	let rfc5280 = CenturyWindow::default();
+	assert_eq!((rfc5280.year(49), rfc5280.year(50)), (2049, 1950));
+	assert!(rfc5280.contains(2049) && !rfc5280.contains(2050) && !rfc5280.contains(1949));
+
+	let window = CenturyWindow::new(1990);
+	assert_eq!((window.year(89), window.year(90), window.year(0)), (2089, 1990, 2000));
+	assert_eq!(window.decode(&utc(b"891231235959Z")).unwrap().to_rfc3339(), "2089-12-31T23:59:59+00:00");
+	assert_eq!(Time::from_token(&utc(b"891231235959Z")).unwrap().to_rfc3339(), "1989-12-31T23:59:59+00:00");
+
+	// Years in the window have to be UTCTime (RFC 5280 4.1.2.5)
+	let generalized = |body| primitive(Tag::GeneralizedTime, Rules::Der, body);
+	assert_eq!(rfc5280.check(&generalized(b"20491231235959Z")).unwrap_err(), UtcTimeRequired);
+	assert_eq!(rfc5280.check(&generalized(b"20500101000000Z")), Ok(()));
+	assert_eq!(rfc5280.check(&generalized(b"19491231235959Z")), Ok(()));
+	assert_eq!(rfc5280.check(&utc(b"491231235959Z")), Ok(()));
+	assert_eq!(window.check(&generalized(b"20500101000000Z")).unwrap_err(), UtcTimeRequired);
+}
+
+#[test]
+fn validity() {
+	use chrono::{UTC, TimeZone};
+	use dsl::decode;
+
+	let validity = |not_before: &[u8], not_after: &[u8]| {
+		let mut w = Writer::new();
+		w.constructed(Tag::Sequence, |w| {
+			w.primitive(Tag::UtcTime, not_before);
+			w.primitive(Tag::GeneralizedTime, not_after);
+			Ok(())
+		}).unwrap();
+		w.into_bytes()
+	};
+
+	let data = validity(b"170101000000Z", b"20500101000000Z");
+	let period = decode::<Validity>(&data).unwrap();
+	assert_eq!(period, Validity::new(UTC.ymd(2017, 1, 1).and_hms(0, 0, 0), UTC.ymd(2050, 1, 1).and_hms(0, 0, 0)));
+
+	// Both ends are included
+	assert!(period.contains(&UTC.ymd(2017, 1, 1).and_hms(0, 0, 0)));
+	assert!(period.contains(&UTC.ymd(2050, 1, 1).and_hms(0, 0, 0)));
+	assert!(!period.contains(&UTC.ymd(2016, 12, 31).and_hms(23, 59, 59)));
+
+	let later = Validity::new(UTC.ymd(2050, 1, 1).and_hms(0, 0, 0), UTC.ymd(2060, 1, 1).and_hms(0, 0, 0));
+	let after = Validity::new(UTC.ymd(2050, 1, 1).and_hms(0, 0, 1), UTC.ymd(2060, 1, 1).and_hms(0, 0, 0));
+	assert!(period.overlaps(&later) && later.overlaps(&period));
+	assert!(!period.overlaps(&after));
+	assert_eq!(period.intersection(&later).unwrap().not_before, later.not_before);
+	assert_eq!(period.intersection(&after), None);
+
+	let data = validity(b"170101000000Z", b"20491231235959Z");
+	let why = decode::<Validity>(&data).unwrap_err();
+	assert_eq!(*why.kind(), UtcTimeRequired);
+	assert_eq!(why.location().unwrap().offset, 17);
+}
+
 #[test]
 fn invalid_time() {
 	let invalid_time = Token{
 		enc: Encoding::Primitive,
 		tag: Tag::UtcTime,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "990231181001Z".as_bytes()
 	};
@@ -387,9 +780,560 @@ fn invalid_time() {
 		enc: Encoding::Primitive,
 		tag: Tag::GeneralizedTime,
 		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
 		header: &[],
 		body: "20991020181001".as_bytes()
 	};
 
 	assert_eq!(Time::from_token(&truncated).unwrap_err(), MalformedToken);
 }
+
+fn reencode<'a, T: ToToken>(value: &T, body: &'a mut Vec<u8>) -> Token<'a> {
+	*body = value.to_der().unwrap();
+
+	Token{
+		enc: Encoding::Primitive,
+		tag: value.tag(),
+		depth: 0,
+		offset: 0,
+		rules: Rules::Der,
+		limits: Limits::new(),
+		header: &[],
+		body: body,
+	}
+}
+
+#[test]
+fn roundtrip_bool() {
+	let mut body = Vec::new();
+
+	for b in &[true, false] {
+		let token = reencode(&Bool(*b), &mut body);
+		assert_eq!(Bool::from_token(&token).unwrap(), Bool(*b));
+	}
+}
+
+#[test]
+fn roundtrip_int() {
+	let mut body = Vec::new();
+
+	let cases = [
+		(0i64, &[0x00u8] as &[u8]),
+		(127, &[0x7F]),
+		(128, &[0x00, 0x80]),
+		(-128, &[0x80]),
+		(-129, &[0xFF, 0x7F]),
+		(256, &[0x01, 0x00]),
+		(i64::max_value(), &[0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+		(i64::min_value(), &[0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+	];
+
+	for case in &cases {
+		let token = reencode(&Int(case.0), &mut body);
+		assert_eq!(token.body, case.1);
+		assert_eq!(Int::<i64>::from_token(&token).unwrap(), Int(case.0));
+	}
+
+	let token = reencode(&Int(-1i8), &mut body);
+	assert_eq!(Int::<i8>::from_token(&token).unwrap(), Int(-1i8));
+}
+
+#[test]
+fn roundtrip_bitstring() {
+	let mut body = Vec::new();
+
+	let bs = Bitstring::new(&[0b10100000], 5);
+	let token = reencode(&bs, &mut body);
+	assert_eq!(token.body, &[0x5, 0b10100000]);
+	assert_eq!(Bitstring::from_token(&token).unwrap(), bs);
+
+	let empty = Bitstring::new(&[], 0);
+	let token = reencode(&empty, &mut body);
+	assert_eq!(token.body, &[0x0]);
+	assert_eq!(Bitstring::from_token(&token).unwrap(), empty);
+}
+
+#[test]
+fn named_bitstring() {
+	let mut body = Vec::new();
+
+	// Bits without names keep their trailing zeros
+	let padded = Bitstring::new(&[0xA3, 0xB0, 0x00], 0);
+	let token = reencode(&padded, &mut body);
+	assert_eq!(token.body, &[0x0, 0xA3, 0xB0, 0x00]);
+
+	let bs = Bitstring::from_token(&token).unwrap();
+	assert_eq!(bs, padded);
+	assert_eq!(bs.check_named_bits().unwrap_err(), MalformedToken);
+
+	let named = Bitstring::from_named_bits(&[0, 2, 6, 7, 8, 10, 11]);
+	let token = reencode(&named, &mut body);
+	assert_eq!(token.body, &[0x4, 0xA3, 0xB0]);
+
+	let bs = Bitstring::from_token(&token).unwrap();
+	assert_eq!(bs.len_bits(), 12);
+	assert_eq!(bs, named);
+	bs.check_named_bits().unwrap();
+
+	let none = Bitstring::from_named_bits(&[]);
+	let token = reencode(&none, &mut body);
+	assert_eq!(token.body, &[0x0]);
+
+	let invalid = Bitstring::new(&[0x80], 8);
+	assert_eq!(invalid.to_der().unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn roundtrip_octetstring() {
+	let mut body = Vec::new();
+
+	let os = Octetstring(Cow::Borrowed(&[0x00, 0xFF, 0x10]));
+	let token = reencode(&os, &mut body);
+	assert_eq!(Octetstring::from_token(&token).unwrap(), os);
+}
+
+#[test]
+fn roundtrip_null() {
+	let mut body = Vec::new();
+
+	let token = reencode(&Null, &mut body);
+	assert_eq!(token.body.len(), 0);
+	assert_eq!(Null::from_token(&token).unwrap(), Null);
+}
+
+#[test]
+fn roundtrip_oid() {
+	let mut body = Vec::new();
+
+	let token = reencode(&MYOID, &mut body);
+	assert_eq!(token.body, &[0b0_1010010, 0b1_1010111, 0b0_0000000]);
+	assert_eq!(Oid::from_token(&token).unwrap(), MYOID);
+
+	let rsa = Oid::from_arcs(&[1, 2, 840, 113549, 1, 1, 11]).unwrap();
+	let token = reencode(&rsa, &mut body);
+	assert_eq!(token.body, &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B]);
+	assert_eq!(Oid::from_token(&token).unwrap(), rsa);
+
+	assert_eq!(oid![1].to_der().unwrap_err(), UnsupportedOid);
+	assert_eq!(oid![1, 40].to_der().unwrap_err(), UnsupportedOid);
+	assert_eq!(oid![3, 1].to_der().unwrap_err(), UnsupportedOid);
+}
+
+#[test]
+fn roundtrip_string() {
+	let mut body = Vec::new();
+
+	let s = String(Cow::Borrowed("äöüß·"));
+	let token = reencode(&s, &mut body);
+	assert_eq!(token.tag, Tag::Utf8String);
+	assert_eq!(String::from_token(&token).unwrap(), s);
+}
+
+#[test]
+fn roundtrip_time() {
+	use chrono::{UTC, TimeZone};
+
+	let mut body = Vec::new();
+
+	let utc = UTC.ymd(2049, 10, 20).and_hms(18, 10, 1);
+	let token = reencode(&utc, &mut body);
+	assert_eq!(token.tag, Tag::UtcTime);
+	assert_eq!(token.body, "491020181001Z".as_bytes());
+	assert_eq!(Time::from_token(&token).unwrap(), utc);
+
+	let generalized = UTC.ymd(2050, 1, 1).and_hms(0, 0, 0);
+	let token = reencode(&generalized, &mut body);
+	assert_eq!(token.tag, Tag::GeneralizedTime);
+	assert_eq!(token.body, "20500101000000Z".as_bytes());
+	assert_eq!(Time::from_token(&token).unwrap(), generalized);
+
+	// UTCTime cannot carry fractions
+	let fraction = UTC.ymd(2017, 5, 31).and_hms_micro(23, 59, 59, 120);
+	let token = reencode(&fraction, &mut body);
+	assert_eq!(token.tag, Tag::GeneralizedTime);
+	assert_eq!(token.body, "20170531235959.00012Z".as_bytes());
+	assert_eq!(Time::from_token(&token).unwrap(), fraction);
+
+	let lenient = LenientTime::from_token(&primitive(Tag::GeneralizedTime, Rules::Ber, b"201705311230-0130")).unwrap();
+	let token = reencode(&lenient, &mut body);
+	assert_eq!(token.body, "20170531123000-0130".as_bytes());
+	assert_eq!(LenientTime::from_token(&token).unwrap(), lenient);
+}
+
+#[test]
+fn ber_bool() {
+	let token = Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Bool,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Ber,
+		limits: Limits::new(),
+		header: &[],
+		body: &[0x01]
+	};
+
+	assert_eq!(Bool::from_token(&token).unwrap(), Bool(true));
+
+	let der = Token{rules: Rules::Der, ..token};
+	assert_eq!(Bool::from_token(&der).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn ber_octetstring() {
+	let data = [0x24, 0x80, 0x04, 0x02, 0xAA, 0xBB, 0x04, 0x01, 0xCC, 0x00, 0x00];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+
+	// Segments are concatenated
+	let os = Octetstring::from_token(&token).unwrap();
+	assert_eq!(&*os.0, &[0xAA, 0xBB, 0xCC]);
+
+	let segments = token.segments(Tag::Octetstring).unwrap();
+	assert_eq!(segments.iter().map(|s| s.body).collect::<Vec<_>>(), vec![&[0xAA, 0xBB][..], &[0xCC][..]]);
+
+	let data = [0x24, 0x80, 0x24, 0x80, 0x04, 0x02, 0xAA, 0xBB, 0x00, 0x00, 0x00, 0x00];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+
+	// A single segment is borrowed
+	match Octetstring::from_token(&token).unwrap().0 {
+		Cow::Borrowed(contents) => assert_eq!(contents, &[0xAA, 0xBB]),
+		Cow::Owned(_)           => panic!("a single segment is not borrowed")
+	}
+
+	let der = Token{rules: Rules::Der, ..token};
+	assert_eq!(Octetstring::from_token(&der).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn nested_segments() {
+	// Constructed segments nested beyond the depth limit
+	let mut data = Vec::new();
+
+	for _ in 0..40 {
+		data.extend_from_slice(&[0x24, 0x80]);
+	}
+
+	data.extend_from_slice(&[0x04, 0x00]);
+
+	for _ in 0..40 {
+		data.extend_from_slice(&[0x00, 0x00]);
+	}
+
+	let token = Token{
+		enc: Encoding::Constructed,
+		tag: Tag::Octetstring,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Ber,
+		limits: Limits::new(),
+		header: &data[..2],
+		body: &data[2..data.len() - 2],
+	};
+
+	assert_eq!(token.segments(Tag::Octetstring).unwrap_err(), NestedTooDeep);
+}
+
+#[test]
+fn ber_string() {
+	let data = [0x2C, 0x08, 0x04, 0x02, 0xC3, 0xA4, 0x04, 0x02, 0xC3, 0xB6];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+
+	assert_eq!(String::from_token(&token).unwrap(), "äö");
+
+	// Segments may split multibyte characters
+	let data = [0x2C, 0x07, 0x04, 0x01, 0xC3, 0x04, 0x02, 0xA4, 0x20];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+
+	assert_eq!(String::from_token(&token).unwrap(), "ä ");
+}
+
+#[test]
+fn ber_bitstring() {
+	let data = [
+		0x23, 0x80,
+			0x03, 0x02, 0x00, 0xA0,
+			0x03, 0x02, 0x04, 0xF0,
+		0x00, 0x00
+	];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+
+	let bs = Bitstring::from_token(&token).unwrap();
+	assert_eq!(bs.len_bits(), 12);
+	assert_eq!(bs.is_set(0), true);
+	assert_eq!(bs.is_set(1), false);
+	assert_eq!(bs.is_set(11), true);
+
+	// Only the last segment may have unused bits
+	let data = [
+		0x23, 0x08,
+			0x03, 0x02, 0x04, 0xA0,
+			0x03, 0x02, 0x04, 0xF0
+	];
+	let token = Parser::with_rules(&data, Rules::Ber).next().unwrap().unwrap();
+
+	assert_eq!(Bitstring::from_token(&token).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn ber_bitstring_padding() {
+	// 11.2 does not apply to BER
+	let token = Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Bitstring,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Ber,
+		limits: Limits::new(),
+		header: &[],
+		body: &[0x4, 0xA3, 0xB0, 0xBF]
+	};
+
+	let bs = Bitstring::from_token(&token).unwrap();
+	assert_eq!(bs.len_bits(), 20);
+}
+
+#[test]
+fn cer_canonical_values() {
+	let token = Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Bool,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Cer,
+		limits: Limits::new(),
+		header: &[],
+		body: &[0x01]
+	};
+
+	assert_eq!(Bool::from_token(&token).unwrap_err(), MalformedToken);
+
+	let token = Token{
+		enc: Encoding::Primitive,
+		tag: Tag::Bitstring,
+		depth: 0,
+		offset: 0,
+		rules: Rules::Cer,
+		limits: Limits::new(),
+		header: &[],
+		body: &[0x4, 0xA3, 0xB0, 0xBF]
+	};
+
+	assert_eq!(Bitstring::from_token(&token).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn set_order() {
+	let set = |rules, data| Parser::with_rules(data, rules).next().unwrap().unwrap();
+
+	// Components ordered by tag, with [1] primitive following [0] constructed
+	let data = [0x31, 0x08, 0x02, 0x01, 0x05, 0xA0, 0x00, 0x81, 0x01, 0xFF];
+	assert_eq!(Set::check_components(&set(Rules::Der, &data)), Ok(()));
+	assert!(Set::check_elements(&set(Rules::Der, &data)).is_err());
+
+	let data = [0x31, 0x08, 0x81, 0x01, 0xFF, 0xA0, 0x00, 0x02, 0x01, 0x05];
+	let why = Set::check_components(&set(Rules::Der, &data)).unwrap_err();
+	assert_eq!(*why.kind(), UnsortedSet);
+	assert_eq!(why.location().unwrap().offset, 5);
+
+	// Elements ordered by encoding, the shorter padded with zeros
+	let data = [0x31, 0x09, 0x04, 0x01, 0x01, 0x04, 0x02, 0x01, 0x00, 0x04, 0x00];
+	let why = Set::check_elements(&set(Rules::Der, &data)).unwrap_err();
+	assert_eq!(why.location().unwrap().offset, 9);
+
+	let data = [0x31, 0x0C, 0x04, 0x00, 0x04, 0x01, 0x00, 0x04, 0x01, 0x01, 0x04, 0x02, 0x01, 0x00];
+	assert_eq!(Set::check_elements(&set(Rules::Der, &data)), Ok(()));
+
+	// Indefinite lengths
+	let data = [0x31, 0x80, 0x04, 0x01, 0x02, 0x24, 0x80, 0x04, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00];
+	assert_eq!(Set::check_elements(&set(Rules::Ber, &data)), Ok(()));
+	assert_eq!(Set::check_components(&set(Rules::Ber, &data)), Ok(()));
+}
+
+#[test]
+fn enumerated() {
+	let token = primitive(Tag::Enumerated, Rules::Der, &[0x02]);
+	assert_eq!(Enumerated::<u8>::from_token(&token).unwrap(), Enumerated(2));
+	assert!(!Enumerated::<u8>::matches(Tag::Int));
+
+	let token = primitive(Tag::Enumerated, Rules::Der, &[0x00, 0x02]);
+	assert_eq!(Enumerated::<u8>::from_token(&token).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn real() {
+	let real = |rules, body| Real::from_token(&primitive(Tag::Real, rules, body));
+
+	// Special values (8.5.9)
+	assert_eq!(real(Rules::Der, &[]).unwrap(), Real(0.0));
+	assert_eq!(real(Rules::Der, &[0x40]).unwrap(), Real(::std::f64::INFINITY));
+	assert_eq!(real(Rules::Der, &[0x41]).unwrap(), Real(::std::f64::NEG_INFINITY));
+	assert!(real(Rules::Der, &[0x42]).unwrap().0.is_nan());
+	assert!(real(Rules::Der, &[0x43]).unwrap().0.is_sign_negative());
+	assert_eq!(real(Rules::Der, &[0x44]).unwrap_err(), MalformedToken);
+
+	// Binary, with base 2, 8 and 16 and a scaling factor
+	assert_eq!(real(Rules::Der, &[0x80, 0xFF, 0x03]).unwrap(), Real(1.5));
+	assert_eq!(real(Rules::Der, &[0xC0, 0x00, 0x03]).unwrap(), Real(-3.0));
+	assert_eq!(real(Rules::Ber, &[0x90, 0x01, 0x01]).unwrap(), Real(8.0));
+	assert_eq!(real(Rules::Ber, &[0xA4, 0xFF, 0x01]).unwrap(), Real(0.125));
+	assert_eq!(real(Rules::Ber, &[0x83, 0x01, 0x02, 0x01]).unwrap(), Real(4.0));
+	assert_eq!(real(Rules::Ber, &[0xB0, 0x00, 0x01]).unwrap_err(), MalformedToken);
+	assert_eq!(real(Rules::Ber, &[0x81, 0x00]).unwrap_err(), MalformedToken);
+
+	// Decimal (ISO 6093)
+	assert_eq!(real(Rules::Ber, b"\x01 -123").unwrap(), Real(-123.0));
+	assert_eq!(real(Rules::Ber, b"\x021,5").unwrap(), Real(1.5));
+	assert_eq!(real(Rules::Ber, b"\x031.5E+2").unwrap(), Real(150.0));
+	assert_eq!(real(Rules::Der, b"\x0315.E-1").unwrap(), Real(1.5));
+	assert_eq!(real(Rules::Der, b"\x03-1.E+0").unwrap(), Real(-1.0));
+	assert_eq!(real(Rules::Ber, b"\x011.5").unwrap_err(), MalformedToken);
+	assert_eq!(real(Rules::Ber, b"\x021E2").unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn der_real() {
+	let real = |rules, body| Real::from_token(&primitive(Tag::Real, rules, body));
+
+	// 11.3.1: base 2, no scaling, an odd mantissa and minimal octets
+	for body in &[&[0x80, 0x00, 0x02][..], &[0x90, 0x00, 0x01], &[0x84, 0x00, 0x01],
+	              &[0x81, 0x00, 0x01, 0x01], &[0x80, 0x00, 0x00, 0x01]] {
+		assert!(real(Rules::Ber, body).is_ok());
+		assert_eq!(real(Rules::Der, body).unwrap_err(), MalformedToken);
+	}
+
+	// 11.3.2: NR3 without leading or trailing zeros
+	for body in &[&b"\x01123"[..], b"\x0315E-1", b"\x03150.E-1", b"\x0301.E+0", b"\x031.E0", b"\x031.E-0", b"\x03+1.E+0"] {
+		assert!(real(Rules::Ber, body).is_ok());
+		assert_eq!(real(Rules::Der, body).unwrap_err(), MalformedToken);
+	}
+}
+
+#[test]
+fn roundtrip_real() {
+	let mut body = Vec::new();
+
+	let token = reencode(&Real(1.5), &mut body);
+	assert_eq!(token.body, &[0x80, 0xFF, 0x03]);
+
+	let values = [0.0, -0.0, 1.0, -0.1, 1e300, 5e-324, ::std::f64::MAX, ::std::f64::NEG_INFINITY];
+
+	for value in &values {
+		let token = reencode(&Real(*value), &mut body);
+		let decoded = Real::from_token(&token).unwrap().0;
+		assert_eq!(decoded, *value);
+		assert_eq!(decoded.is_sign_negative(), value.is_sign_negative());
+	}
+}
+
+#[test]
+fn relative_oid() {
+	let token = primitive(Tag::RelativeOid, Rules::Der, &[0x08, 0x86, 0x48, 0x00]);
+	let oid = RelativeOid::from_token(&token).unwrap();
+	assert_eq!(oid.arcs(), &[8, 840, 0]);
+
+	let mut body = Vec::new();
+	assert_eq!(reencode(&oid, &mut body).body, token.body);
+
+	let token = primitive(Tag::RelativeOid, Rules::Der, &[0x80, 0x01]);
+	assert_eq!(RelativeOid::from_token(&token).unwrap_err(), MalformedToken);
+}
+
+#[test]
+fn date_time() {
+	use chrono::{NaiveDate, NaiveTime};
+
+	let date = Date::from_token(&primitive(Tag::Date, Rules::Der, b"2017-05-31")).unwrap();
+	assert_eq!(date, Date(NaiveDate::from_ymd(2017, 5, 31)));
+
+	let time = TimeOfDay::from_token(&primitive(Tag::TimeOfDay, Rules::Der, b"23:59:01")).unwrap();
+	assert_eq!(time, TimeOfDay(NaiveTime::from_hms(23, 59, 1)));
+
+	let token = primitive(Tag::DateTime, Rules::Der, b"2017-05-31T23:59:01");
+	let date_time = DateTime::from_token(&token).unwrap();
+	assert_eq!(date_time, DateTime(NaiveDate::from_ymd(2017, 5, 31).and_hms(23, 59, 1)));
+
+	let mut body = Vec::new();
+	assert_eq!(reencode(&date, &mut body).body, b"2017-05-31");
+	assert_eq!(reencode(&time, &mut body).body, b"23:59:01");
+	assert_eq!(reencode(&date_time, &mut body).body, token.body);
+
+	for body in &[&b"20170531"[..], b"2017-5-31", b"2017-02-29", b"2017-05-31Z", b"+017-05-31"] {
+		assert_eq!(Date::from_token(&primitive(Tag::Date, Rules::Der, body)).unwrap_err(), MalformedToken);
+	}
+
+	for body in &[&b"235901"[..], b"24:00:00", b"23:59:60", b"23:59:01Z"] {
+		assert_eq!(TimeOfDay::from_token(&primitive(Tag::TimeOfDay, Rules::Der, body)).unwrap_err(), MalformedToken);
+	}
+}
+
+#[test]
+fn duration() {
+	let duration = |body| Duration::from_token(&primitive(Tag::Duration, Rules::Der, body));
+
+	for body in &[&b"P1Y2M10DT2H30M"[..], b"P6W", b"PT0.5S", b"P1DT1,5H", b"P3M"] {
+		assert_eq!(duration(body).unwrap().0.as_bytes(), *body);
+	}
+
+	for body in &[&b"P"[..], b"1Y", b"PT", b"P1DT", b"P2M1Y", b"P1.5DT1H", b"P1W2D", b"P.5D", b"P1.D", b"P1H", b"PT1D"] {
+		assert_eq!(duration(body).unwrap_err(), MalformedToken);
+	}
+}
+
+#[test]
+fn oid_iri() {
+	let token = primitive(Tag::OidIri, Rules::Der, b"/ISO/Registration_Authority/19785.CBEFF");
+	assert_eq!(OidIri::from_token(&token).unwrap(), OidIri("/ISO/Registration_Authority/19785.CBEFF"));
+
+	let token = primitive(Tag::RelativeOidIri, Rules::Der, "Exemple/Ünicode/0".as_bytes());
+	assert_eq!(RelativeOidIri::from_token(&token).unwrap(), RelativeOidIri("Exemple/Ünicode/0"));
+
+	for body in &[&b"ISO"[..], b"/", b"/ISO//1", b"/ISO/01", b"/-ISO", b"/ISO A"] {
+		assert_eq!(OidIri::from_token(&primitive(Tag::OidIri, Rules::Der, body)).unwrap_err(), MalformedToken);
+	}
+}
+
+#[test]
+fn external() {
+	let data = [0x28, 0x09, 0x06, 0x03, 0x2A, 0x03, 0x04, 0x81, 0x02, 0xAB, 0xCD];
+	assert_eq!(decode::<External>(&data).unwrap(), External{
+		direct_reference: Some(Oid::from_arcs(&[1, 2, 3, 4]).unwrap()),
+		indirect_reference: None,
+		data_value_descriptor: None,
+		encoding: ExternalEncoding::OctetAligned(Octetstring(Cow::Borrowed(&[0xAB, 0xCD]))),
+	});
+
+	let data = [0x28, 0x0B, 0x02, 0x01, 0x05, 0x07, 0x01, 0x41, 0xA0, 0x03, 0x01, 0x01, 0xFF];
+	assert_eq!(decode::<External>(&data).unwrap(), External{
+		direct_reference: None,
+		indirect_reference: Some(Int(5)),
+		data_value_descriptor: Some(String(Cow::Borrowed("A"))),
+		encoding: ExternalEncoding::SingleType(Any{tag: Tag::Bool, enc: Encoding::Primitive, body: &[0xFF]}),
+	});
+
+	assert!(decode::<External>(&[0x28, 0x03, 0x02, 0x01, 0x05]).is_err());
+	assert!(decode::<External>(&[0x30, 0x04, 0x81, 0x02, 0xAB, 0xCD]).is_err());
+}
+
+#[test]
+fn embedded_pdv() {
+	let data = [0x2B, 0x0B, 0xA0, 0x05, 0x81, 0x03, 0x2A, 0x03, 0x04, 0x82, 0x02, 0xAB, 0xCD];
+	assert_eq!(decode::<EmbeddedPdv>(&data).unwrap(), EmbeddedPdv{
+		identification: Identification::Syntax(Oid::from_arcs(&[1, 2, 3, 4]).unwrap()),
+		data_value: Octetstring(Cow::Borrowed(&[0xAB, 0xCD])),
+	});
+
+	let data = [0x2B, 0x10, 0xA0, 0x0C, 0xA0, 0x0A, 0x80, 0x03, 0x2A, 0x03, 0x04, 0x81, 0x03, 0x2A, 0x03, 0x05, 0x82, 0x00];
+	assert_eq!(decode::<EmbeddedPdv>(&data).unwrap().identification, Identification::Syntaxes{
+		abstract_syntax: Oid::from_arcs(&[1, 2, 3, 4]).unwrap(),
+		transfer: Oid::from_arcs(&[1, 2, 3, 5]).unwrap(),
+	});
+
+	let data = [0x2B, 0x08, 0xA0, 0x02, 0x85, 0x00, 0x82, 0x02, 0xAB, 0xCD];
+	assert_eq!(decode::<EmbeddedPdv>(&data).unwrap().identification, Identification::Fixed);
+
+	// The data-value-descriptor is absent
+	let data = [0x2B, 0x0B, 0xA0, 0x02, 0x85, 0x00, 0x81, 0x01, 0x41, 0x82, 0x00];
+	assert!(decode::<EmbeddedPdv>(&data).is_err());
+}
@@ -2,9 +2,9 @@ use std::str::from_utf8;
 use std::num::ParseIntError;
 use std::convert::From;
 
-use {Token, Tag, Error};
-use types::TokenType;
-use Error::MalformedToken;
+use {Token, Tag, Rules, Error};
+use types::{TokenType, ToToken};
+use Error::{MalformedToken, UtcTimeRequired};
 use Encoding;
 use Tag::{UtcTime, GeneralizedTime};
 
@@ -12,10 +12,27 @@ use chrono::*;
 
 pub type Time = DateTime<UTC>;
 
+/// A GeneralizedTime in any of the forms BER permits (X.680 46.2), which
+/// may omit the minutes and seconds, give an offset from UTC, or be local
+/// time without any offset. Decoding into `Time` only accepts UTC with
+/// seconds, as CER and DER require.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LenientTime {
+	pub time: NaiveDateTime,
+	/// The offset from UTC, unless the time is local time
+	pub offset: Option<FixedOffset>,
+}
+
+/// The hundred years two-digit UTCTime years stand for, starting with
+/// `first_year`. RFC 5280 uses 1950 through 2049, and requires later years
+/// to be GeneralizedTime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CenturyWindow {
+	pub first_year: i32,
+}
+
 /// The exact length of a UTCTime token (YYMMDDHHMMSS'Z')
 const UTCTIME_LENGTH: usize = 12 + 1;
-/// The exact length of a GeneralizedTime token (YYYYMMDDHHMMSS'Z')
-const GENERALIZEDTIME_LENGTH: usize = 14 + 1;
 
 impl From<ParseIntError> for Error {
 	fn from(_: ParseIntError) -> Error {
@@ -37,49 +54,66 @@ impl<'a> TokenType<'a> for Time {
 	}
 
 	fn from_token(token: &Token<'a>) -> Result<Time, Error> {
-		if token.body[token.body.len()-1] != 'Z' as u8 {
-			return Err(MalformedToken);
+		CenturyWindow::default().decode(token)
+	}
+}
+
+impl CenturyWindow {
+	pub fn new(first_year: i32) -> CenturyWindow {
+		CenturyWindow {
+			first_year: first_year
 		}
+	}
+
+	/// The year a two-digit UTCTime year stands for.
+	pub fn year(&self, two_digits: u32) -> i32 {
+		// The encoding is not linear: with the window of RFC 5280, 00 is
+		// 2000, 49 is 2049, 50 is 1950 and 99 is 1999
+		self.first_year + ((two_digits as i32 - self.first_year) % 100 + 100) % 100
+	}
+
+	pub fn contains(&self, year: i32) -> bool {
+		year >= self.first_year && year < self.first_year + 100
+	}
 
+	/// Decodes a UTCTime with two-digit years in the window, or a
+	/// GeneralizedTime.
+	pub fn decode(&self, token: &Token) -> Result<Time, Error> {
 		let s = match from_utf8(token.body) {
 			Ok(s) => s,
 			_     => return Err(MalformedToken)
 		};
 
 		let time = if token.tag == UtcTime {
-			if token.body.len() != UTCTIME_LENGTH {
+			if token.body.len() != UTCTIME_LENGTH || !s.ends_with('Z') {
 				return Err(MalformedToken)
 			}
 
-			let mut yr: u32 = try!((&s[ 0.. 2]).parse());
-			let     mo: u32 = try!((&s[ 2.. 4]).parse());
-			let     dy: u32 = try!((&s[ 4.. 6]).parse());
-			let     h:  u32 = try!((&s[ 6.. 8]).parse());
-			let     m:  u32 = try!((&s[ 8..10]).parse());
-			let     s:  u32 = try!((&s[10..12]).parse());
-
-			// Years are from (19)50 to (20)49, so 99 is 1999 and 00 is 2000.
-			// Normalize years, since the encoding is not linear:
-			// 00 -> 2000, 49 -> 2049, 50 -> 1950, 99 -> 1999
-			yr += match yr > 49 {
-				true  => 1900,
-				false => 2000
-			};
-
-			UTC.ymd_opt(yr as i32, mo, dy).and_hms_opt(h, m, s)
+			let yr: u32 = try!((&s[ 0.. 2]).parse());
+			let mo: u32 = try!((&s[ 2.. 4]).parse());
+			let dy: u32 = try!((&s[ 4.. 6]).parse());
+			let h:  u32 = try!((&s[ 6.. 8]).parse());
+			let m:  u32 = try!((&s[ 8..10]).parse());
+			let s:  u32 = try!((&s[10..12]).parse());
+
+			UTC.ymd_opt(self.year(yr), mo, dy).and_hms_opt(h, m, s)
 		} else if token.tag == GeneralizedTime {
-			if token.body.len() != GENERALIZEDTIME_LENGTH {
-				return Err(MalformedToken)
+			let parts = try!(Generalized::parse(s));
+
+			// 11.7: seconds are present, and the time is in UTC
+			if parts.digits != 14 || !s.ends_with('Z') {
+				return Err(MalformedToken);
 			}
 
-			let yr: u32 = try!((&s[ 0.. 4]).parse());
-			let mo: u32 = try!((&s[ 4.. 6]).parse());
-			let dy: u32 = try!((&s[ 6.. 8]).parse());
-			let h:  u32 = try!((&s[ 8..10]).parse());
-			let m:  u32 = try!((&s[10..12]).parse());
-			let s:  u32 = try!((&s[12..14]).parse());
+			// 11.7.3 and 11.7.4: fractions use a full stop and have no
+			// trailing zeros
+			if let Some(fraction) = parts.fraction {
+				if token.rules != Rules::Ber && (fraction.starts_with(',') || fraction.ends_with('0')) {
+					return Err(MalformedToken);
+				}
+			}
 
-			UTC.ymd_opt(yr as i32, mo, dy).and_hms_opt(h, m, s)
+			LocalResult::Single(UTC.from_utc_datetime(&parts.time))
 		} else {
 			return Err(MalformedToken);
 		};
@@ -89,4 +123,211 @@ impl<'a> TokenType<'a> for Time {
 			_                         => Err(MalformedToken)
 		}
 	}
+
+	/// Checks that a time in the window is a UTCTime, as certificate
+	/// profiles such as RFC 5280 (4.1.2.5) require.
+	pub fn check(&self, token: &Token) -> Result<(), Error> {
+		if token.tag != GeneralizedTime {
+			return Ok(());
+		}
+
+		match self.contains(try!(self.decode(token)).year()) {
+			true  => Err(UtcTimeRequired),
+			false => Ok(())
+		}
+	}
+}
+
+impl Default for CenturyWindow {
+	fn default() -> CenturyWindow {
+		CenturyWindow::new(1950)
+	}
+}
+
+impl<'a> TokenType<'a> for LenientTime {
+	fn matches(tag: Tag) -> bool {
+		tag == GeneralizedTime
+	}
+
+	fn encoding() -> Encoding {
+		Encoding::Primitive
+	}
+
+	fn from_token(token: &Token<'a>) -> Result<LenientTime, Error> {
+		let parts = try!(Generalized::parse(try!(from_utf8(token.body))));
+
+		Ok(LenientTime {
+			time: parts.time,
+			offset: parts.offset,
+		})
+	}
+}
+
+impl LenientTime {
+	/// The time in UTC, unless it is local time.
+	pub fn to_utc(&self) -> Option<Time> {
+		self.offset.map(|offset| UTC.from_utc_datetime(&(self.time - offset.local_minus_utc())))
+	}
+}
+
+// The parts of a GeneralizedTime
+struct Generalized<'a> {
+	time: NaiveDateTime,
+	offset: Option<FixedOffset>,
+	/// The number of digits before the fraction: 10, 12 or 14
+	digits: usize,
+	/// The fraction, including its decimal mark
+	fraction: Option<&'a str>,
+}
+
+impl<'a> Generalized<'a> {
+	// YYYYMMDDHH[MM[SS]][(.|,)F][Z|(+|-)HH[MM]]
+	fn parse(s: &'a str) -> Result<Generalized<'a>, Error> {
+		let (local, zone) = match s.find(|c| c == 'Z' || c == '+' || c == '-') {
+			Some(i) => (&s[..i], Some(&s[i..])),
+			None    => (s, None)
+		};
+
+		let (digits, fraction) = match local.find(|c| c == '.' || c == ',') {
+			Some(i) => (&local[..i], Some(&local[i..])),
+			None    => (local, None)
+		};
+
+		if !Self::is_digits(digits) {
+			return Err(MalformedToken);
+		}
+
+		let field = |start: usize| (&digits[start..start + 2]).parse::<u32>().unwrap_or(0);
+
+		// The unit the fraction is of, in nanoseconds
+		let (min, sec, unit) = match digits.len() {
+			10 => (0, 0, 3600_000_000_000),
+			12 => (field(10), 0, 60_000_000_000),
+			14 => (field(10), field(12), 1_000_000_000),
+			_  => return Err(MalformedToken)
+		};
+
+		let date = NaiveDate::from_ymd_opt(try!((&digits[0..4]).parse()), field(4), field(6));
+		let time = NaiveTime::from_hms_opt(field(8), min, sec);
+
+		let mut time = match (date, time) {
+			(Some(date), Some(time)) => NaiveDateTime::new(date, time),
+			_                        => return Err(MalformedToken)
+		};
+
+		if let Some(fraction) = fraction {
+			if !Self::is_digits(&fraction[1..]) {
+				return Err(MalformedToken);
+			}
+
+			// Digits beyond nanoseconds are truncated
+			let precise = &fraction[1..fraction.len().min(19)];
+			let nanos = try!(precise.parse::<u128>()) * unit / 10u128.pow(precise.len() as u32);
+
+			time = time + Duration::nanoseconds(nanos as i64);
+		}
+
+		let offset = match zone {
+			None       => None,
+			Some("Z")  => Some(FixedOffset::east(0)),
+			Some(zone) => {
+				// The zone is sliced by bytes, so everything after the sign
+				// has to be an ASCII digit
+				if !Self::is_digits(&zone[1..]) {
+					return Err(MalformedToken);
+				}
+
+				let (hours, minutes) = match zone.len() {
+					3 => (&zone[1..3], "00"),
+					5 => (&zone[1..3], &zone[3..5]),
+					_ => return Err(MalformedToken)
+				};
+
+				let (hours, minutes) = (try!(hours.parse::<i32>()), try!(minutes.parse::<i32>()));
+
+				if minutes >= 60 {
+					return Err(MalformedToken);
+				}
+
+				let offset = match zone.starts_with('-') {
+					true  => FixedOffset::west_opt(hours * 3600 + minutes * 60),
+					false => FixedOffset::east_opt(hours * 3600 + minutes * 60)
+				};
+
+				match offset {
+					Some(offset) => Some(offset),
+					None         => return Err(MalformedToken)
+				}
+			}
+		};
+
+		Ok(Generalized {
+			time: time,
+			offset: offset,
+			digits: digits.len(),
+			fraction: fraction,
+		})
+	}
+
+	fn is_digits(s: &str) -> bool {
+		!s.is_empty() && s.bytes().all(|b| b >= b'0' && b <= b'9')
+	}
+
+	// The fraction of a second, without trailing zeros (11.7.3)
+	fn fraction(nanos: u32) -> String {
+		match nanos {
+			0 => String::new(),
+			_ => format!(".{:09}", nanos).trim_right_matches('0').to_string()
+		}
+	}
+}
+
+impl ToToken for Time {
+	/// Follows RFC 5280 in using UTCTime for years 1950 through 2049 and
+	/// GeneralizedTime for everything else. Times with fractional seconds,
+	/// which UTCTime cannot represent, are always GeneralizedTime.
+	fn tag(&self) -> Tag {
+		match CenturyWindow::default().contains(self.year()) && self.nanosecond() == 0 {
+			true  => UtcTime,
+			false => GeneralizedTime
+		}
+	}
+
+	// 11.7 and 11.8
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		if self.year() < 0 || self.year() > 9999 {
+			return Err(MalformedToken);
+		}
+
+		let s = match self.tag() {
+			UtcTime => self.format("%y%m%d%H%M%SZ").to_string(),
+			_       => format!("{}{}Z", self.format("%Y%m%d%H%M%S"), Generalized::fraction(self.nanosecond()))
+		};
+
+		Ok(s.into_bytes())
+	}
+}
+
+impl ToToken for LenientTime {
+	fn tag(&self) -> Tag {
+		GeneralizedTime
+	}
+
+	fn to_der(&self) -> Result<Vec<u8>, Error> {
+		if self.time.year() < 0 || self.time.year() > 9999 {
+			return Err(MalformedToken);
+		}
+
+		let zone = match self.offset.map(|offset| offset.local_minus_utc().num_minutes()) {
+			None          => String::new(),
+			Some(0)       => "Z".to_string(),
+			Some(minutes) => {
+				let sign = if minutes < 0 { '-' } else { '+' };
+				format!("{}{:02}{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+			}
+		};
+
+		let fraction = Generalized::fraction(self.time.nanosecond());
+		Ok(format!("{}{}{}", self.time.format("%Y%m%d%H%M%S"), fraction, zone).into_bytes())
+	}
 }
@@ -0,0 +1,78 @@
+use std::iter::Peekable;
+
+use {Token, Error, Location};
+use dsl::{Decode, Matcher, end};
+use types::{Sequence, Time, CenturyWindow, TokenType};
+
+/// The validity period of a certificate (RFC 5280 4.1.2.5), which includes
+/// both of its ends. Times through 2049 have to be UTCTime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Validity {
+	pub not_before: Time,
+	pub not_after: Time,
+}
+
+impl Validity {
+	pub fn new(not_before: Time, not_after: Time) -> Validity {
+		Validity {
+			not_before: not_before,
+			not_after: not_after,
+		}
+	}
+
+	/// Whether `time` is within the period.
+	pub fn contains(&self, time: &Time) -> bool {
+		self.not_before <= *time && *time <= self.not_after
+	}
+
+	/// Whether both periods have any time in common.
+	pub fn overlaps(&self, other: &Validity) -> bool {
+		self.intersection(other).is_some()
+	}
+
+	/// The period within both periods, if any.
+	pub fn intersection(&self, other: &Validity) -> Option<Validity> {
+		let validity = Validity {
+			not_before: self.not_before.max(other.not_before),
+			not_after: self.not_after.min(other.not_after),
+		};
+
+		match validity.not_before <= validity.not_after {
+			true  => Some(validity),
+			false => None
+		}
+	}
+
+	fn time<'a, I>(depth: u8, parser: &mut Peekable<I>) -> Result<Time, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		if let Some(&Ok(ref token)) = parser.peek() {
+			if token.depth == depth && Time::matches(token.tag) {
+				try!(CenturyWindow::default().check(token).map_err(|why| why.at(Location::of(token))));
+			}
+		}
+
+		Matcher::new(depth).required(parser)
+	}
+}
+
+impl<'a> Decode<'a> for Validity {
+	fn decode<I>(matcher: Matcher, parser: &mut Peekable<I>) -> Result<Validity, Error>
+		where I: Iterator<Item=Result<Token<'a>, Error>>
+	{
+		let depth = matcher.depth();
+		try!(matcher.required::<_, Sequence>(parser));
+
+		let validity = Validity {
+			not_before: try!(Validity::time(depth + 1, parser)),
+			not_after: try!(Validity::time(depth + 1, parser)),
+		};
+
+		try!(end(depth, parser));
+		Ok(validity)
+	}
+
+	fn accepts(matcher: &Matcher, token: &Token<'a>) -> bool {
+		matcher.matches::<Sequence>(token)
+	}
+}
@@ -0,0 +1,263 @@
+use {Parser, Tag, Class, Encoding, Rules, Error};
+use types::ToToken;
+
+const MULTIPART_ID: usize      = 31;
+const INDEFINITE_LENGTH: u8    = 0b10000000;
+const END_OF_CONTENTS: [u8; 2] = [0, 0];
+const FRAGMENT_LENGTH: usize   = 1000;
+
+/// Serializes tokens into DER or CER.
+///
+/// Primitive values are written as a whole, constructed values are opened
+/// with `constructed` and receive their children from a closure. Lengths
+/// of constructed values are computed once the closure returns.
+pub struct Writer {
+	buf: Vec<u8>,
+	rules: Rules,
+}
+
+impl Writer {
+	pub fn new() -> Writer {
+		Writer::with_rules(Rules::Der)
+	}
+
+	/// Creates a writer producing the encoding of `rules`. Since any DER
+	/// encoding is valid BER, `Rules::Ber` behaves like `Rules::Der`.
+	pub fn with_rules(rules: Rules) -> Writer {
+		Writer {
+			buf: Vec::new(),
+			rules: rules
+		}
+	}
+
+	pub fn primitive(&mut self, tag: Tag, body: &[u8]) {
+		self.implicit(tag, tag, body)
+	}
+
+	/// Like `primitive`, but with `tag` replacing the `universal` tag of the
+	/// value (8.14), which decides whether CER splits it into fragments.
+	pub fn implicit(&mut self, tag: Tag, universal: Tag, body: &[u8]) {
+		// 9.2
+		if self.rules == Rules::Cer && universal.is_string() && body.len() > FRAGMENT_LENGTH {
+			return self.fragments(tag, universal, body);
+		}
+
+		self.write_tag(Encoding::Primitive, tag);
+		self.write_length(body.len());
+		self.buf.extend_from_slice(body);
+	}
+
+	pub fn write<T>(&mut self, value: &T) -> Result<(), Error>
+		where T: ToToken
+	{
+		let body = try!(value.to_der());
+		self.primitive(value.tag(), &body);
+		Ok(())
+	}
+
+	pub fn encode<T>(&mut self, value: &T) -> Result<(), Error>
+		where T: Encode
+	{
+		value.encode(None, self)
+	}
+
+	pub fn constructed<F>(&mut self, tag: Tag, f: F) -> Result<(), Error>
+		where F: FnOnce(&mut Writer) -> Result<(), Error>
+	{
+		self.write_tag(Encoding::Constructed, tag);
+
+		// 9.1
+		if self.rules == Rules::Cer {
+			self.buf.push(INDEFINITE_LENGTH);
+			try!(f(self));
+			self.buf.extend_from_slice(&END_OF_CONTENTS);
+			return Ok(());
+		}
+
+		let start = self.buf.len();
+		try!(f(self));
+
+		// The length goes in front of the body, which is only now known
+		let body = self.buf.split_off(start);
+		self.write_length(body.len());
+		self.buf.extend_from_slice(&body);
+
+		Ok(())
+	}
+
+	/// Like `constructed`, but orders the children by their tags, as
+	/// required of the components of a SET (9.3 and 10.3). Untagged CHOICE
+	/// components are ordered by the tag of the chosen alternative, which is
+	/// what DER requires, but CER orders them by the smallest tag of the
+	/// CHOICE (9.3), which is not supported.
+	pub fn set<F>(&mut self, tag: Tag, f: F) -> Result<(), Error>
+		where F: FnOnce(&mut Writer) -> Result<(), Error>
+	{
+		let mut children = Writer::with_rules(self.rules);
+		try!(f(&mut children));
+
+		let mut starts = Vec::new();
+		for token in Parser::with_rules(&children.buf, Rules::Ber) {
+			let token = try!(token);
+
+			if token.depth == 0 {
+				starts.push((token.tag, token.offset));
+			}
+		}
+
+		let mut components = Vec::new();
+		for (i, &(order, start)) in starts.iter().enumerate() {
+			let end = starts.get(i + 1).map_or(children.buf.len(), |&(_, next)| next);
+			components.push((order, start, end));
+		}
+
+		components.sort_by_key(|&(order, _, _)| order);
+
+		self.constructed(tag, |w| {
+			for &(_, start, end) in &components {
+				w.buf.extend_from_slice(&children.buf[start..end]);
+			}
+
+			Ok(())
+		})
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.buf
+	}
+
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.buf
+	}
+
+	// 9.2
+	// Splits a string into fragments of 1000 octets, which keep the
+	// universal tag of implicitly tagged strings. The first octet of a
+	// bitstring holds the number of unused bits, which is repeated in every
+	// fragment.
+	fn fragments(&mut self, tag: Tag, universal: Tag, body: &[u8]) {
+		let (fragment, data, unused) = match universal {
+			Tag::Bitstring => (Tag::Bitstring, &body[1..], Some(body[0])),
+			_              => (Tag::Octetstring, body, None)
+		};
+
+		let chunk_len = match unused {
+			Some(_) => FRAGMENT_LENGTH - 1,
+			None    => FRAGMENT_LENGTH
+		};
+
+		self.write_tag(Encoding::Constructed, tag);
+		self.buf.push(INDEFINITE_LENGTH);
+
+		let chunks = data.chunks(chunk_len).count();
+
+		for (i, chunk) in data.chunks(chunk_len).enumerate() {
+			self.write_tag(Encoding::Primitive, fragment);
+
+			match unused {
+				Some(unused) => {
+					// Only the last fragment may have unused bits (8.6.4)
+					let unused = if i == chunks - 1 { unused } else { 0 };
+
+					self.write_length(chunk.len() + 1);
+					self.buf.push(unused);
+				},
+				None => self.write_length(chunk.len())
+			}
+
+			self.buf.extend_from_slice(chunk);
+		}
+
+		self.buf.extend_from_slice(&END_OF_CONTENTS);
+	}
+
+	// 8.1.2
+	fn write_tag(&mut self, enc: Encoding, tag: Tag) {
+		let class = match tag.class() {
+			Class::Universal   => 0,
+			Class::Application => 1,
+			Class::Context     => 2,
+			Class::Private     => 3,
+		};
+
+		let encoding = match enc {
+			Encoding::Primitive   => 0,
+			Encoding::Constructed => 1,
+		};
+
+		let leading = (class << 6) | (encoding << 5);
+
+		match tag.id() {
+			id if id < MULTIPART_ID => self.buf.push(leading | id as u8),
+			id => {
+				self.buf.push(leading | MULTIPART_ID as u8);
+				self.write_multipart_tag(id);
+			}
+		}
+	}
+
+	// 8.1.2.4.2
+	fn write_multipart_tag(&mut self, id: usize) {
+		let mut groups = Vec::new();
+		let mut rest = id;
+
+		while rest > 0 {
+			groups.push((rest & 0b01111111) as u8);
+			rest >>= 7;
+		}
+
+		// All but the last octet have bit 8 set
+		for (i, group) in groups.iter().enumerate().rev() {
+			match i {
+				0 => self.buf.push(*group),
+				_ => self.buf.push(*group | 0b10000000)
+			}
+		}
+	}
+
+	// 8.1.3 and 10.1
+	fn write_length(&mut self, length: usize) {
+		if length < 128 {
+			self.buf.push(length as u8);
+			return;
+		}
+
+		let mut octets = Vec::new();
+		let mut rest = length;
+
+		while rest > 0 {
+			octets.push((rest & 0xFF) as u8);
+			rest >>= 8;
+		}
+
+		self.buf.push(0b10000000 | octets.len() as u8);
+		self.buf.extend(octets.iter().rev());
+	}
+}
+
+impl Default for Writer {
+	fn default() -> Writer {
+		Writer::new()
+	}
+}
+
+/// Values written as a token and its descendants, such as the structures
+/// generated by `#[derive(Encode)]` from moccasin-derive.
+pub trait Encode {
+	/// Writes the value, replacing its own tag with `tag` if given
+	/// (implicit tagging, 8.14).
+	fn encode(&self, tag: Option<Tag>, w: &mut Writer) -> Result<(), Error>;
+}
+
+impl<T> Encode for T
+	where T: ToToken
+{
+	fn encode(&self, tag: Option<Tag>, w: &mut Writer) -> Result<(), Error> {
+		let body = try!(self.to_der());
+		w.implicit(tag.unwrap_or(self.tag()), self.tag(), &body);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests;
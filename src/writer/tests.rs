@@ -0,0 +1,291 @@
+use {Parser, Token, Rules, Error};
+use Encoding::*;
+use Class::*;
+use Tag::*;
+use super::{Writer, Encode};
+use dsl::Matcher;
+
+#[test]
+fn encode_tag()
+{
+	let cases = [
+		(Composed(Private, 0b1111), &[0b11001111u8, 0x00] as &[u8]),
+		(Bool, &[0b00000001u8, 0x00]),
+		(Composed(Universal, 0b11111111), &[0b00011111u8, 0b10000001, 0b01111111, 0x00]),
+		(Composed(Context, 31), &[0b10011111u8, 0b00011111, 0x00]),
+	];
+
+	for case in &cases {
+		let mut w = Writer::new();
+		w.primitive(case.0, &[]);
+
+		assert_eq!(w.as_bytes(), case.1);
+	}
+}
+
+#[test]
+fn encode_length()
+{
+	let cases = [
+		(0usize, &[0x00u8] as &[u8]),
+		(127, &[0x7F]),
+		(128, &[0x81, 0x80]),
+		(255, &[0x81, 0xFF]),
+		(256, &[0x82, 0x01, 0x00]),
+		(70000, &[0x83, 0x01, 0x11, 0x70]),
+	];
+
+	for case in &cases {
+		let body = vec![0u8; case.0];
+
+		let mut w = Writer::new();
+		w.primitive(Octetstring, &body);
+
+		let bytes = w.into_bytes();
+		assert_eq!(bytes[0], 0x04);
+		assert_eq!(&bytes[1..1 + case.1.len()], case.1);
+		assert_eq!(bytes.len(), 1 + case.1.len() + case.0);
+	}
+}
+
+#[test]
+fn nested() {
+	let mut w = Writer::new();
+
+	w.constructed(Sequence, |w| {
+		w.primitive(Int, &[0x05]);
+
+		try!(w.constructed(Composed(Context, 3), |w| {
+			w.primitive(Octetstring, &[0xAB; 200]);
+			Ok(())
+		}));
+
+		w.primitive(Null, &[]);
+		Ok(())
+	}).unwrap();
+
+	let bytes = w.into_bytes();
+	let tokens: Vec<Token> = Parser::new(&bytes).map(|t| t.unwrap()).collect();
+
+	let expected = [
+		(Constructed, Sequence,             0, &[0x30u8, 0x81, 0xD3] as &[u8]),
+		(Primitive,   Int,                  1, &[0x02, 0x01]),
+		(Constructed, Composed(Context, 3), 1, &[0xA3, 0x81, 0xCB]),
+		(Primitive,   Octetstring,          2, &[0x04, 0x81, 0xC8]),
+		(Primitive,   Null,                 1, &[0x05, 0x00]),
+	];
+
+	assert_eq!(tokens.len(), expected.len());
+
+	for (token, expected) in tokens.iter().zip(expected.iter()) {
+		assert_eq!(token.enc,    expected.0);
+		assert_eq!(token.tag,    expected.1);
+		assert_eq!(token.depth,  expected.2);
+		assert_eq!(token.header, expected.3);
+	}
+}
+
+#[test]
+fn propagate_error() {
+	let mut w = Writer::new();
+
+	let result = w.constructed(Sequence, |_| Err(Error::OutOfMemory));
+	assert_eq!(result, Err(Error::OutOfMemory));
+}
+
+fn rewrite(w: &mut Writer, tokens: &[Token], i: &mut usize) -> Result<(), Error> {
+	let token = &tokens[*i];
+	*i += 1;
+
+	match token.enc {
+		Primitive   => Ok(w.primitive(token.tag, token.body)),
+		Constructed => w.constructed(token.tag, |w| {
+			while *i < tokens.len() && tokens[*i].depth > token.depth {
+				try!(rewrite(w, tokens, i));
+			}
+			Ok(())
+		})
+	}
+}
+
+#[test]
+fn x509_google() {
+	let data = include_bytes!("../../tests/certs/google.der");
+	let tokens: Vec<Token> = Parser::new(data).map(|t| t.unwrap()).collect();
+
+	let mut w = Writer::new();
+	rewrite(&mut w, &tokens, &mut 0).unwrap();
+
+	assert_eq!(w.as_bytes(), &data[..]);
+}
+
+#[test]
+fn tag_roundtrip() {
+	let tags = [
+		Bool, Int, Bitstring, Octetstring, Null, Oid, ObjectDescriptor,
+		External, Real, Enumerated, EmbeddedPdv, Utf8String, RelativeOid, Time,
+		Sequence, Set, NumericString, PrintableString, T61String, Ia5String,
+		UtcTime, GeneralizedTime, GraphicString, VisibleString,
+		UniversalString, BmpString, Date, TimeOfDay, DateTime, Duration,
+		OidIri, RelativeOidIri, Composed(Application, 1234),
+		Composed(Universal, 27)
+	];
+
+	for tag in &tags {
+		let mut w = Writer::new();
+		w.primitive(*tag, &[]);
+
+		let bytes = w.into_bytes();
+		let token = Parser::new(&bytes).next().unwrap().unwrap();
+		assert_eq!(token.tag, *tag);
+		assert_eq!(token.tag.class(), tag.class());
+	}
+}
+
+#[test]
+fn write_types() {
+	use types::{Int, Bool, Null, Oid, String, TokenType};
+
+	let oid = Oid::from_arcs(&[1, 2, 840, 113549]).unwrap();
+	let mut w = Writer::new();
+
+	w.constructed(Sequence, |w| {
+		try!(w.write(&Int(-129i32)));
+		try!(w.write(&Bool(true)));
+		try!(w.write(&Null));
+		try!(w.write(&oid));
+		w.write(&String("moccasin".into()))
+	}).unwrap();
+
+	let bytes = w.into_bytes();
+	let mut p = Parser::new(&bytes);
+
+	assert_eq!(p.next().unwrap().unwrap().tag, Sequence);
+	assert_eq!(Int::<i32>::from_token(&p.next().unwrap().unwrap()).unwrap(), Int(-129));
+	assert_eq!(Bool::from_token(&p.next().unwrap().unwrap()).unwrap(), Bool(true));
+	assert_eq!(Null::from_token(&p.next().unwrap().unwrap()).unwrap(), Null);
+	assert_eq!(Oid::from_token(&p.next().unwrap().unwrap()).unwrap(), oid);
+	assert_eq!(String::from_token(&p.next().unwrap().unwrap()).unwrap(), "moccasin");
+	assert!(p.next().is_none());
+}
+
+#[test]
+fn cer_nested() {
+	let mut w = Writer::with_rules(Rules::Cer);
+
+	w.constructed(Sequence, |w| {
+		w.primitive(Int, &[0x05]);
+
+		w.constructed(Set, |w| {
+			w.primitive(Null, &[]);
+			Ok(())
+		})
+	}).unwrap();
+
+	assert_eq!(w.as_bytes(), &[
+		0x30, 0x80,
+			0x02, 0x01, 0x05,
+			0x31, 0x80,
+				0x05, 0x00,
+			0x00, 0x00,
+		0x00, 0x00
+	]);
+
+	let tokens: Vec<Token> = Parser::with_rules(w.as_bytes(), Rules::Cer).map(|t| t.unwrap()).collect();
+	assert_eq!(tokens.len(), 4);
+}
+
+#[test]
+fn cer_fragments() {
+	use types::{self, TokenType};
+
+	let data: Vec<u8> = (0..2500).map(|i| i as u8).collect();
+
+	let mut w = Writer::with_rules(Rules::Cer);
+	w.primitive(Octetstring, &data);
+
+	let bytes = w.into_bytes();
+	let token = Parser::with_rules(&bytes, Rules::Cer).next().unwrap().unwrap();
+
+	assert_eq!(token.enc, Constructed);
+	assert_eq!(token.segments(Octetstring).unwrap().iter().map(|s| s.body.len()).collect::<Vec<_>>(), vec![1000, 1000, 500]);
+	assert_eq!(&*types::Octetstring::from_token(&token).unwrap().0, &data[..]);
+
+	// Short strings remain primitive
+	let mut w = Writer::with_rules(Rules::Cer);
+	w.primitive(Octetstring, &data[..1000]);
+	assert_eq!(&w.as_bytes()[..4], &[0x04, 0x82, 0x03, 0xE8]);
+
+	let mut body = vec![0x04];
+	body.extend_from_slice(&data[..1500]);
+	body[1500] = 0xF0;
+
+	let mut w = Writer::with_rules(Rules::Cer);
+	w.primitive(Bitstring, &body);
+
+	let bytes = w.into_bytes();
+	let token = Parser::with_rules(&bytes, Rules::Cer).next().unwrap().unwrap();
+	let segments = token.segments(Bitstring).unwrap();
+
+	assert_eq!(segments.len(), 2);
+	assert_eq!(segments[0].body.len(), 1000);
+	assert_eq!(segments[0].body[0], 0);
+	assert_eq!(segments[1].body[0], 4);
+
+	let bs = types::Bitstring::from_token(&token).unwrap();
+	assert_eq!(bs.len_bits(), 1500 * 8 - 4);
+
+	// Implicitly tagged strings are split by their universal tag
+	let text: ::std::string::String = (0..1500).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+
+	let mut w = Writer::with_rules(Rules::Cer);
+	types::String(text[..].into()).encode(Some(Composed(Context, 0)), &mut w).unwrap();
+
+	let bytes = w.into_bytes();
+	assert_eq!(&bytes[..4], &[0xA0, 0x80, 0x04, 0x82]);
+
+	let mut p = Parser::with_rules(&bytes, Rules::Cer).peekable();
+	let matcher = Matcher::implicit(0, Composed(Context, 0), Ia5String);
+	assert_eq!(matcher.required::<_, types::String>(&mut p).unwrap().0, text);
+}
+
+#[test]
+fn set_order() {
+	for rules in &[Rules::Der, Rules::Cer] {
+		let mut w = Writer::with_rules(*rules);
+
+		w.set(Set, |w| {
+			w.primitive(Composed(Context, 1), &[0x01]);
+			try!(w.constructed(Sequence, |w| {
+				w.primitive(Null, &[]);
+				Ok(())
+			}));
+			w.primitive(Composed(Private, 0), &[]);
+			w.primitive(Composed(Universal, 17), &[]);
+			w.primitive(Composed(Context, 0), &[0x00]);
+			w.primitive(Int, &[0x05]);
+			Ok(())
+		}).unwrap();
+
+		let bytes = w.into_bytes();
+		let tags = Parser::with_rules(&bytes, *rules)
+			.map(|t| t.unwrap())
+			.filter(|t| t.depth == 1)
+			.map(|t| t.tag)
+			.collect::<Vec<_>>();
+
+		assert_eq!(tags, vec![Int, Sequence, Set, Composed(Context, 0), Composed(Context, 1), Composed(Private, 0)]);
+	}
+}
+
+#[test]
+fn encode_implicit() {
+	use types::Bool;
+	use Encode;
+
+	let mut w = Writer::new();
+	w.encode(&Bool(true)).unwrap();
+	Bool(false).encode(Some(Composed(Context, 2)), &mut w).unwrap();
+
+	assert_eq!(w.as_bytes(), &[0x01, 0x01, 0xFF, 0x82, 0x01, 0x00]);
+}